@@ -0,0 +1,301 @@
+use crate::parser::WordPart;
+
+/// Most words a brace expression expands to, a larger one is left alone.
+/// More words would not fit in the arguments of a command anyway.
+const MAX_WORDS: usize = 1 << 17;
+
+/// A piece of a word during brace expansion.
+/// Only unquoted characters can be braces or commas, any other part is copied as it is.
+#[derive(Debug, Clone)]
+enum Piece {
+    Char(char),
+    Part(WordPart),
+}
+
+/// Expands the brace expressions of a word, returning the parts of the resulting words in order.
+/// Supports lists (`{a,b,c}`), sequences (`{1..10}`, `{01..10..2}`, `{a..e}`) and nesting.
+/// Quoted and escaped braces, and braces in expansions, are left alone,
+/// as are braces without a top-level `,` or a valid sequence,
+/// and braces that would expand to more than `MAX_WORDS` words.
+pub fn expand(parts: &[WordPart]) -> Vec<Vec<WordPart>> {
+    let mut pieces = Vec::new();
+    for part in parts {
+        match part {
+            WordPart::Literal(text) => pieces.extend(text.chars().map(Piece::Char)),
+            part => pieces.push(Piece::Part(part.clone())),
+        }
+    }
+
+    expand_pieces(&pieces)
+        .into_iter()
+        .map(|pieces| into_parts(&pieces))
+        .collect()
+}
+
+/// Expands the first valid brace expression of a word and the words that result from it.
+fn expand_pieces(pieces: &[Piece]) -> Vec<Vec<Piece>> {
+    let opening = pieces
+        .iter()
+        .enumerate()
+        .filter(|(_, piece)| matches!(piece, Piece::Char('{')))
+        .map(|(i, _)| i);
+
+    for open in opening {
+        if let Some((close, alternatives)) = parse_brace(pieces, open) {
+            // the alternatives and the suffix may contain more braces.
+            let mut words = Vec::new();
+            for alternative in alternatives {
+                let mut word = pieces[..open].to_vec();
+                word.extend(alternative);
+                word.extend_from_slice(&pieces[close + 1..]);
+
+                words.extend(expand_pieces(&word));
+                if words.len() > MAX_WORDS {
+                    return vec![pieces.to_vec()];
+                }
+            }
+            return words;
+        }
+    }
+
+    vec![pieces.to_vec()]
+}
+
+/// Parses the brace expression opened at `open`.
+/// Returns the position of the closing `}` and the alternatives,
+/// or `None` if the braces are not a list or a sequence.
+fn parse_brace(pieces: &[Piece], open: usize) -> Option<(usize, Vec<Vec<Piece>>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut i = open + 1;
+
+    let close = loop {
+        match pieces.get(i)? {
+            Piece::Char('{') => depth += 1,
+            Piece::Char('}') if depth == 0 => break i,
+            Piece::Char('}') => depth -= 1,
+            Piece::Char(',') if depth == 0 => commas.push(i),
+            _ => {}
+        }
+        i += 1;
+    };
+
+    if commas.is_empty() {
+        // a sequence is only made of unquoted characters.
+        let body = pieces[open + 1..close]
+            .iter()
+            .map(|piece| match piece {
+                Piece::Char(c) => Some(*c),
+                Piece::Part(_) => None,
+            })
+            .collect::<Option<String>>()?;
+        return sequence(&body).map(|items| (close, items));
+    }
+
+    let mut bounds = vec![open];
+    bounds.extend(commas);
+    bounds.push(close);
+
+    let alternatives = bounds
+        .windows(2)
+        .map(|pair| pieces[pair[0] + 1..pair[1]].to_vec())
+        .collect();
+    Some((close, alternatives))
+}
+
+/// Expands a sequence body like `1..10`, `01..10..2` or `a..e`.
+fn sequence(body: &str) -> Option<Vec<Vec<Piece>>> {
+    let parts: Vec<&str> = body.split("..").collect();
+    let (first, last, step) = match parts.as_slice() {
+        [first, last] => (*first, *last, 1),
+        [first, last, step] => (
+            *first,
+            *last,
+            step.parse::<i64>().ok()?.unsigned_abs().max(1),
+        ),
+        _ => return None,
+    };
+
+    if let (Ok(start), Ok(end)) = (first.parse::<i64>(), last.parse::<i64>()) {
+        // a leading zero on either end pads every number to the same width.
+        let padded = [first, last].iter().any(|n| {
+            n.trim_start_matches('-').len() > 1 && n.trim_start_matches('-').starts_with('0')
+        });
+        let width = if padded {
+            first.len().max(last.len())
+        } else {
+            0
+        };
+
+        let items = range(start, end, step)?
+            .map(|n| {
+                let text = if n < 0 {
+                    let width = width.saturating_sub(1);
+                    format!("-{:0width$}", n.unsigned_abs(), width = width)
+                } else {
+                    format!("{:0width$}", n, width = width)
+                };
+                text.chars().map(Piece::Char).collect()
+            })
+            .collect();
+        return Some(items);
+    }
+
+    let (mut first_chars, mut last_chars) = (first.chars(), last.chars());
+    match (
+        first_chars.next(),
+        first_chars.next(),
+        last_chars.next(),
+        last_chars.next(),
+    ) {
+        (Some(start), None, Some(end), None)
+            if start.is_ascii_alphabetic() && end.is_ascii_alphabetic() =>
+        {
+            let items = range(start as i64, end as i64, step)?
+                .map(|c| vec![letter(c as u8 as char)])
+                .collect();
+            Some(items)
+        }
+        _ => None,
+    }
+}
+
+/// Returns the values from `start` to `end` (inclusive) in steps of `step`, counting down if needed.
+/// Returns `None` if there are more than `MAX_WORDS` values.
+fn range(start: i64, end: i64, step: u64) -> Option<impl Iterator<Item = i64>> {
+    let count = (start.abs_diff(end) / step).checked_add(1)?;
+    if count > MAX_WORDS as u64 {
+        return None;
+    }
+
+    let next = move |n: &i64| {
+        if start <= end {
+            n.checked_add_unsigned(step)
+        } else {
+            n.checked_sub_unsigned(step)
+        }
+    };
+    Some(std::iter::successors(Some(start), next).take(count as usize))
+}
+
+/// Returns a character of a letter sequence, the ones that are not letters are quoted.
+fn letter(c: char) -> Piece {
+    if c.is_ascii_alphanumeric() {
+        Piece::Char(c)
+    } else {
+        Piece::Part(WordPart::Escaped(c))
+    }
+}
+
+/// Joins the pieces of a word into its parts again.
+fn into_parts(pieces: &[Piece]) -> Vec<WordPart> {
+    let mut parts = Vec::new();
+    for piece in pieces {
+        match (piece, parts.last_mut()) {
+            (Piece::Char(c), Some(WordPart::Literal(text))) => text.push(*c),
+            (Piece::Char(c), _) => parts.push(WordPart::Literal(c.to_string())),
+            (Piece::Part(part), _) => parts.push(part.clone()),
+        }
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{self, WordPart};
+
+    /// Expands the brace expressions of a raw word, returning the raw words.
+    fn expand(raw: &str) -> Vec<String> {
+        let word = parser::parse_word(raw).unwrap();
+        super::expand(&word.parts)
+            .iter()
+            .map(|parts| raw_text(parts))
+            .collect()
+    }
+
+    /// Writes parts back the way they appear in the source.
+    fn raw_text(parts: &[WordPart]) -> String {
+        parts
+            .iter()
+            .map(|part| match part {
+                WordPart::Literal(text)
+                | WordPart::Parameter(text)
+                | WordPart::CommandSubstitution(text) => text.clone(),
+                WordPart::Escaped(c) => format!("\\{}", c),
+                WordPart::SingleQuoted(text) => format!("'{}'", text),
+                WordPart::DoubleQuoted(parts) => format!("\"{}\"", raw_text(parts)),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_list() {
+        assert_eq!(expand("a{b,c}d"), ["abd", "acd"]);
+        assert_eq!(expand("{a,b}{1,2}"), ["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand("x{,y}"), ["x", "xy"]);
+        assert_eq!(expand("{a,b{1,2},c}"), ["a", "b1", "b2", "c"]);
+    }
+
+    #[test]
+    fn test_sequence() {
+        assert_eq!(expand("{1..5}"), ["1", "2", "3", "4", "5"]);
+        assert_eq!(expand("{3..1}"), ["3", "2", "1"]);
+        assert_eq!(expand("{01..10..3}"), ["01", "04", "07", "10"]);
+        assert_eq!(expand("{-2..2..2}"), ["-2", "0", "2"]);
+        assert_eq!(expand("{a..e..2}"), ["a", "c", "e"]);
+        // characters between the letters are quoted, so they are not pattern characters.
+        assert_eq!(
+            expand("{Z..a}"),
+            ["Z", "\\[", "\\\\", "\\]", "\\^", "\\_", "\\`", "a"]
+        );
+        assert_eq!(expand("f{1..2}.txt"), ["f1.txt", "f2.txt"]);
+
+        // the bounds may be the largest numbers, even with a step that goes past them.
+        let (min, max) = (i64::MIN, i64::MAX);
+        assert_eq!(
+            expand(&format!("{{{}..{}..{}}}", min, max, max)),
+            [
+                min.to_string(),
+                "-1".to_string(),
+                "9223372036854775806".to_string()
+            ]
+        );
+        assert_eq!(
+            expand(&format!("{{{}..{}}}", max, max - 1)),
+            [max.to_string(), (max - 1).to_string()]
+        );
+        assert_eq!(expand(&format!("{{{}..{}}}", min, min)), [min.to_string()]);
+        assert_eq!(expand("{-05..-3}"), ["-05", "-04", "-03"]);
+    }
+
+    #[test]
+    fn test_not_expanded() {
+        for word in [
+            "{a}",
+            "{}",
+            "{a,b",
+            "'{a,b}'",
+            "\"{a,b}\"",
+            "\\{a,b}",
+            "${x}",
+            "${x:-{a,b}}",
+            "$(echo {a,b})",
+            "{1..a}",
+            "{aa..b}",
+            "{1..99999999999}",
+            "{-9223372036854775808..9223372036854775807}",
+            "{1..131073}",
+            "{1,2}{1..131072}",
+        ] {
+            assert_eq!(expand(word), [word], "{}", word);
+        }
+    }
+
+    #[test]
+    fn test_quoted_alternatives() {
+        assert_eq!(expand("{'a b',c}"), ["'a b'", "c"]);
+        assert_eq!(expand("{a,'}'}"), ["a", "'}'"]);
+        assert_eq!(expand("${x}{1,2}"), ["${x}1", "${x}2"]);
+        assert_eq!(expand("{a,$(echo b,c)}"), ["a", "$(echo b,c)"]);
+    }
+}
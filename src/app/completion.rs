@@ -0,0 +1,395 @@
+use std::path::Path;
+
+use is_executable::IsExecutable;
+
+use super::{graphemes, Variables};
+use crate::commands::CommandsRegistry;
+
+/// What the arguments of a command are completed with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgCompletion {
+    /// Files and directories.
+    Paths,
+    /// Directories only.
+    Directories,
+    /// Names of registered commands.
+    Commands,
+}
+
+/// Everything the completion engine needs to know about the shell.
+pub struct CompletionContext<'a> {
+    pub registry: &'a CommandsRegistry,
+    pub variables: &'a Variables,
+}
+
+/// The result of completing the word at the cursor.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Completion {
+    /// Byte offset of the start of the completed word.
+    pub start: usize,
+    /// Text that replaces the word up to the cursor, `None` if nothing can be inserted.
+    pub replacement: Option<String>,
+    /// Candidates to list when the completion is ambiguous.
+    pub candidates: Vec<String>,
+}
+
+/// A possible completion of a word.
+#[derive(Debug)]
+struct Candidate {
+    /// The whole completed word, without quoting.
+    text: String,
+    /// The name shown in the candidate list.
+    display: String,
+    /// Whether the candidate is a directory, so more can be completed after it.
+    is_dir: bool,
+}
+
+/// The word under the cursor, as far as it has been typed.
+#[derive(Debug, Default)]
+struct WordAtCursor {
+    /// Byte offset of the start of the word.
+    start: usize,
+    /// The word with quotes and escapes removed.
+    text: String,
+    /// The quote that is still open at the cursor.
+    quote: Option<char>,
+    /// The preceding words of the same command, with quotes and escapes removed.
+    previous_words: Vec<String>,
+}
+
+/// Completes the word that ends at byte offset `cursor` of `line`.
+/// The first word of a command is completed with command names, later words according to
+/// the `ArgCompletion` of the command.
+pub fn complete(line: &str, cursor: usize, context: &CompletionContext) -> Completion {
+    let word = word_at_cursor(&line[..cursor]);
+    let home = context.variables.get("HOME");
+
+    // assignments in front of a command are not part of it.
+    let mut command_words = word
+        .previous_words
+        .iter()
+        .skip_while(|w| Variables::parse_assignment(w).is_some());
+
+    let candidates = match command_words.next() {
+        None if word.text.contains('/') => complete_path(&word.text, home, |path| {
+            path.is_dir() || path.is_executable()
+        }),
+        None => complete_command(&word.text, context.registry),
+        Some(command_name) => {
+            let arg_completion = context
+                .registry
+                .get_command(command_name, context.variables.get("PATH"))
+                .map(|command| command.arg_completion())
+                .unwrap_or(ArgCompletion::Paths);
+
+            match arg_completion {
+                ArgCompletion::Paths => complete_path(&word.text, home, |_| true),
+                ArgCompletion::Directories => complete_path(&word.text, home, Path::is_dir),
+                ArgCompletion::Commands => complete_command(&word.text, context.registry),
+            }
+        }
+    };
+
+    let mut completion = Completion {
+        start: word.start,
+        replacement: None,
+        candidates: Vec::new(),
+    };
+
+    match candidates.as_slice() {
+        [] => {}
+        [candidate] => {
+            let finished = !candidate.is_dir;
+            completion.replacement = Some(quote_word(&candidate.text, word.quote, finished));
+        }
+        _ => {
+            let prefix = common_prefix(candidates.iter().map(|c| c.text.as_str()));
+            if prefix.len() > word.text.len() {
+                completion.replacement = Some(quote_word(prefix, word.quote, false));
+            } else {
+                completion.candidates = candidates.into_iter().map(|c| c.display).collect();
+            }
+        }
+    }
+
+    completion
+}
+
+/// Finds the start and the unquoted text of the last word of `line`.
+fn word_at_cursor(line: &str) -> WordAtCursor {
+    let mut word = WordAtCursor::default();
+    let mut in_word = false;
+    let mut chars = line.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        match (word.quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => word.quote = None,
+            (Some('"'), '\\') if chars.peek().is_some_and(|(_, c)| "$`\"\\".contains(*c)) => {
+                word.text.push(chars.next().unwrap().1);
+            }
+            (Some(_), _) => word.text.push(c),
+            (None, ' ' | '\t' | '<' | '>' | '|' | ';' | '&' | '(' | ')') => {
+                if in_word {
+                    word.previous_words.push(std::mem::take(&mut word.text));
+                    in_word = false;
+                }
+                // a control operator starts a new command.
+                if matches!(c, '|' | ';' | '&' | '(' | ')') {
+                    word.previous_words.clear();
+                }
+            }
+            (None, _) => {
+                if !in_word {
+                    word.start = i;
+                    in_word = true;
+                }
+                match c {
+                    '\'' | '"' => word.quote = Some(c),
+                    '\\' => {
+                        if let Some((_, escaped)) = chars.next() {
+                            word.text.push(escaped);
+                        }
+                    }
+                    _ => word.text.push(c),
+                }
+            }
+        }
+    }
+
+    if !in_word {
+        word.start = line.len();
+    }
+    word
+}
+
+/// Returns the registered command names that start with `prefix`.
+fn complete_command(prefix: &str, registry: &CommandsRegistry) -> Vec<Candidate> {
+    let mut names: Vec<&String> = registry
+        .registered_names
+        .iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    names.dedup();
+
+    names
+        .into_iter()
+        .map(|name| Candidate {
+            text: name.clone(),
+            display: name.clone(),
+            is_dir: false,
+        })
+        .collect()
+}
+
+/// Returns the paths that start with `prefix` and pass the `filter`.
+/// Directories are completed with a trailing `/`, dotfiles only if the prefix asks for them.
+fn complete_path(
+    prefix: &str,
+    home: Option<&str>,
+    filter: impl Fn(&Path) -> bool,
+) -> Vec<Candidate> {
+    let (dir, name_prefix) = match prefix.rfind('/') {
+        Some(i) => (&prefix[..=i], &prefix[i + 1..]),
+        None => ("", prefix),
+    };
+
+    let lookup_dir = match (dir, home) {
+        ("", _) => ".".to_string(),
+        (dir, Some(home)) if dir.starts_with("~/") => {
+            format!("{}{}", home, &dir[1..])
+        }
+        (dir, _) => dir.to_string(),
+    };
+
+    let Ok(entries) = std::fs::read_dir(&lookup_dir) else {
+        return Vec::new();
+    };
+
+    let mut candidates: Vec<Candidate> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(name_prefix)
+                || (name.starts_with('.') && !name_prefix.starts_with('.'))
+            {
+                return None;
+            }
+
+            let path = entry.path();
+            if !filter(&path) {
+                return None;
+            }
+
+            let is_dir = path.is_dir();
+            let suffix = if is_dir { "/" } else { "" };
+
+            Some(Candidate {
+                text: format!("{}{}{}", dir, name, suffix),
+                display: format!("{}{}", name, suffix),
+                is_dir,
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| a.text.cmp(&b.text));
+    candidates
+}
+
+/// Returns the longest common prefix of all `words`.
+fn common_prefix<'a>(mut words: impl Iterator<Item = &'a str>) -> &'a str {
+    let Some(first) = words.next() else {
+        return "";
+    };
+
+    words.fold(first, |prefix, word| {
+        let len = prefix
+            .char_indices()
+            .zip(word.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or(prefix.len().min(word.len()));
+        &prefix[..len]
+    })
+}
+
+/// Quotes a completed word so that the shell reads it back unchanged.
+/// An open quote is kept and closed if the word is `finished`, which also appends a space.
+fn quote_word(text: &str, quote: Option<char>, finished: bool) -> String {
+    let mut result = String::new();
+
+    match quote {
+        Some('\'') => {
+            result.push('\'');
+            result.push_str(&text.replace('\'', "'\\''"));
+        }
+        Some(q) => {
+            result.push(q);
+            for c in text.chars() {
+                if "$`\"\\".contains(c) {
+                    result.push('\\');
+                }
+                result.push(c);
+            }
+        }
+        None => {
+            for (i, c) in text.chars().enumerate() {
+                // a leading `~` has to stay unescaped to be expanded.
+                if " \t\n'\"\\|&;()<>$`*?[]{}!#".contains(c) || (c == '~' && i > 0) {
+                    result.push('\\');
+                }
+                result.push(c);
+            }
+        }
+    }
+
+    if finished {
+        if let Some(q) = quote {
+            result.push(q);
+        }
+        result.push(' ');
+    }
+
+    result
+}
+
+/// Lays out `items` in columns that fit into `width` terminal columns.
+/// Items are ordered top to bottom, then left to right.
+pub fn format_columns(items: &[String], width: usize) -> String {
+    let column_width = items
+        .iter()
+        .map(|item| graphemes::display_width(item))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let columns = (width / column_width).max(1);
+    let rows = items.len().div_ceil(columns);
+
+    let mut output = String::new();
+    for row in 0..rows {
+        let mut line = String::new();
+        for column in 0..columns {
+            if let Some(item) = items.get(column * rows + row) {
+                // `format!` pads by chars, which is off for wide characters.
+                let padding = column_width - graphemes::display_width(item);
+                line.push_str(item);
+                line.push_str(&" ".repeat(padding));
+            }
+        }
+        output.push_str(line.trim_end());
+        output.push_str("\r\n");
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{common_prefix, format_columns, quote_word, word_at_cursor};
+
+    #[test]
+    fn test_word_at_cursor() {
+        let word = word_at_cursor("ls -la src/ma");
+        assert_eq!(word.start, 7);
+        assert_eq!(word.text, "src/ma");
+        assert_eq!(word.previous_words, vec!["ls", "-la"]);
+    }
+
+    #[test]
+    fn test_word_at_cursor_empty_word() {
+        let word = word_at_cursor("cd ");
+        assert_eq!(word.start, 3);
+        assert_eq!(word.text, "");
+        assert_eq!(word.previous_words, vec!["cd"]);
+    }
+
+    #[test]
+    fn test_word_at_cursor_after_operator() {
+        let word = word_at_cursor("echo a | gr");
+        assert_eq!(word.text, "gr");
+        assert!(word.previous_words.is_empty());
+    }
+
+    #[test]
+    fn test_word_at_cursor_quoted() {
+        let word = word_at_cursor("cat \"My Doc");
+        assert_eq!(word.start, 4);
+        assert_eq!(word.text, "My Doc");
+        assert_eq!(word.quote, Some('"'));
+
+        let word = word_at_cursor("cat My\\ Doc");
+        assert_eq!(word.text, "My Doc");
+        assert_eq!(word.quote, None);
+    }
+
+    #[test]
+    fn test_quote_word() {
+        assert_eq!(quote_word("My Documents/", None, false), "My\\ Documents/");
+        assert_eq!(quote_word("~/a b", None, true), "~/a\\ b ");
+        assert_eq!(quote_word("a b", Some('"'), true), "\"a b\" ");
+        assert_eq!(quote_word("it's", Some('\''), false), "'it'\\''s");
+    }
+
+    #[test]
+    fn test_common_prefix() {
+        assert_eq!(common_prefix(["format", "fork", "for"].into_iter()), "for");
+        assert_eq!(common_prefix(["abc"].into_iter()), "abc");
+        assert_eq!(common_prefix(["ab", "cd"].into_iter()), "");
+        assert_eq!(common_prefix(["äb", "äc"].into_iter()), "ä");
+    }
+
+    #[test]
+    fn test_format_columns() {
+        let items: Vec<String> = ["a", "bb", "c", "d", "e"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(format_columns(&items, 12), "a   c   e\r\nbb  d\r\n");
+        assert_eq!(format_columns(&items, 1), "a\r\nbb\r\nc\r\nd\r\ne\r\n");
+    }
+
+    #[test]
+    fn test_format_columns_wide_characters() {
+        let items: Vec<String> = ["日本", "a", "b"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(format_columns(&items, 12), "日本  b\r\na\r\n");
+    }
+}
@@ -1,3 +1,5 @@
+use std::io;
+
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -10,11 +12,59 @@ pub enum ShellError {
     #[error("{command_name}: command not found")]
     CommandNotFound { command_name: String },
     /// The command execution failed.
-    /// The message will be formatted as an error message (red color).
-    #[error("\x1b[31m{0}\x1b[0m")]
+    /// The message is shown in red if stderr is a terminal.
+    #[error("{0}")]
     CommandExecutionFail(String),
     /// The shell input could not be parsed.
-    /// The message will be formatted as an error message (red color).
-    #[error("\x1b[31m{0}\x1b[0m")]
+    /// The message is shown in red if stderr is a terminal.
+    #[error("{0}")]
     ParsingFail(String),
+    /// A word could not be expanded (e.g. a bad substitution like `${1a}`).
+    /// The message is shown in red if stderr is a terminal.
+    #[error("{0}")]
+    ExpansionFail(String),
+    /// `${VAR:?message}` found `VAR` unset or null, a shell that is not interactive exits.
+    /// The message is shown in red if stderr is a terminal.
+    #[error("{0}")]
+    ParameterNotSet(String),
+}
+
+impl ShellError {
+    /// Returns the exit status reported for a command that failed with this error.
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            ShellError::EmptyInput => 0,
+            ShellError::CommandNotFound { .. } => 127,
+            ShellError::ParsingFail(_) => 2,
+            ShellError::CommandExecutionFail(_)
+            | ShellError::ExpansionFail(_)
+            | ShellError::ParameterNotSet(_) => 1,
+        }
+    }
+
+    /// Creates the error of a builtin that failed to write its output, e.g. to an output closed with `>&-`.
+    pub fn write_error(command_name: &str, err: io::Error) -> Self {
+        ShellError::CommandExecutionFail(format!("{}: write error: {}", command_name, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShellError;
+
+    #[test]
+    fn test_exit_status() {
+        assert_eq!(ShellError::EmptyInput.exit_status(), 0);
+        let not_found = ShellError::CommandNotFound {
+            command_name: "nope".to_string(),
+        };
+        assert_eq!(not_found.exit_status(), 127);
+        assert_eq!(ShellError::ParsingFail(String::new()).exit_status(), 2);
+        assert_eq!(
+            ShellError::CommandExecutionFail(String::new()).exit_status(),
+            1
+        );
+        assert_eq!(ShellError::ExpansionFail(String::new()).exit_status(), 1);
+        assert_eq!(ShellError::ParameterNotSet(String::new()).exit_status(), 1);
+    }
 }
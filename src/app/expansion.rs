@@ -0,0 +1,1061 @@
+use std::ffi::{CStr, CString};
+
+use super::{brace, glob, pattern, variables::Variables, Shell, ShellError};
+use crate::parser::{self, WordPart};
+
+/// Field separators used when `IFS` is not set.
+const DEFAULT_IFS: &str = " \t\n";
+
+/// Characters that have a special meaning in shell patterns.
+const PATTERN_CHARS: &[char] = &['*', '?', '[', ']', '\\'];
+
+/// A single field produced by word expansion.
+#[derive(Debug, Default)]
+struct Field {
+    /// The final text of the field.
+    text: String,
+    /// The field as a shell pattern, with quoted pattern characters escaped.
+    pattern: String,
+}
+
+/// Accumulates the fields produced by expanding a single word.
+#[derive(Debug, Default)]
+struct Fields {
+    done: Vec<Field>,
+    current: Field,
+    /// Whether the current field exists, even if it is empty (e.g. after `""`).
+    started: bool,
+    /// Whether an unquoted separator was seen, so the next character starts a new field.
+    split_pending: bool,
+}
+
+impl Fields {
+    /// Marks the current field as existing, starting a new one if a split is pending.
+    fn start(&mut self) {
+        if self.split_pending {
+            self.finish();
+            self.split_pending = false;
+        }
+        self.started = true;
+    }
+
+    /// Appends a character to the current field.
+    fn push(&mut self, c: char, quoted: bool) {
+        self.start();
+        self.current.text.push(c);
+        if quoted && PATTERN_CHARS.contains(&c) {
+            self.current.pattern.push('\\');
+        }
+        self.current.pattern.push(c);
+    }
+
+    /// Appends a string to the current field.
+    fn push_str(&mut self, s: &str, quoted: bool) {
+        for c in s.chars() {
+            self.push(c, quoted);
+        }
+    }
+
+    /// Appends the result of an unquoted expansion, splitting it into fields on `ifs` characters.
+    fn push_split(&mut self, value: &str, ifs: &str) {
+        for c in value.chars() {
+            if !ifs.contains(c) {
+                self.push(c, false);
+            } else if c.is_whitespace() {
+                self.split_pending = true;
+            } else {
+                // a non-whitespace separator always delimits a field, even an empty one.
+                self.started = true;
+                self.finish();
+                self.split_pending = false;
+            }
+        }
+    }
+
+    /// Completes the current field if it exists.
+    fn finish(&mut self) {
+        if self.started {
+            self.done.push(std::mem::take(&mut self.current));
+            self.started = false;
+        }
+    }
+
+    fn into_fields(mut self) -> Vec<Field> {
+        self.finish();
+        self.done
+    }
+}
+
+/// Operators of the `${name<op>word}` parameter expansion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamOp {
+    /// `-` / `:-`, use the word if the parameter is unset (or null).
+    Default,
+    /// `=` / `:=`, assign the word if the parameter is unset (or null).
+    Assign,
+    /// `?` / `:?`, fail with the word as message if the parameter is unset (or null).
+    Error,
+    /// `+` / `:+`, use the word if the parameter is set (and not null).
+    Alternative,
+    /// `#`, remove the shortest matching prefix.
+    RemoveShortestPrefix,
+    /// `##`, remove the longest matching prefix.
+    RemoveLongestPrefix,
+    /// `%`, remove the shortest matching suffix.
+    RemoveShortestSuffix,
+    /// `%%`, remove the longest matching suffix.
+    RemoveLongestSuffix,
+    /// `/`, replace the first match.
+    ReplaceFirst,
+    /// `//`, replace every match.
+    ReplaceAll,
+    /// `/#`, replace a match at the start.
+    ReplacePrefix,
+    /// `/%`, replace a match at the end.
+    ReplaceSuffix,
+}
+
+impl ParamOp {
+    /// Operators ordered so that longer ones are tried first.
+    const ALL: [(&'static str, ParamOp); 12] = [
+        ("-", ParamOp::Default),
+        ("=", ParamOp::Assign),
+        ("?", ParamOp::Error),
+        ("+", ParamOp::Alternative),
+        ("##", ParamOp::RemoveLongestPrefix),
+        ("#", ParamOp::RemoveShortestPrefix),
+        ("%%", ParamOp::RemoveLongestSuffix),
+        ("%", ParamOp::RemoveShortestSuffix),
+        ("//", ParamOp::ReplaceAll),
+        ("/#", ParamOp::ReplacePrefix),
+        ("/%", ParamOp::ReplaceSuffix),
+        ("/", ParamOp::ReplaceFirst),
+    ];
+
+    /// Parses the operator at the start of `rest`.
+    /// Returns the operator, whether it had a `:` prefix and the remaining word.
+    fn parse(rest: &[char]) -> Option<(ParamOp, bool, &[char])> {
+        let (colon, rest) = match rest.first() {
+            Some(':') => (true, &rest[1..]),
+            _ => (false, rest),
+        };
+
+        ParamOp::ALL.iter().find_map(|(text, op)| {
+            let len = text.chars().count();
+            let matches = rest.len() >= len && rest[..len].iter().copied().eq(text.chars());
+            // only the first four operators accept the `:` prefix.
+            let colon_allowed = matches!(
+                op,
+                ParamOp::Default | ParamOp::Assign | ParamOp::Error | ParamOp::Alternative
+            );
+            (matches && (!colon || colon_allowed)).then(|| (*op, colon, &rest[len..]))
+        })
+    }
+}
+
+/// Which unquoted text is split into fields during expansion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Splitting {
+    /// Nothing is split (e.g. assignment values).
+    None,
+    /// Results of expansions are split.
+    Expansions,
+    /// Results of expansions and literal text are split.
+    Everything,
+}
+
+/// The result of a parameter expansion.
+enum ParamValue {
+    /// The expanded value.
+    Text(String),
+    /// The positional parameters of `$@`, they stay separate fields even inside double quotes.
+    Fields(Vec<String>),
+    /// The parts of a word that still have to be expanded in place (e.g. the default of `${name:-word}`).
+    Word(Vec<WordPart>),
+}
+
+impl Shell {
+    /// Expands the parts of a word into a list of fields.
+    /// Expands braces, removes quotes and escapes, expands a leading tilde,
+    /// substitutes parameters outside of single quotes,
+    /// splits the results of unquoted expansions on `IFS` characters
+    /// and replaces fields with unquoted wildcards by the matching paths.
+    pub(super) fn expand_word(&mut self, parts: &[WordPart]) -> Result<Vec<String>, ShellError> {
+        let mut fields = Fields::default();
+        for parts in brace::expand(parts) {
+            self.expand_parts(&parts, &mut fields, Splitting::Expansions, false)?;
+            fields.finish();
+        }
+
+        let mut words = Vec::new();
+        for field in fields.into_fields() {
+            if self.options.noglob || !glob::has_wildcards(&field.pattern) {
+                words.push(field.text);
+                continue;
+            }
+
+            let paths = glob::expand(&field.pattern, self.options.dotglob);
+            if !paths.is_empty() {
+                words.extend(paths);
+            } else if self.options.failglob {
+                return Err(ShellError::ExpansionFail(format!(
+                    "no match: {}",
+                    field.text
+                )));
+            } else if !self.options.nullglob {
+                words.push(field.text);
+            }
+        }
+
+        Ok(words)
+    }
+
+    /// Expands the parts of a word into a single string, without field splitting.
+    pub(super) fn expand_word_to_string(
+        &mut self,
+        parts: &[WordPart],
+    ) -> Result<String, ShellError> {
+        self.expand_to_field(parts).map(|field| field.text)
+    }
+
+    /// Expands the parts of a word into a shell pattern, quoted pattern characters match literally.
+    pub(super) fn expand_pattern(&mut self, parts: &[WordPart]) -> Result<String, ShellError> {
+        self.expand_to_field(parts).map(|field| field.pattern)
+    }
+
+    /// Expands the body of a here-document.
+    /// Parameters are substituted like inside double quotes, but double quotes themselves are literal.
+    pub(super) fn expand_here_document(&mut self, body: &str) -> Result<String, ShellError> {
+        let chars: Vec<char> = body.chars().collect();
+        let mut text = String::new();
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+
+            match c {
+                '\\' => match chars.get(i) {
+                    // an escaped newline joins the lines.
+                    Some('\n') => i += 1,
+                    Some(&next) if matches!(next, '$' | '`' | '\\') => {
+                        text.push(next);
+                        i += 1;
+                    }
+                    _ => text.push('\\'),
+                },
+                '`' => {
+                    let (output, next) = self.backquote_substitution(&chars, i)?;
+                    text.push_str(&output);
+                    i = next;
+                }
+                '$' => {
+                    let (value, next) = self.expand_parameter(&chars, i)?;
+                    i = next;
+
+                    match value {
+                        Some(ParamValue::Text(value)) => text.push_str(&value),
+                        Some(ParamValue::Fields(values)) => text.push_str(&values.join(" ")),
+                        Some(ParamValue::Word(word)) => {
+                            text.push_str(&self.expand_to_field(&word)?.text)
+                        }
+                        None => text.push('$'),
+                    }
+                }
+                _ => text.push(c),
+            }
+        }
+
+        Ok(text)
+    }
+
+    /// Expands the parts of a word into a single field.
+    fn expand_to_field(&mut self, parts: &[WordPart]) -> Result<Field, ShellError> {
+        let mut fields = Fields::default();
+        self.expand_parts(parts, &mut fields, Splitting::None, false)?;
+        Ok(fields.into_fields().pop().unwrap_or_default())
+    }
+
+    /// Expands the parts of a word into `fields`, splitting unquoted text as specified by `splitting`.
+    fn expand_parts(
+        &mut self,
+        parts: &[WordPart],
+        fields: &mut Fields,
+        splitting: Splitting,
+        in_double_quote: bool,
+    ) -> Result<(), ShellError> {
+        let ifs = self.variables.get("IFS").unwrap_or(DEFAULT_IFS).to_string();
+
+        for (i, part) in parts.iter().enumerate() {
+            match part {
+                WordPart::Literal(text) if in_double_quote => fields.push_str(text, true),
+                WordPart::Literal(text) => {
+                    let text = match i {
+                        0 => self.expand_tilde(text, parts.len() == 1, fields),
+                        _ => text,
+                    };
+                    if splitting == Splitting::Everything {
+                        fields.push_split(text, &ifs);
+                    } else {
+                        fields.push_str(text, false);
+                    }
+                }
+                WordPart::Escaped(c) => fields.push(*c, true),
+                WordPart::SingleQuoted(text) => {
+                    fields.start();
+                    fields.push_str(text, true);
+                }
+                // `"$@"` without positional parameters expands to no field at all.
+                WordPart::DoubleQuoted(inner)
+                    if self.positional_parameters.is_empty() && is_quoted_all_parameters(inner) => {
+                }
+                WordPart::DoubleQuoted(inner) => {
+                    fields.start();
+                    self.expand_parts(inner, fields, splitting, true)?;
+                }
+                WordPart::Parameter(text) => match self.parameter_part(text)? {
+                    Some(value) => {
+                        self.push_value(value, fields, splitting, in_double_quote, &ifs)?
+                    }
+                    // a parameter that is not supported is taken literally.
+                    None => fields.push_str(text, in_double_quote),
+                },
+                WordPart::CommandSubstitution(text) => {
+                    let output = self.command_substitution_part(text)?;
+                    self.push_value(
+                        ParamValue::Text(output),
+                        fields,
+                        splitting,
+                        in_double_quote,
+                        &ifs,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expands a tilde prefix at the start of the first, unquoted, part of a word.
+    /// The prefix is the text up to the first `/`, or the whole word if it is the only part.
+    /// Returns the rest of the text.
+    fn expand_tilde<'a>(&self, text: &'a str, only_part: bool, fields: &mut Fields) -> &'a str {
+        let Some(rest) = text.strip_prefix('~') else {
+            return text;
+        };
+        let prefix = match rest.find('/') {
+            Some(end) => &rest[..end],
+            None if only_part => rest,
+            // a quoted or expanded prefix is not a user name.
+            None => return text,
+        };
+
+        match self.tilde_directory(prefix) {
+            Some(dir) => {
+                fields.push_str(&dir, true);
+                &rest[prefix.len()..]
+            }
+            None => text,
+        }
+    }
+
+    /// Adds the value of a parameter expansion or command substitution to `fields`.
+    fn push_value(
+        &mut self,
+        value: ParamValue,
+        fields: &mut Fields,
+        splitting: Splitting,
+        in_double_quote: bool,
+        ifs: &str,
+    ) -> Result<(), ShellError> {
+        match value {
+            ParamValue::Text(value) if splitting != Splitting::None && !in_double_quote => {
+                fields.push_split(&value, ifs);
+            }
+            ParamValue::Text(value) => fields.push_str(&value, in_double_quote),
+            ParamValue::Fields(values) if in_double_quote => {
+                for (n, value) in values.iter().enumerate() {
+                    if n > 0 {
+                        fields.finish();
+                    }
+                    fields.start();
+                    fields.push_str(value, true);
+                }
+            }
+            ParamValue::Fields(values) if splitting == Splitting::None => {
+                fields.push_str(&values.join(" "), false);
+            }
+            ParamValue::Fields(values) => {
+                for value in values {
+                    fields.split_pending = true;
+                    fields.push_split(&value, ifs);
+                }
+            }
+            ParamValue::Word(word) if in_double_quote => {
+                let value = self.expand_to_field(&word)?;
+                fields.push_str(&value.text, true);
+            }
+            ParamValue::Word(word) if splitting == Splitting::None => {
+                self.expand_parts(&word, fields, Splitting::None, false)?;
+            }
+            // the word is itself the result of an expansion, so its literal text is split too.
+            ParamValue::Word(word) => {
+                self.expand_parts(&word, fields, Splitting::Everything, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Expands a `$name`, `${...}` or special parameter part.
+    /// Returns `None` for a parameter that is not supported.
+    fn parameter_part(&mut self, text: &str) -> Result<Option<ParamValue>, ShellError> {
+        let chars: Vec<char> = text.chars().collect();
+        if text.starts_with("${") {
+            // the lexer only ends a `${` at its closing brace.
+            let body = &chars[2..chars.len() - 1];
+            return self.expand_braced_parameter(body).map(Some);
+        }
+        self.expand_parameter(&chars, 1).map(|(value, _)| value)
+    }
+
+    /// Runs the command of a `$(...)` or `` `...` `` part and returns its output.
+    fn command_substitution_part(&mut self, text: &str) -> Result<String, ShellError> {
+        let command = match text.strip_prefix("$(") {
+            Some(rest) => rest.strip_suffix(')').unwrap_or(rest).to_string(),
+            None => {
+                let chars: Vec<char> = text.chars().collect();
+                backquoted_command(&chars, 1).map_or_else(String::new, |(command, _)| command)
+            }
+        };
+        self.command_substitution(&command)
+    }
+
+    /// Returns the directory of a tilde prefix: the home directory for `~` and `~user`,
+    /// the current directory for `~+` and the previous one for `~-`.
+    /// Returns `None` if the directory is unknown.
+    fn tilde_directory(&self, prefix: &str) -> Option<String> {
+        match prefix {
+            "" => self
+                .variables
+                .get("HOME")
+                .map(str::to_string)
+                .or_else(|| home_directory(None)),
+            "+" => self.variables.get("PWD").map(str::to_string),
+            "-" => self.variables.get("OLDPWD").map(str::to_string),
+            user => home_directory(Some(user)),
+        }
+    }
+
+    /// Expands the parameter that follows a `$` at position `start`.
+    /// Returns the expanded value (`None` if the `$` is literal) and the position after the expansion.
+    fn expand_parameter(
+        &mut self,
+        chars: &[char],
+        start: usize,
+    ) -> Result<(Option<ParamValue>, usize), ShellError> {
+        match chars.get(start) {
+            Some('{') => {
+                let end = find_closing_brace(chars, start + 1).ok_or_else(|| {
+                    ShellError::ParsingFail("bad substitution: missing `}`".to_string())
+                })?;
+                let value = self.expand_braced_parameter(&chars[start + 1..end])?;
+                Ok((Some(value), end + 1))
+            }
+            Some('(') => {
+                let end = find_closing_paren(chars, start + 1).ok_or_else(|| {
+                    ShellError::ParsingFail("bad substitution: missing `)`".to_string())
+                })?;
+                let command: String = chars[start + 1..end].iter().collect();
+                let output = self.command_substitution(&command)?;
+                Ok((Some(ParamValue::Text(output)), end + 1))
+            }
+            Some('@') => {
+                let values = self.positional_parameters.clone();
+                Ok((Some(ParamValue::Fields(values)), start + 1))
+            }
+            Some(&c) if is_special_parameter(c) => {
+                let value = self.get_parameter(&c.to_string()).unwrap_or_default();
+                Ok((Some(ParamValue::Text(value)), start + 1))
+            }
+            Some(&c) if c.is_ascii_alphabetic() || c == '_' => {
+                let len = chars[start..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                    .count();
+                let name: String = chars[start..start + len].iter().collect();
+                let value = self.get_parameter(&name).unwrap_or_default();
+                Ok((Some(ParamValue::Text(value)), start + len))
+            }
+            _ => Ok((None, start)),
+        }
+    }
+
+    /// Runs the command of a `` `...` `` substitution whose body starts at `start`.
+    /// Returns the output and the position after the closing backquote.
+    fn backquote_substitution(
+        &mut self,
+        chars: &[char],
+        start: usize,
+    ) -> Result<(String, usize), ShellError> {
+        let (command, end) = backquoted_command(chars, start).ok_or_else(|| {
+            ShellError::ParsingFail("bad substitution: missing closing `".to_string())
+        })?;
+
+        let output = self.command_substitution(&command)?;
+        Ok((output, end))
+    }
+
+    /// Expands the body of a `${...}` parameter expansion.
+    fn expand_braced_parameter(&mut self, body: &[char]) -> Result<ParamValue, ShellError> {
+        let bad_substitution = || {
+            let body: String = body.iter().collect();
+            ShellError::ExpansionFail(format!("${{{}}}: bad substitution", body))
+        };
+
+        // `${#name}` is the length of the value.
+        if body.len() > 1 && body[0] == '#' {
+            let name: String = body[1..].iter().collect();
+            if !is_parameter_name(&name) {
+                return Err(bad_substitution());
+            }
+            let value = self.get_parameter(&name).unwrap_or_default();
+            return Ok(ParamValue::Text(value.chars().count().to_string()));
+        }
+
+        let name_len = match body.first() {
+            Some(c) if c.is_ascii_digit() => body.iter().take_while(|c| c.is_ascii_digit()).count(),
+            Some(&c) if is_special_parameter(c) => 1,
+            _ => body
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+                .count(),
+        };
+        let name: String = body[..name_len].iter().collect();
+        if !is_parameter_name(&name) {
+            return Err(bad_substitution());
+        }
+
+        let value = self.get_parameter(&name);
+        let rest = &body[name_len..];
+        if rest.is_empty() {
+            if name == "@" {
+                return Ok(ParamValue::Fields(self.positional_parameters.clone()));
+            }
+            return Ok(ParamValue::Text(value.unwrap_or_default()));
+        }
+
+        let (op, colon, word) = ParamOp::parse(rest).ok_or_else(bad_substitution)?;
+        let word: String = word.iter().collect();
+        let word = parser::parse_word(&word)
+            .map_err(|err| ShellError::ExpansionFail(format!("{}: {}", bad_substitution(), err)))?
+            .parts;
+        // with `:` a null value is treated like an unset one.
+        let is_set = match &value {
+            Some(value) => !colon || !value.is_empty(),
+            None => false,
+        };
+
+        let value = match op {
+            ParamOp::Default if is_set => value.unwrap_or_default(),
+            ParamOp::Default => return Ok(ParamValue::Word(word)),
+            ParamOp::Alternative if is_set => return Ok(ParamValue::Word(word)),
+            ParamOp::Alternative => String::new(),
+            ParamOp::Assign | ParamOp::Error if is_set => value.unwrap_or_default(),
+            ParamOp::Assign => {
+                if !Variables::is_valid_name(&name) {
+                    return Err(ShellError::ExpansionFail(format!(
+                        "${}: cannot assign in this way",
+                        name
+                    )));
+                }
+                let word = self.expand_to_field(&word)?.text;
+                self.variables.set(&name, word.clone());
+                word
+            }
+            ParamOp::Error => {
+                let message = self.expand_to_field(&word)?.text;
+                let message = if message.is_empty() {
+                    "parameter null or not set".to_string()
+                } else {
+                    message
+                };
+                return Err(ShellError::ParameterNotSet(format!(
+                    "{}: {}",
+                    name, message
+                )));
+            }
+            ParamOp::RemoveShortestPrefix
+            | ParamOp::RemoveLongestPrefix
+            | ParamOp::RemoveShortestSuffix
+            | ParamOp::RemoveLongestSuffix => {
+                let pattern = self.expand_to_field(&word)?.pattern;
+                remove_pattern(&value.unwrap_or_default(), &pattern, op)
+            }
+            ParamOp::ReplaceFirst
+            | ParamOp::ReplaceAll
+            | ParamOp::ReplacePrefix
+            | ParamOp::ReplaceSuffix => {
+                let (pattern, replacement) = split_unquoted(&word, '/');
+                let pattern = self.expand_to_field(&pattern)?.pattern;
+                let replacement = self.expand_to_field(&replacement)?.text;
+                replace_pattern(&value.unwrap_or_default(), &pattern, &replacement, op)
+            }
+        };
+
+        Ok(ParamValue::Text(value))
+    }
+
+    /// Returns the value of a variable or special parameter, `None` if it is unset.
+    fn get_parameter(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.last_status.to_string()),
+            "$" => Some(std::process::id().to_string()),
+            "0" => Some(self.shell_name.clone()),
+            "#" => Some(self.positional_parameters.len().to_string()),
+            "@" => Some(self.positional_parameters.join(" ")),
+            "*" => {
+                // the parameters are joined with the first character of `IFS`.
+                let ifs = self.variables.get("IFS").unwrap_or(DEFAULT_IFS);
+                let separator = ifs.chars().next().map(String::from).unwrap_or_default();
+                Some(self.positional_parameters.join(&separator))
+            }
+            _ if name.chars().all(|c| c.is_ascii_digit()) => {
+                let index: usize = name.parse().ok()?;
+                self.positional_parameters
+                    .get(index.checked_sub(1)?)
+                    .cloned()
+            }
+            _ => self.variables.get(name).map(str::to_string),
+        }
+    }
+}
+
+/// Looks up the home directory of a user in the password database, or of the current user.
+fn home_directory(user: Option<&str>) -> Option<String> {
+    let entry = unsafe {
+        match user {
+            Some(user) => {
+                let name = CString::new(user).ok()?;
+                libc::getpwnam(name.as_ptr())
+            }
+            None => libc::getpwuid(libc::getuid()),
+        }
+    };
+    if entry.is_null() {
+        return None;
+    }
+
+    let dir = unsafe { CStr::from_ptr((*entry).pw_dir) };
+    dir.to_str().ok().map(str::to_string)
+}
+
+/// Returns `true` for single-character special parameters like `$?`.
+fn is_special_parameter(c: char) -> bool {
+    matches!(c, '?' | '$' | '#' | '@' | '*') || c.is_ascii_digit()
+}
+
+/// Returns `true` if the parts between double quotes are only `$@` or `${@}`.
+fn is_quoted_all_parameters(parts: &[WordPart]) -> bool {
+    matches!(parts, [WordPart::Parameter(text)] if text == "$@" || text == "${@}")
+}
+
+/// Returns `true` if `name` is a variable name or a special parameter.
+fn is_parameter_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if is_special_parameter(c) => true,
+        (Some(c), _) if c.is_ascii_digit() => name.chars().all(|c| c.is_ascii_digit()),
+        _ => Variables::is_valid_name(name),
+    }
+}
+
+/// Finds the `}` that closes a `${` whose body starts at `start`.
+/// Quotes, escapes and nested expansions are skipped.
+fn find_closing_brace(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut in_single_quote = false;
+    let mut in_double_quote = false;
+    let mut i = start;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            '"' if !in_single_quote => in_double_quote = !in_double_quote,
+            '\\' if !in_single_quote => i += 1,
+            '$' if !in_single_quote && chars.get(i + 1) == Some(&'{') => {
+                depth += 1;
+                i += 1;
+            }
+            '}' if !in_single_quote && !in_double_quote => {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Returns the command of a `` `...` `` substitution whose body starts at `start`
+/// and the position after the closing backquote, or `None` if it is not closed.
+/// Inside backquotes a backslash only escapes `$`, `` ` `` and `\`.
+fn backquoted_command(chars: &[char], start: usize) -> Option<(String, usize)> {
+    let mut command = String::new();
+    let mut i = start;
+
+    loop {
+        match chars.get(i)? {
+            '`' => break,
+            '\\' if matches!(chars.get(i + 1), Some('$' | '`' | '\\')) => {
+                command.push(chars[i + 1]);
+                i += 1;
+            }
+            &c => command.push(c),
+        }
+        i += 1;
+    }
+
+    Some((command, i + 1))
+}
+
+/// Finds the `)` that closes a `$(` whose command starts at `start`.
+/// The command is tokenized like by the parser, so quoted parentheses and `case` patterns are skipped.
+fn find_closing_paren(chars: &[char], start: usize) -> Option<usize> {
+    let command: String = chars[start..].iter().collect();
+    let end = parser::command_substitution_end(&command)?;
+    Some(start + command[..end].chars().count())
+}
+
+/// Splits the parts of a word at the first `separator` that is not quoted or escaped.
+fn split_unquoted(parts: &[WordPart], separator: char) -> (Vec<WordPart>, Vec<WordPart>) {
+    for (i, part) in parts.iter().enumerate() {
+        let WordPart::Literal(text) = part else {
+            continue;
+        };
+        let Some((before, after)) = text.split_once(separator) else {
+            continue;
+        };
+
+        let mut first = parts[..i].to_vec();
+        if !before.is_empty() {
+            first.push(WordPart::Literal(before.to_string()));
+        }
+        let mut second = Vec::new();
+        if !after.is_empty() {
+            second.push(WordPart::Literal(after.to_string()));
+        }
+        second.extend_from_slice(&parts[i + 1..]);
+        return (first, second);
+    }
+
+    (parts.to_vec(), Vec::new())
+}
+
+/// Returns the byte offsets of every char boundary of `s`, including its end.
+fn char_boundaries(s: &str) -> Vec<usize> {
+    s.char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(s.len()))
+        .collect()
+}
+
+/// Removes the prefix or suffix of `value` that matches `pattern`.
+fn remove_pattern(value: &str, pattern: &str, op: ParamOp) -> String {
+    let boundaries = char_boundaries(value);
+
+    let found = match op {
+        ParamOp::RemoveShortestPrefix => boundaries
+            .iter()
+            .find(|&&i| pattern::matches(pattern, &value[..i]))
+            .map(|&i| &value[i..]),
+        ParamOp::RemoveLongestPrefix => boundaries
+            .iter()
+            .rev()
+            .find(|&&i| pattern::matches(pattern, &value[..i]))
+            .map(|&i| &value[i..]),
+        ParamOp::RemoveShortestSuffix => boundaries
+            .iter()
+            .rev()
+            .find(|&&i| pattern::matches(pattern, &value[i..]))
+            .map(|&i| &value[..i]),
+        ParamOp::RemoveLongestSuffix => boundaries
+            .iter()
+            .find(|&&i| pattern::matches(pattern, &value[i..]))
+            .map(|&i| &value[..i]),
+        _ => None,
+    };
+
+    found.unwrap_or(value).to_string()
+}
+
+/// Replaces the parts of `value` that match `pattern` with `replacement`.
+/// Matches are always the longest possible ones.
+fn replace_pattern(value: &str, pattern: &str, replacement: &str, op: ParamOp) -> String {
+    let boundaries = char_boundaries(value);
+    let end = value.len();
+
+    match op {
+        ParamOp::ReplacePrefix => {
+            match boundaries
+                .iter()
+                .rev()
+                .find(|&&j| pattern::matches(pattern, &value[..j]))
+            {
+                Some(&j) => format!("{}{}", replacement, &value[j..]),
+                None => value.to_string(),
+            }
+        }
+        ParamOp::ReplaceSuffix => {
+            match boundaries
+                .iter()
+                .find(|&&i| pattern::matches(pattern, &value[i..]))
+            {
+                Some(&i) => format!("{}{}", &value[..i], replacement),
+                None => value.to_string(),
+            }
+        }
+        _ => {
+            let mut result = String::new();
+            let mut b = 0;
+
+            while b < boundaries.len() {
+                let i = boundaries[b];
+                let longest_match = boundaries[b..]
+                    .iter()
+                    .rev()
+                    .find(|&&j| j > i && pattern::matches(pattern, &value[i..j]));
+
+                match longest_match {
+                    Some(&j) => {
+                        result.push_str(replacement);
+                        if op == ParamOp::ReplaceFirst {
+                            result.push_str(&value[j..]);
+                            return result;
+                        }
+                        b = boundaries.iter().position(|&k| k == j).unwrap();
+                    }
+                    None if i < end => {
+                        result.push_str(&value[i..boundaries[b + 1]]);
+                        b += 1;
+                    }
+                    None => break,
+                }
+            }
+
+            result
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        backquoted_command, find_closing_paren, home_directory, remove_pattern, replace_pattern,
+        Fields, ParamOp,
+    };
+    use crate::{app::Shell, parser};
+
+    /// Expands a raw word the way a command word is expanded.
+    fn expand_raw(shell: &mut Shell, raw: &str) -> Vec<String> {
+        let word = parser::parse_word(raw).unwrap();
+        shell.expand_word(&word.parts).unwrap()
+    }
+
+    fn split(value: &str, ifs: &str) -> Vec<String> {
+        let mut fields = Fields::default();
+        fields.push_split(value, ifs);
+        fields.into_fields().into_iter().map(|f| f.text).collect()
+    }
+
+    /// Creates a shell with the variables used by tilde expansion.
+    fn tilde_shell(oldpwd: Option<&str>) -> Shell {
+        let mut shell = Shell::new();
+        shell.variables.set("HOME", "/home/test".to_string());
+        shell.variables.set("PWD", "/current".to_string());
+        match oldpwd {
+            Some(dir) => shell.variables.set("OLDPWD", dir.to_string()),
+            None => shell.variables.unset("OLDPWD"),
+        }
+        shell
+    }
+
+    #[test]
+    fn test_tilde_expansion() {
+        let mut shell = tilde_shell(Some("/previous"));
+        let mut expand = |raw: &str| expand_raw(&mut shell, raw);
+
+        assert_eq!(expand("~"), ["/home/test"]);
+        assert_eq!(expand("~/src"), ["/home/test/src"]);
+        assert_eq!(expand("~+"), ["/current"]);
+        assert_eq!(expand("~+/a"), ["/current/a"]);
+        assert_eq!(expand("~-"), ["/previous"]);
+        if let Some(root) = home_directory(Some("root")) {
+            assert_eq!(expand("~root/x"), [format!("{}/x", root)]);
+        }
+        assert_eq!(expand("~no-such-user-shellrs"), ["~no-such-user-shellrs"]);
+    }
+
+    #[test]
+    fn test_quoted_tilde_is_literal() {
+        let mut shell = tilde_shell(None);
+        let mut expand = |raw: &str| expand_raw(&mut shell, raw);
+
+        assert_eq!(expand("~\"x\""), ["~x"]);
+        assert_eq!(expand("\"~\""), ["~"]);
+        assert_eq!(expand("\\~"), ["~"]);
+        assert_eq!(expand("'~'/a"), ["~/a"]);
+        assert_eq!(expand("a~"), ["a~"]);
+        // the home directory is not split or globbed.
+        shell.variables.set("HOME", "/home/a b*".to_string());
+        assert_eq!(expand_raw(&mut shell, "~"), ["/home/a b*"]);
+        // without OLDPWD `~-` stays as it is.
+        assert_eq!(expand_raw(&mut shell, "~-"), ["~-"]);
+    }
+
+    fn chars(s: &str) -> Vec<char> {
+        s.chars().collect()
+    }
+
+    #[test]
+    fn test_backquoted_command() {
+        let command = |s: &str| backquoted_command(&chars(s), 1);
+
+        assert_eq!(command("`echo a` b"), Some(("echo a".to_string(), 8)));
+        assert_eq!(
+            command(r"`echo \$x \` \\ \n`"),
+            Some((r"echo $x ` \ \n".to_string(), 19))
+        );
+        assert_eq!(command("`echo a"), None);
+        assert_eq!(command(r"`echo \`"), None);
+    }
+
+    #[test]
+    fn test_find_closing_paren() {
+        let find = |s: &str| find_closing_paren(&chars(s), 2);
+
+        assert_eq!(find("$(echo a) b"), Some(8));
+        assert_eq!(find("$(echo $(echo x))"), Some(16));
+        assert_eq!(find("$(echo (a) b)"), Some(12));
+        assert_eq!(find("$(echo ')' \")\" \\))"), Some(17));
+        assert_eq!(find("$(case a in a) x;; esac) b"), Some(23));
+        assert_eq!(find("$(echo a"), None);
+        assert_eq!(find("$(echo ')'"), None);
+    }
+
+    #[test]
+    fn test_command_substitution() {
+        let mut shell = Shell::new();
+        shell.variables.set("x", "1".to_string());
+        let mut expand = |raw: &str| expand_raw(&mut shell, raw);
+
+        assert_eq!(expand("$(echo a; echo; echo)"), ["a"]);
+        assert_eq!(expand("<$(echo)>"), ["<>"]);
+        assert_eq!(expand("$(echo $(echo x))"), ["x"]);
+        assert_eq!(expand("`echo \\$x`"), ["1"]);
+        assert_eq!(expand("$(echo 'a  b')"), ["a", "b"]);
+        assert_eq!(expand("\"$(echo 'a  b')\""), ["a  b"]);
+        assert_eq!(expand("\"`echo 'a  b'`\""), ["a  b"]);
+        assert_eq!(expand("x$(echo)y"), ["xy"]);
+        assert_eq!(expand("$(case a in a) echo yes;; esac)"), ["yes"]);
+        assert!(expand("$(echo)").is_empty());
+    }
+
+    #[test]
+    fn test_split_whitespace() {
+        assert_eq!(split("  a  b\tc\n", " \t\n"), vec!["a", "b", "c"]);
+        assert!(split("   ", " \t\n").is_empty());
+    }
+
+    #[test]
+    fn test_split_non_whitespace() {
+        assert_eq!(split("a:b::c", ":"), vec!["a", "b", "", "c"]);
+        assert_eq!(split(":a:", ":"), vec!["", "a"]);
+        assert_eq!(split("a : b", " :"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_quoted_pattern_chars_are_escaped() {
+        let mut fields = Fields::default();
+        fields.push_str("*.", true);
+        fields.push_str("r?", false);
+        let field = fields.into_fields().pop().unwrap();
+        assert_eq!(field.text, "*.r?");
+        assert_eq!(field.pattern, "\\*.r?");
+    }
+
+    #[test]
+    fn test_parse_operator() {
+        let chars: Vec<char> = ":-word".chars().collect();
+        let (op, colon, word) = ParamOp::parse(&chars).unwrap();
+        assert_eq!(op, ParamOp::Default);
+        assert!(colon);
+        assert_eq!(word.iter().collect::<String>(), "word");
+
+        let chars: Vec<char> = "##*/".chars().collect();
+        let (op, colon, _) = ParamOp::parse(&chars).unwrap();
+        assert_eq!(op, ParamOp::RemoveLongestPrefix);
+        assert!(!colon);
+
+        let chars: Vec<char> = ":#x".chars().collect();
+        assert!(ParamOp::parse(&chars).is_none());
+    }
+
+    #[test]
+    fn test_remove_pattern() {
+        let path = "/usr/local/bin.tar.gz";
+        assert_eq!(
+            remove_pattern(path, "*/", ParamOp::RemoveShortestPrefix),
+            "usr/local/bin.tar.gz"
+        );
+        assert_eq!(
+            remove_pattern(path, "*/", ParamOp::RemoveLongestPrefix),
+            "bin.tar.gz"
+        );
+        assert_eq!(
+            remove_pattern(path, ".*", ParamOp::RemoveShortestSuffix),
+            "/usr/local/bin.tar"
+        );
+        assert_eq!(
+            remove_pattern(path, ".*", ParamOp::RemoveLongestSuffix),
+            "/usr/local/bin"
+        );
+        assert_eq!(
+            remove_pattern("abc", "x", ParamOp::RemoveShortestPrefix),
+            "abc"
+        );
+    }
+
+    #[test]
+    fn test_replace_pattern() {
+        assert_eq!(
+            replace_pattern("aXbXc", "X", "-", ParamOp::ReplaceFirst),
+            "a-bXc"
+        );
+        assert_eq!(
+            replace_pattern("aXbXc", "X", "-", ParamOp::ReplaceAll),
+            "a-b-c"
+        );
+        assert_eq!(
+            replace_pattern("aXbXc", "X*", "", ParamOp::ReplaceFirst),
+            "a"
+        );
+        assert_eq!(
+            replace_pattern("abcabc", "abc", "x", ParamOp::ReplacePrefix),
+            "xabc"
+        );
+        assert_eq!(
+            replace_pattern("abcabc", "abc", "x", ParamOp::ReplaceSuffix),
+            "abcx"
+        );
+        assert_eq!(
+            replace_pattern("héllo", "é", "e", ParamOp::ReplaceAll),
+            "hello"
+        );
+        assert_eq!(replace_pattern("abc", "z", "x", ParamOp::ReplaceAll), "abc");
+    }
+}
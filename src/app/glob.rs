@@ -0,0 +1,219 @@
+use std::fs;
+
+use super::pattern;
+
+/// Returns `true` if the pattern contains an unescaped `*`, `?` or bracket expression.
+pub fn has_wildcards(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    let mut open_bracket = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '*' | '?' => return true,
+            '[' => open_bracket = true,
+            ']' if open_bracket => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Expands a pattern into the sorted list of matching paths.
+/// Each `/`-separated component is matched against the directory entries separately,
+/// a `**` component matches any number of directories.
+/// Names starting with `.` only match a pattern that starts with `.`, unless `dotglob` is set.
+pub fn expand(pattern: &str, dotglob: bool) -> Vec<String> {
+    let (mut paths, rest) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_string()], rest),
+        None => (vec![String::new()], pattern),
+    };
+
+    let components: Vec<&str> = rest.split('/').collect();
+    for (i, component) in components.iter().enumerate() {
+        let last = i == components.len() - 1;
+
+        paths = if component.is_empty() {
+            // a trailing `/` only keeps directories, `//` is the same as `/`.
+            paths
+                .into_iter()
+                .filter(|path| !last || is_dir(path))
+                .map(|path| {
+                    if path.ends_with('/') {
+                        path
+                    } else {
+                        path + "/"
+                    }
+                })
+                .collect()
+        } else if *component == "**" {
+            let mut found = Vec::new();
+            for path in paths {
+                if !last {
+                    found.push(path.clone());
+                }
+                walk(&path, dotglob, !last, &mut found);
+            }
+            found
+        } else if has_wildcards(component) {
+            let mut found = Vec::new();
+            for path in paths {
+                for name in matching_entries(&path, component, dotglob) {
+                    found.push(join(&path, &name));
+                }
+            }
+            found
+        } else {
+            paths
+                .into_iter()
+                .map(|path| join(&path, &unescape(component)))
+                .filter(|path| fs::symlink_metadata(path).is_ok())
+                .collect()
+        };
+
+        if paths.is_empty() {
+            break;
+        }
+    }
+
+    paths.sort();
+    paths
+}
+
+/// Returns the names of the entries of `dir` that match the pattern `component`.
+fn matching_entries(dir: &str, component: &str, dotglob: bool) -> Vec<String> {
+    let show_hidden = dotglob || component.starts_with('.') || component.starts_with("\\.");
+
+    entries(dir)
+        .into_iter()
+        .filter(|name| show_hidden || !name.starts_with('.'))
+        .filter(|name| pattern::matches(component, name))
+        .collect()
+}
+
+/// Collects every path below `dir`, or only the directories.
+/// Symbolic links are not followed and hidden entries are skipped unless `dotglob` is set.
+fn walk(dir: &str, dotglob: bool, only_dirs: bool, found: &mut Vec<String>) {
+    for name in entries(dir) {
+        if !dotglob && name.starts_with('.') {
+            continue;
+        }
+
+        let path = join(dir, &name);
+        let is_real_dir = fs::symlink_metadata(&path).is_ok_and(|meta| meta.is_dir());
+        if is_real_dir || !only_dirs {
+            found.push(path.clone());
+        }
+        if is_real_dir {
+            walk(&path, dotglob, only_dirs, found);
+        }
+    }
+}
+
+/// Returns the names of the entries of a directory, `""` is the current directory.
+fn entries(dir: &str) -> Vec<String> {
+    let dir = if dir.is_empty() { "." } else { dir };
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    read_dir
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect()
+}
+
+fn is_dir(path: &str) -> bool {
+    fs::metadata(if path.is_empty() { "." } else { path }).is_ok_and(|meta| meta.is_dir())
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() || dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Removes the backslash escapes of a pattern without wildcards.
+fn unescape(pattern: &str) -> String {
+    let mut text = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => text.extend(chars.next()),
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    #[test]
+    fn test_has_wildcards() {
+        assert!(has_wildcards("*.rs"));
+        assert!(has_wildcards("file?"));
+        assert!(has_wildcards("[ab]c"));
+        assert!(!has_wildcards("plain"));
+        assert!(!has_wildcards("\\*.rs"));
+        assert!(!has_wildcards("["));
+        assert!(!has_wildcards("a]"));
+    }
+
+    #[test]
+    fn test_expand() {
+        let root = env::temp_dir().join(format!("shellrs-glob-{:x}", rand::random::<u64>()));
+        for dir in ["src/app", "src/.hidden", "docs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "main.rs",
+            "src/lib.rs",
+            "src/app/shell.rs",
+            "src/.hidden/x.rs",
+            ".env",
+        ] {
+            fs::write(root.join(file), "").unwrap();
+        }
+
+        let root_str = root.to_str().unwrap();
+        let expand = |pattern: &str, dotglob: bool| -> Vec<String> {
+            expand(&format!("{}/{}", root_str, pattern), dotglob)
+                .into_iter()
+                .map(|path| path[root_str.len() + 1..].to_string())
+                .collect()
+        };
+
+        assert_eq!(expand("*", false), ["docs", "main.rs", "src"]);
+        assert_eq!(expand("*", true), [".env", "docs", "main.rs", "src"]);
+        assert_eq!(expand(".*", false), [".env"]);
+        assert_eq!(expand("*/", false), ["docs/", "src/"]);
+        assert_eq!(expand("s?c/*.rs", false), ["src/lib.rs"]);
+        assert_eq!(expand("[a-m]*", false), ["docs", "main.rs"]);
+        assert_eq!(
+            expand("**/*.rs", false),
+            ["main.rs", "src/app/shell.rs", "src/lib.rs"]
+        );
+        assert_eq!(
+            expand("src/**", true),
+            [
+                "src/.hidden",
+                "src/.hidden/x.rs",
+                "src/app",
+                "src/app/shell.rs",
+                "src/lib.rs"
+            ]
+        );
+        assert!(expand("*.txt", false).is_empty());
+        assert!(expand("nothing/*", false).is_empty());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,93 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Returns the byte offset of the grapheme cluster boundary before `pos`.
+/// Returns `0` at the start of the text.
+pub fn previous_boundary(text: &str, pos: usize) -> usize {
+    text[..pos]
+        .grapheme_indices(true)
+        .next_back()
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Returns the byte offset of the grapheme cluster boundary after `pos`.
+/// Returns the length of the text at its end.
+pub fn next_boundary(text: &str, pos: usize) -> usize {
+    text[pos..]
+        .graphemes(true)
+        .next()
+        .map(|grapheme| pos + grapheme.len())
+        .unwrap_or(text.len())
+}
+
+/// Returns the number of terminal columns `text` takes up.
+/// Wide (e.g. CJK) characters take two columns, combining and other zero-width characters none.
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Decodes the length of a UTF-8 sequence from its first byte.
+/// Returns `None` if the byte can't start a sequence.
+pub fn utf8_sequence_len(first_byte: u8) -> Option<usize> {
+    match first_byte {
+        0x00..=0x7F => Some(1),
+        0xC2..=0xDF => Some(2),
+        0xE0..=0xEF => Some(3),
+        0xF0..=0xF4 => Some(4),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{display_width, next_boundary, previous_boundary, utf8_sequence_len};
+
+    #[test]
+    fn test_boundaries_ascii() {
+        assert_eq!(previous_boundary("abc", 2), 1);
+        assert_eq!(previous_boundary("abc", 0), 0);
+        assert_eq!(next_boundary("abc", 1), 2);
+        assert_eq!(next_boundary("abc", 3), 3);
+    }
+
+    #[test]
+    fn test_boundaries_multi_byte() {
+        // "ä" is two bytes, "日" three.
+        let text = "ä日x";
+        assert_eq!(next_boundary(text, 0), 2);
+        assert_eq!(next_boundary(text, 2), 5);
+        assert_eq!(previous_boundary(text, 5), 2);
+        assert_eq!(previous_boundary(text, 2), 0);
+    }
+
+    #[test]
+    fn test_boundaries_grapheme_clusters() {
+        // "e" followed by a combining acute accent is a single cluster.
+        let text = "e\u{301}x";
+        assert_eq!(next_boundary(text, 0), 3);
+        assert_eq!(previous_boundary(text, 3), 0);
+
+        // a family emoji joined with zero-width joiners.
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(next_boundary(family, 0), family.len());
+    }
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本"), 4);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width(""), 0);
+    }
+
+    #[test]
+    fn test_utf8_sequence_len() {
+        assert_eq!(utf8_sequence_len(b'a'), Some(1));
+        assert_eq!("ä".as_bytes()[0], 0xC3);
+        assert_eq!(utf8_sequence_len(0xC3), Some(2));
+        assert_eq!(utf8_sequence_len("日".as_bytes()[0]), Some(3));
+        assert_eq!(utf8_sequence_len("👨".as_bytes()[0]), Some(4));
+        assert_eq!(utf8_sequence_len(0x80), None);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, Write},
+    os::fd::{AsRawFd, RawFd},
+    path::PathBuf,
+};
+
+use crate::dprintln;
+
+/// Name of the history file in the home directory.
+const HISTORY_FILE_NAME: &str = ".shellrs_history";
+
+/// Number of entries kept when `HISTSIZE` is not set.
+pub const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+/// Direction of a history search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchDirection {
+    /// Towards older entries.
+    Backward,
+    /// Towards newer entries.
+    Forward,
+}
+
+/// Command history with up/down browsing and a persistent history file.
+/// The history file is shared by all running shells, every access to it is guarded with `flock`.
+pub struct History {
+    /// Submitted lines, oldest first.
+    entries: VecDeque<String>,
+    /// Maximum number of entries kept in memory and in the history file.
+    capacity: usize,
+    /// Path of the history file, `None` if the history is not persisted.
+    path: Option<PathBuf>,
+    /// Index of the entry that is currently browsed, `None` while editing the draft.
+    browse_index: Option<usize>,
+    /// The unsaved line that was being edited when browsing started.
+    draft: String,
+}
+
+impl History {
+    /// Creates a new empty instance of the `History` struct.
+    pub fn new(capacity: usize, path: Option<PathBuf>) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            capacity,
+            path,
+            browse_index: None,
+            draft: String::new(),
+        }
+    }
+
+    /// Returns the default history file path (`~/.shellrs_history`).
+    pub fn default_path(home: Option<&str>) -> Option<PathBuf> {
+        home.map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME))
+    }
+
+    /// Loads the entries of the history file, replacing the in-memory entries.
+    pub fn load(&mut self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let mut contents = String::new();
+        {
+            let _lock = FileLock::shared(&file)?;
+            file.read_to_string(&mut contents)?;
+        }
+
+        self.entries = contents.lines().map(decode_entry).collect();
+        self.truncate();
+        dprintln!("loaded {} history entries", self.entries.len());
+
+        Ok(())
+    }
+
+    /// Adds a submitted line to the history and appends it to the history file.
+    /// Blank lines and repetitions of the previous entry are skipped.
+    pub fn add(&mut self, line: &str) -> io::Result<()> {
+        self.reset_browsing();
+
+        if line.trim().is_empty() || self.entries.back().is_some_and(|last| last == line) {
+            return Ok(());
+        }
+
+        self.entries.push_back(line.to_string());
+        self.truncate();
+        self.append_to_file(line)
+    }
+
+    /// Returns the entries, oldest first.
+    pub fn entries(&self) -> &VecDeque<String> {
+        &self.entries
+    }
+
+    /// Finds the closest entry that contains `query`, starting with the entry at index `from`.
+    /// Returns the index of the entry and the byte offset of the match in it.
+    pub fn search(
+        &self,
+        query: &str,
+        from: usize,
+        direction: SearchDirection,
+    ) -> Option<(usize, usize)> {
+        if query.is_empty() || from >= self.entries.len() {
+            return None;
+        }
+
+        let find = |index: usize| self.entries[index].find(query).map(|pos| (index, pos));
+        match direction {
+            SearchDirection::Backward => (0..=from).rev().find_map(find),
+            SearchDirection::Forward => (from..self.entries.len()).find_map(find),
+        }
+    }
+
+    /// Steps to the previous (older) entry.
+    /// `current` is the line being edited, it is kept as the draft when browsing starts.
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let index = match self.browse_index {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+            Some(0) => return None,
+            Some(index) => index - 1,
+        };
+
+        self.browse_index = Some(index);
+        Some(&self.entries[index])
+    }
+
+    /// Steps to the next (newer) entry.
+    /// Stepping past the newest entry returns the draft.
+    pub fn next(&mut self) -> Option<&str> {
+        let index = self.browse_index?;
+
+        if index + 1 < self.entries.len() {
+            self.browse_index = Some(index + 1);
+            Some(&self.entries[index + 1])
+        } else {
+            self.browse_index = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// Stops browsing, the next `previous` call starts from the newest entry again.
+    pub fn reset_browsing(&mut self) {
+        self.browse_index = None;
+        self.draft.clear();
+    }
+
+    /// Drops the oldest entries that exceed the capacity.
+    fn truncate(&mut self) {
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Appends a line to the history file.
+    /// The file is rewritten with only the newest entries once it grows to twice the capacity.
+    fn append_to_file(&self, line: &str) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let _lock = FileLock::exclusive(&file)?;
+
+        // a single write with O_APPEND, so lines of concurrent shells never interleave.
+        file.write_all(format!("{}\n", encode_entry(line)).as_bytes())?;
+
+        let mut contents = String::new();
+        file.rewind()?;
+        file.read_to_string(&mut contents)?;
+
+        let line_count = contents.lines().count();
+        if line_count > self.capacity * 2 {
+            dprintln!("trimming history file with {} entries", line_count);
+
+            let kept: Vec<&str> = contents.lines().skip(line_count - self.capacity).collect();
+            file.set_len(0)?;
+            file.write_all(format!("{}\n", kept.join("\n")).as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An advisory lock on a file, released when dropped.
+/// The lock must not outlive the file it was taken on.
+struct FileLock {
+    fd: RawFd,
+}
+
+impl FileLock {
+    /// Locks the file for reading, other readers are allowed.
+    fn shared(file: &File) -> io::Result<Self> {
+        Self::lock(file.as_raw_fd(), libc::LOCK_SH)
+    }
+
+    /// Locks the file for writing, no other locks are allowed.
+    fn exclusive(file: &File) -> io::Result<Self> {
+        Self::lock(file.as_raw_fd(), libc::LOCK_EX)
+    }
+
+    fn lock(fd: RawFd, operation: i32) -> io::Result<Self> {
+        loop {
+            if unsafe { libc::flock(fd, operation) } == 0 {
+                return Ok(Self { fd });
+            }
+
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        unsafe { libc::flock(self.fd, libc::LOCK_UN) };
+    }
+}
+
+/// Encodes an entry as a single line of the history file.
+/// Backslashes and newlines are escaped, so multi-line entries survive a round trip.
+fn encode_entry(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Decodes a line of the history file into an entry.
+fn decode_entry(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                chars.next();
+                entry.push('\n');
+            }
+            ('\\', Some('\\')) => {
+                chars.next();
+                entry.push('\\');
+            }
+            _ => entry.push(c),
+        }
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_entry, encode_entry, History, SearchDirection};
+
+    fn history_with(entries: &[&str]) -> History {
+        let mut history = History::new(3, None);
+        for entry in entries {
+            history.add(entry).unwrap();
+        }
+        history
+    }
+
+    #[test]
+    fn test_add_skips_blank_and_repeated_lines() {
+        let history = history_with(&["ls", "ls", "  ", "pwd", "ls"]);
+        assert_eq!(history.entries(), &["ls", "pwd", "ls"]);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let history = history_with(&["a", "b", "c", "d"]);
+        assert_eq!(history.entries(), &["b", "c", "d"]);
+    }
+
+    #[test]
+    fn test_browsing_keeps_draft() {
+        let mut history = history_with(&["a", "b"]);
+
+        assert_eq!(history.previous("draft"), Some("b"));
+        assert_eq!(history.previous("ignored"), Some("a"));
+        assert_eq!(history.previous("ignored"), None);
+        assert_eq!(history.next(), Some("b"));
+        assert_eq!(history.next(), Some("draft"));
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn test_browsing_empty_history() {
+        let mut history = history_with(&[]);
+        assert_eq!(history.previous("draft"), None);
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn test_search() {
+        let history = history_with(&["git status", "ls", "git log"]);
+
+        assert_eq!(
+            history.search("git", 2, SearchDirection::Backward),
+            Some((2, 0))
+        );
+        assert_eq!(
+            history.search("git", 1, SearchDirection::Backward),
+            Some((0, 0))
+        );
+        assert_eq!(history.search("log", 1, SearchDirection::Backward), None);
+        assert_eq!(
+            history.search("log", 1, SearchDirection::Forward),
+            Some((2, 4))
+        );
+        assert_eq!(
+            history.search("s", 2, SearchDirection::Backward),
+            Some((1, 1))
+        );
+        assert_eq!(history.search("", 2, SearchDirection::Backward), None);
+        assert_eq!(history.search("git", 3, SearchDirection::Backward), None);
+    }
+
+    #[test]
+    fn test_entry_encoding_round_trip() {
+        let entry = "echo a\\nb\nfor x in y";
+        let encoded = encode_entry(entry);
+        assert!(!encoded.contains('\n'));
+        assert_eq!(decode_entry(&encoded), entry);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{
+    env,
+    fs::{self, File, OpenOptions},
+    io::{self, stdin, Read, Seek, SeekFrom, Stdin, Write},
+    os::fd::AsFd,
+    process::Stdio,
+};
+
+/// The input type of the shell.
+/// Can be either the standard input, a file or closed (`<&-`).
+pub enum ShellInput {
+    Stdin(Stdin),
+    File(File),
+    Closed,
+}
+
+impl ShellInput {
+    pub fn stdin() -> Self {
+        ShellInput::Stdin(stdin())
+    }
+
+    /// Returns a new file handle for the same open file, used by `>&` and `<&`.
+    pub fn try_clone_file(&self) -> io::Result<File> {
+        match self {
+            ShellInput::Stdin(stdin) => Ok(File::from(stdin.as_fd().try_clone_to_owned()?)),
+            ShellInput::File(file) => file.try_clone(),
+            ShellInput::Closed => Err(io::Error::from_raw_os_error(libc::EBADF)),
+        }
+    }
+
+    /// Converts the `ShellInput` into a `Stdio`.
+    /// A closed input is inherited here, it has to be closed in the child process.
+    pub fn as_stdio(&mut self) -> io::Result<Stdio> {
+        match self {
+            ShellInput::File(ref mut file) => Ok(Stdio::from(file.try_clone()?)),
+            ShellInput::Stdin(_) | ShellInput::Closed => Ok(Stdio::inherit()),
+        }
+    }
+}
+
+impl Read for ShellInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ShellInput::Stdin(ref mut reader) => reader.read(buf),
+            ShellInput::File(ref mut reader) => reader.read(buf),
+            ShellInput::Closed => Err(io::Error::from_raw_os_error(libc::EBADF)),
+        }
+    }
+}
+
+/// Creates a file that contains the given text, used for here-documents and here-strings.
+/// The file is an unlinked temporary file, so external commands can read it as well.
+pub fn text_file(text: &str) -> io::Result<File> {
+    let path = env::temp_dir().join(format!(
+        "shellrs-{}-{:x}",
+        std::process::id(),
+        rand::random::<u64>()
+    ));
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+
+    file.write_all(text.as_bytes())?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::app::graphemes;
+
+/// Maximum number of kills kept in the kill ring.
+const KILL_RING_SIZE: usize = 16;
+
+/// Killed (cut) text that can be yanked (pasted) back, newest first.
+#[derive(Debug, Default)]
+pub struct KillRing {
+    entries: VecDeque<String>,
+    /// Index of the entry that was yanked last.
+    yank_index: usize,
+}
+
+impl KillRing {
+    /// Adds killed text to the ring.
+    /// If `extend` is set, the text is joined with the newest entry instead, so consecutive kills
+    /// are yanked back as one. Text killed backwards is prepended.
+    pub fn kill(&mut self, text: &str, backward: bool, extend: bool) {
+        if text.is_empty() {
+            return;
+        }
+
+        match self.entries.front_mut() {
+            Some(last) if extend => {
+                if backward {
+                    last.insert_str(0, text);
+                } else {
+                    last.push_str(text);
+                }
+            }
+            _ => {
+                self.entries.push_front(text.to_string());
+                self.entries.truncate(KILL_RING_SIZE);
+            }
+        }
+    }
+
+    /// Returns the newest entry.
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_index = 0;
+        self.entries.front().map(String::as_str)
+    }
+
+    /// Returns the entry before the one yanked last, wrapping around at the oldest entry.
+    pub fn rotate(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        self.yank_index = (self.yank_index + 1) % self.entries.len();
+        Some(&self.entries[self.yank_index])
+    }
+}
+
+/// Returns `true` if the grapheme is part of a word (letters, digits and underscores).
+fn is_word_grapheme(grapheme: &str) -> bool {
+    grapheme
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Returns the start of the word before `pos`, skipping any non-word characters first.
+pub fn previous_word_start(text: &str, pos: usize) -> usize {
+    let mut start = pos;
+    let mut in_word = false;
+
+    for (i, grapheme) in text[..pos].grapheme_indices(true).rev() {
+        if is_word_grapheme(grapheme) {
+            in_word = true;
+        } else if in_word {
+            break;
+        }
+        start = i;
+    }
+
+    start
+}
+
+/// Returns the end of the word after `pos`, skipping any non-word characters first.
+pub fn next_word_end(text: &str, pos: usize) -> usize {
+    let mut end = pos;
+    let mut in_word = false;
+
+    for grapheme in text[pos..].graphemes(true) {
+        if is_word_grapheme(grapheme) {
+            in_word = true;
+        } else if in_word {
+            break;
+        }
+        end += grapheme.len();
+    }
+
+    end
+}
+
+/// Returns the start of the whitespace-delimited word before `pos`, skipping trailing whitespace.
+pub fn previous_blank_word_start(text: &str, pos: usize) -> usize {
+    let trimmed = text[..pos].trim_end();
+    trimmed
+        .rfind(char::is_whitespace)
+        .map(|i| i + trimmed[i..].chars().next().unwrap().len_utf8())
+        .unwrap_or(0)
+}
+
+/// Swaps the grapheme before `pos` with the one at `pos`, or the last two at the end of the line.
+/// Returns the new cursor position, after the swapped graphemes.
+pub fn transpose(text: &mut String, pos: usize) -> usize {
+    let pos = if pos == text.len() {
+        graphemes::previous_boundary(text, pos)
+    } else {
+        pos
+    };
+    if pos == 0 {
+        return pos;
+    }
+
+    let start = graphemes::previous_boundary(text, pos);
+    let end = graphemes::next_boundary(text, pos);
+    let swapped = format!("{}{}", &text[pos..end], &text[start..pos]);
+    text.replace_range(start..end, &swapped);
+
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        next_word_end, previous_blank_word_start, previous_word_start, transpose, KillRing,
+    };
+
+    #[test]
+    fn test_word_motions() {
+        let text = "echo foo-bar  baz";
+        assert_eq!(previous_word_start(text, text.len()), 14);
+        assert_eq!(previous_word_start(text, 14), 9);
+        assert_eq!(previous_word_start(text, 9), 5);
+        assert_eq!(previous_word_start(text, 0), 0);
+
+        assert_eq!(next_word_end(text, 0), 4);
+        assert_eq!(next_word_end(text, 4), 8);
+        assert_eq!(next_word_end(text, 8), 12);
+        assert_eq!(next_word_end(text, text.len()), text.len());
+    }
+
+    #[test]
+    fn test_word_motions_unicode() {
+        let text = "über straße";
+        assert_eq!(previous_word_start(text, text.len()), 6);
+        assert_eq!(next_word_end(text, 0), 5);
+    }
+
+    #[test]
+    fn test_previous_blank_word_start() {
+        let text = "ls foo-bar/baz  ";
+        assert_eq!(previous_blank_word_start(text, text.len()), 3);
+        assert_eq!(previous_blank_word_start(text, 3), 0);
+        assert_eq!(previous_blank_word_start("", 0), 0);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let mut text = String::from("abc");
+        assert_eq!(transpose(&mut text, 1), 2);
+        assert_eq!(text, "bac");
+
+        // at the end of the line the last two characters are swapped.
+        assert_eq!(transpose(&mut text, 3), 3);
+        assert_eq!(text, "bca");
+
+        assert_eq!(transpose(&mut text, 0), 0);
+        assert_eq!(text, "bca");
+
+        let mut text = String::from("ä日");
+        assert_eq!(transpose(&mut text, 5), 5);
+        assert_eq!(text, "日ä");
+    }
+
+    #[test]
+    fn test_kill_ring() {
+        let mut ring = KillRing::default();
+        assert_eq!(ring.yank(), None);
+
+        ring.kill("one", false, false);
+        ring.kill("two", false, false);
+        assert_eq!(ring.yank(), Some("two"));
+        assert_eq!(ring.rotate(), Some("one"));
+        assert_eq!(ring.rotate(), Some("two"));
+    }
+
+    #[test]
+    fn test_kill_ring_extends_consecutive_kills() {
+        let mut ring = KillRing::default();
+        ring.kill("bar", true, false);
+        ring.kill("foo ", true, true);
+        ring.kill(" baz", false, true);
+        assert_eq!(ring.yank(), Some("foo bar baz"));
+    }
+}
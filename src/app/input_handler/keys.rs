@@ -0,0 +1,148 @@
+use std::{
+    fs::File,
+    io::Read,
+    os::fd::{AsRawFd, RawFd},
+};
+
+use crate::app::graphemes;
+
+/// How long to wait for the rest of an escape sequence before treating `Esc` as a key of its own.
+const ESCAPE_TIMEOUT_MS: i32 = 50;
+
+/// A key press decoded from the terminal input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A printable character.
+    Char(char),
+    /// A letter pressed with Ctrl, stored in lowercase.
+    Ctrl(char),
+    /// A character pressed with Alt (or after Esc).
+    Alt(char),
+    Enter,
+    Tab,
+    Backspace,
+    AltBackspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    Delete,
+    /// Anything that has no meaning for the line editor.
+    Unknown,
+}
+
+/// Reads the next key press from the terminal.
+/// Returns `None` at the end of the input.
+pub fn read_key(tty: &mut File) -> Option<Key> {
+    let b = read_byte(tty)?;
+
+    let key = match b {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        127 | 8 => Key::Backspace,
+        0x1B => read_escape(tty),
+        0x01..=0x1A => Key::Ctrl((b'a' + b - 1) as char),
+        _ if b.is_ascii_control() => Key::Unknown,
+        _ => match read_char(tty, b) {
+            Some(c) if !c.is_control() => Key::Char(c),
+            _ => Key::Unknown,
+        },
+    };
+
+    Some(key)
+}
+
+/// Decodes the key that follows an `Esc` byte.
+fn read_escape(tty: &mut File) -> Key {
+    if !wait_for_input(tty.as_raw_fd(), ESCAPE_TIMEOUT_MS) {
+        return Key::Escape;
+    }
+    let Some(b) = read_byte(tty) else {
+        return Key::Escape;
+    };
+
+    match b {
+        b'[' => read_csi(tty),
+        b'O' => match read_byte(tty) {
+            Some(b'A') => Key::Up,
+            Some(b'B') => Key::Down,
+            Some(b'C') => Key::Right,
+            Some(b'D') => Key::Left,
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            _ => Key::Unknown,
+        },
+        127 | 8 => Key::AltBackspace,
+        _ if b.is_ascii_control() => Key::Unknown,
+        _ => match read_char(tty, b) {
+            Some(c) => Key::Alt(c),
+            None => Key::Unknown,
+        },
+    }
+}
+
+/// Decodes a control sequence (`Esc [ parameters final-byte`).
+fn read_csi(tty: &mut File) -> Key {
+    let mut params = String::new();
+
+    loop {
+        let Some(b) = read_byte(tty) else {
+            return Key::Unknown;
+        };
+
+        match b {
+            // parameter and intermediate bytes.
+            0x20..=0x3F => params.push(b as char),
+            b'A' => return Key::Up,
+            b'B' => return Key::Down,
+            b'C' => return Key::Right,
+            b'D' => return Key::Left,
+            b'H' => return Key::Home,
+            b'F' => return Key::End,
+            b'~' => {
+                return match params.as_str() {
+                    "1" | "7" => Key::Home,
+                    "4" | "8" => Key::End,
+                    "3" => Key::Delete,
+                    _ => Key::Unknown,
+                };
+            }
+            _ => return Key::Unknown,
+        }
+    }
+}
+
+/// Reads the rest of the UTF-8 sequence that starts with `first_byte` and decodes it.
+/// Returns `None` if the sequence is malformed.
+fn read_char(tty: &mut File, first_byte: u8) -> Option<char> {
+    let len = graphemes::utf8_sequence_len(first_byte)?;
+
+    let mut bytes = [first_byte, 0, 0, 0];
+    tty.read_exact(&mut bytes[1..len]).ok()?;
+
+    std::str::from_utf8(&bytes[..len]).ok()?.chars().next()
+}
+
+fn read_byte(tty: &mut File) -> Option<u8> {
+    let mut byte = [0u8; 1];
+    loop {
+        match tty.read(&mut byte) {
+            Ok(1) => return Some(byte[0]),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            _ => return None,
+        }
+    }
+}
+
+/// Returns `true` if there is input to read within `timeout_ms` milliseconds.
+fn wait_for_input(fd: RawFd, timeout_ms: i32) -> bool {
+    let mut poll_fd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) > 0 }
+}
@@ -0,0 +1,578 @@
+mod editing;
+mod keys;
+mod vi;
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    ops::Range,
+    os::fd::AsRawFd,
+};
+
+use libc::{tcgetattr, tcsetattr, termios, ECHO, ICANON, ISIG, IXON, TCSANOW};
+
+use super::{
+    completion::{self, CompletionContext},
+    graphemes,
+    history::SearchDirection,
+    EditingMode, History,
+};
+use editing::KillRing;
+use keys::Key;
+use vi::ViState;
+
+/// Terminal width used when it can't be queried.
+const DEFAULT_TERMINAL_WIDTH: usize = 80;
+
+/// How reading a line of input ended.
+#[derive(Debug, PartialEq, Eq)]
+pub enum InputStatus {
+    /// The line was submitted with Enter.
+    Submitted,
+    /// The line was discarded with Ctrl-C.
+    Interrupted,
+    /// The input ended (Ctrl-D on an empty line).
+    Eof,
+}
+
+/// What the line editor does after handling a key.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    /// Keep editing the line.
+    Continue,
+    /// Submit the line.
+    Accept,
+    /// Discard the line.
+    Interrupt,
+    /// The input ended (Ctrl-D on an empty line).
+    Eof,
+}
+
+/// The current match of the incremental history search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SearchMatch {
+    /// Index of the matched history entry.
+    index: usize,
+    /// Byte offset of the match in the entry.
+    pos: usize,
+    /// Byte length of the match. It is shorter than the query while the search fails,
+    /// since the last match is kept when characters are added to the query.
+    len: usize,
+}
+
+impl SearchMatch {
+    /// Searches `history` for `query` starting with the entry at index `from`.
+    fn find(
+        history: &History,
+        query: &str,
+        from: usize,
+        direction: SearchDirection,
+    ) -> Option<Self> {
+        history
+            .search(query, from, direction)
+            .map(|(index, pos)| Self {
+                index,
+                pos,
+                len: query.len(),
+            })
+    }
+
+    /// Splits `entry` into the text before the match, the match and the text after it.
+    fn split<'a>(&self, entry: &'a str) -> (&'a str, &'a str, &'a str) {
+        let (before, rest) = entry.split_at(self.pos);
+        let (matched, after) = rest.split_at(self.len);
+        (before, matched, after)
+    }
+}
+
+pub struct InputHandler {
+    tty: File,
+
+    /// Byte offset of the cursor in the buffer, always on a grapheme cluster boundary.
+    cursor_pos: usize,
+    /// History of submitted lines, browsed with the up and down arrows.
+    pub history: History,
+    /// Text killed with Ctrl-K, Ctrl-U, Ctrl-W and friends, yanked back with Ctrl-Y.
+    kill_ring: KillRing,
+    /// Whether the previous key killed text, consecutive kills are yanked back as one.
+    last_was_kill: bool,
+    /// The text inserted by the previous yank, which Alt-Y replaces with an older kill.
+    last_yank: Option<Range<usize>>,
+    /// State of the vi editing mode.
+    vi: ViState,
+}
+
+impl InputHandler {
+    pub fn new(history: History) -> Self {
+        Self::with_tty(File::open("/dev/tty").unwrap(), history)
+    }
+
+    /// Creates an input handler that reads keys from `tty`.
+    fn with_tty(tty: File, history: History) -> Self {
+        Self {
+            tty,
+            cursor_pos: 0,
+            history,
+            kill_ring: KillRing::default(),
+            last_was_kill: false,
+            last_yank: None,
+            vi: ViState::default(),
+        }
+    }
+
+    pub fn input_loop(
+        &mut self,
+        buffer: &mut String,
+        prompt: &str,
+        completion_context: &CompletionContext,
+        editing_mode: EditingMode,
+    ) -> InputStatus {
+        let fd = self.tty.as_raw_fd();
+
+        // save the original terminal settings.
+        let original_termios = Self::get_termios(fd).unwrap();
+
+        // set terminal to raw mode (disable canonical mode and echo).
+        let raw_termios = Self::disable_canonical_echo(original_termios);
+        Self::set_termios(fd, &raw_termios).unwrap();
+
+        let status = self.edit_line(buffer, prompt, completion_context, editing_mode);
+
+        // restore the original terminal settings.
+        Self::set_termios(fd, &original_termios).expect("failed to restore terminal settings");
+
+        status
+    }
+
+    /// Reads keys and edits the line in `buffer` until it is submitted, discarded or the input ends.
+    fn edit_line(
+        &mut self,
+        buffer: &mut String,
+        prompt: &str,
+        completion_context: &CompletionContext,
+        editing_mode: EditingMode,
+    ) -> InputStatus {
+        let redraw_line = |buffer: &str, cursor_pos: usize| {
+            // \r returns to the beginning of the line; \x1b[K clears the line from the cursor onward.
+            print!("\r{}{}\x1b[K", prompt, buffer);
+
+            let cursor_col = graphemes::display_width(prompt)
+                + graphemes::display_width(&buffer[..cursor_pos])
+                + 1;
+
+            // move the cursor to the correct position.
+            print!("\r\x1b[{}G", cursor_col);
+
+            io::stdout().flush().unwrap();
+        };
+
+        self.last_was_kill = false;
+        self.last_yank = None;
+        if editing_mode == EditingMode::Vi {
+            self.vi.start_line();
+        }
+
+        let mut status = InputStatus::Eof;
+        while let Some(key) = keys::read_key(&mut self.tty) {
+            let action = match editing_mode {
+                EditingMode::Emacs => self.handle_emacs_key(key, buffer, completion_context),
+                EditingMode::Vi => self.handle_vi_key(key, buffer, completion_context),
+            };
+
+            match action {
+                Action::Continue => redraw_line(buffer, self.cursor_pos),
+                Action::Accept => {
+                    println!();
+                    status = InputStatus::Submitted;
+                    break;
+                }
+                Action::Interrupt => {
+                    println!("^C");
+                    buffer.clear();
+                    status = InputStatus::Interrupted;
+                    break;
+                }
+                Action::Eof => break,
+            }
+        }
+        if editing_mode == EditingMode::Vi {
+            vi::reset_cursor_shape();
+        }
+
+        self.cursor_pos = 0;
+        self.history.reset_browsing();
+
+        status
+    }
+
+    /// Handles a key with the emacs (readline) bindings.
+    fn handle_emacs_key(
+        &mut self,
+        key: Key,
+        buffer: &mut String,
+        completion_context: &CompletionContext,
+    ) -> Action {
+        let mut killed = None;
+        let mut yanked = None;
+
+        match key {
+            Key::Enter => return Action::Accept,
+            Key::Ctrl('c') => return Action::Interrupt,
+            Key::Ctrl('d') if buffer.is_empty() => return Action::Eof,
+            Key::Ctrl('d') | Key::Delete => {
+                let end = graphemes::next_boundary(buffer, self.cursor_pos);
+                buffer.replace_range(self.cursor_pos..end, "");
+            }
+            Key::Backspace | Key::Ctrl('h') => {
+                // remove the whole grapheme before the cursor.
+                let start = graphemes::previous_boundary(buffer, self.cursor_pos);
+                buffer.replace_range(start..self.cursor_pos, "");
+                self.cursor_pos = start;
+            }
+            Key::Left | Key::Ctrl('b') => {
+                self.cursor_pos = graphemes::previous_boundary(buffer, self.cursor_pos);
+            }
+            Key::Right | Key::Ctrl('f') => {
+                self.cursor_pos = graphemes::next_boundary(buffer, self.cursor_pos);
+            }
+            Key::Home | Key::Ctrl('a') => self.cursor_pos = 0,
+            Key::End | Key::Ctrl('e') => self.cursor_pos = buffer.len(),
+            Key::Alt('b') => {
+                self.cursor_pos = editing::previous_word_start(buffer, self.cursor_pos);
+            }
+            Key::Alt('f') => {
+                self.cursor_pos = editing::next_word_end(buffer, self.cursor_pos);
+            }
+            Key::Up | Key::Ctrl('p') => {
+                // show the previous history entry.
+                if let Some(entry) = self.history.previous(buffer) {
+                    *buffer = entry.to_string();
+                    self.cursor_pos = buffer.len();
+                }
+            }
+            Key::Down | Key::Ctrl('n') => {
+                // show the next history entry or the draft.
+                if let Some(entry) = self.history.next() {
+                    *buffer = entry.to_string();
+                    self.cursor_pos = buffer.len();
+                }
+            }
+            Key::Ctrl('w') => {
+                let start = editing::previous_blank_word_start(buffer, self.cursor_pos);
+                killed = Some((start..self.cursor_pos, true));
+            }
+            Key::AltBackspace => {
+                let start = editing::previous_word_start(buffer, self.cursor_pos);
+                killed = Some((start..self.cursor_pos, true));
+            }
+            Key::Alt('d') => {
+                let end = editing::next_word_end(buffer, self.cursor_pos);
+                killed = Some((self.cursor_pos..end, false));
+            }
+            Key::Ctrl('u') => killed = Some((0..self.cursor_pos, true)),
+            Key::Ctrl('k') => killed = Some((self.cursor_pos..buffer.len(), false)),
+            Key::Ctrl('y') => {
+                if let Some(text) = self.kill_ring.yank() {
+                    buffer.insert_str(self.cursor_pos, text);
+                    yanked = Some(self.cursor_pos..self.cursor_pos + text.len());
+                    self.cursor_pos += text.len();
+                }
+            }
+            Key::Alt('y') => {
+                // replace the text that was just yanked with an older kill.
+                if let Some(range) = self.last_yank.take() {
+                    if let Some(text) = self.kill_ring.rotate() {
+                        buffer.replace_range(range.clone(), text);
+                        yanked = Some(range.start..range.start + text.len());
+                        self.cursor_pos = range.start + text.len();
+                    }
+                }
+            }
+            Key::Ctrl('t') => {
+                self.cursor_pos = editing::transpose(buffer, self.cursor_pos);
+            }
+            Key::Ctrl('l') => {
+                // move to the top left corner and clear the screen.
+                print!("\x1b[H\x1b[2J");
+            }
+            Key::Tab => self.complete(buffer, completion_context),
+            Key::Ctrl('r') | Key::Ctrl('s') => {
+                // the key that ended the search is handled as usual.
+                if let Some(key) = self.search_history(buffer, key) {
+                    return self.handle_emacs_key(key, buffer, completion_context);
+                }
+            }
+            Key::Char(c) => {
+                buffer.insert(self.cursor_pos, c);
+                self.cursor_pos += c.len_utf8();
+            }
+            _ => {}
+        }
+
+        let kill_continues = std::mem::replace(&mut self.last_was_kill, killed.is_some());
+        if let Some((range, backward)) = killed {
+            self.kill_ring
+                .kill(&buffer[range.clone()], backward, kill_continues);
+            buffer.replace_range(range.clone(), "");
+            self.cursor_pos = range.start;
+        }
+        self.last_yank = yanked;
+
+        Action::Continue
+    }
+
+    /// Runs an incremental history search until it is accepted or cancelled.
+    /// The search goes backwards if started with Ctrl-R and forwards if started with Ctrl-S.
+    /// Ctrl-R and Ctrl-S step to older and newer matches, Ctrl-G cancels and keeps the buffer,
+    /// Esc and any other editing key accept the match into the buffer.
+    /// Returns the key that ended the search, which still has to be handled by the editor.
+    fn search_history(&mut self, buffer: &mut String, start_key: Key) -> Option<Key> {
+        let mut direction = if start_key == Key::Ctrl('s') {
+            SearchDirection::Forward
+        } else {
+            SearchDirection::Backward
+        };
+        let mut query = String::new();
+        let mut found: Option<SearchMatch> = None;
+        let mut failed = false;
+        let newest = self.history.entries().len().saturating_sub(1);
+
+        loop {
+            self.draw_search(&query, found, failed, direction);
+
+            let key = keys::read_key(&mut self.tty)?;
+            let from = match key {
+                Key::Ctrl(c @ ('r' | 's')) => {
+                    let step = if c == 'r' {
+                        SearchDirection::Backward
+                    } else {
+                        SearchDirection::Forward
+                    };
+                    // stepping in the opposite direction first searches from the current match.
+                    let from = match (found, step == direction) {
+                        (Some(found), true) => match step {
+                            SearchDirection::Backward => found.index.checked_sub(1),
+                            SearchDirection::Forward => Some(found.index + 1),
+                        },
+                        (Some(found), false) => Some(found.index),
+                        (None, _) => Some(newest),
+                    };
+                    direction = step;
+                    from
+                }
+                Key::Backspace => {
+                    let end = query.len();
+                    query.truncate(graphemes::previous_boundary(&query, end));
+                    Some(newest)
+                }
+                Key::Char(c) => {
+                    query.push(c);
+                    // a longer query may still match the current entry.
+                    Some(found.map(|found| found.index).unwrap_or(newest))
+                }
+                // Ctrl-G cancels the search, leaving the buffer untouched.
+                Key::Ctrl('g') => return None,
+                Key::Escape => {
+                    self.accept_match(buffer, found);
+                    return None;
+                }
+                key => {
+                    self.accept_match(buffer, found);
+                    return Some(key);
+                }
+            };
+
+            match from.and_then(|from| SearchMatch::find(&self.history, &query, from, direction)) {
+                Some(result) => {
+                    found = Some(result);
+                    failed = false;
+                }
+                None => failed = !query.is_empty(),
+            }
+            if query.is_empty() {
+                found = None;
+            }
+        }
+    }
+
+    /// Replaces the buffer with the matched history entry and moves the cursor to the match.
+    fn accept_match(&mut self, buffer: &mut String, found: Option<SearchMatch>) {
+        if let Some(found) = found {
+            *buffer = self.history.entries()[found.index].clone();
+            self.cursor_pos = found.pos;
+        }
+    }
+
+    /// Draws the search mini-prompt with the matched entry, highlighting the match.
+    fn draw_search(
+        &self,
+        query: &str,
+        found: Option<SearchMatch>,
+        failed: bool,
+        direction: SearchDirection,
+    ) {
+        let label = match (failed, direction) {
+            (false, SearchDirection::Backward) => "(reverse-i-search)",
+            (false, SearchDirection::Forward) => "(i-search)",
+            (true, SearchDirection::Backward) => "(failed reverse-i-search)",
+            (true, SearchDirection::Forward) => "(failed i-search)",
+        };
+        let prompt = format!("{}`{}': ", label, query);
+
+        let (before, matched, after) = match found {
+            Some(found) => found.split(&self.history.entries()[found.index]),
+            None => ("", "", ""),
+        };
+
+        // \x1b[7m turns on reverse video for the match, \x1b[27m turns it off.
+        print!(
+            "\r{}{}\x1b[7m{}\x1b[27m{}\x1b[K",
+            prompt, before, matched, after
+        );
+
+        let cursor_col = graphemes::display_width(&prompt) + graphemes::display_width(before) + 1;
+        print!("\r\x1b[{}G", cursor_col);
+
+        io::stdout().flush().unwrap();
+    }
+
+    /// Completes the word before the cursor.
+    /// Lists the candidates below the line if the completion is ambiguous.
+    fn complete(&mut self, buffer: &mut String, context: &CompletionContext) {
+        let completion = completion::complete(buffer, self.cursor_pos, context);
+
+        if let Some(replacement) = completion.replacement {
+            buffer.replace_range(completion.start..self.cursor_pos, &replacement);
+            self.cursor_pos = completion.start + replacement.len();
+        } else if !completion.candidates.is_empty() {
+            let width = Self::terminal_width(self.tty.as_raw_fd());
+            print!(
+                "\r\n{}",
+                completion::format_columns(&completion.candidates, width)
+            );
+        } else {
+            // ring the bell, there is nothing to complete.
+            print!("\x07");
+        }
+    }
+
+    /// Helper function to get the number of columns of the terminal.
+    fn terminal_width(fd: i32) -> usize {
+        unsafe {
+            let mut size = std::mem::zeroed::<libc::winsize>();
+            if libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) == 0 && size.ws_col > 0 {
+                size.ws_col as usize
+            } else {
+                DEFAULT_TERMINAL_WIDTH
+            }
+        }
+    }
+
+    /// Helper function to get terminal attributes.
+    fn get_termios(fd: i32) -> io::Result<termios> {
+        unsafe {
+            let mut term = std::mem::zeroed::<termios>();
+            if tcgetattr(fd, &mut term) != 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(term)
+            }
+        }
+    }
+
+    /// Helper function to set terminal attributes.
+    fn set_termios(fd: i32, term: &termios) -> io::Result<()> {
+        unsafe {
+            if tcsetattr(fd, TCSANOW, term) != 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns a modified termios with canonical mode and echo disabled.
+    /// Flow control and signal keys are disabled as well, so Ctrl-S and Ctrl-C reach the line editor.
+    fn disable_canonical_echo(mut term: termios) -> termios {
+        term.c_lflag &= !(ICANON | ECHO | ISIG);
+        term.c_iflag &= !IXON;
+        term
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, fs::File};
+
+    use super::{
+        CompletionContext, EditingMode, History, InputHandler, InputStatus, SearchDirection,
+        SearchMatch,
+    };
+    use crate::{app::variables::Variables, commands::CommandsRegistry};
+
+    #[test]
+    fn test_ctrl_c_discards_the_line() {
+        let registry = CommandsRegistry::default();
+        let variables = Variables::default();
+        let context = CompletionContext {
+            registry: &registry,
+            variables: &variables,
+        };
+
+        for mode in [EditingMode::Emacs, EditingMode::Vi] {
+            // the keys of two lines, the first one is discarded with Ctrl-C.
+            let path = env::temp_dir().join(format!("shellrs-keys-{:x}", rand::random::<u64>()));
+            fs::write(&path, "ls -l\x03echo hi\r").unwrap();
+            let mut handler =
+                InputHandler::with_tty(File::open(&path).unwrap(), History::new(10, None));
+
+            let mut buffer = String::new();
+            let status = handler.edit_line(&mut buffer, "> ", &context, mode);
+            assert_eq!(status, InputStatus::Interrupted);
+            assert_eq!(buffer, "");
+            assert_eq!(handler.cursor_pos, 0);
+
+            // the next prompt starts a new line.
+            let status = handler.edit_line(&mut buffer, "> ", &context, mode);
+            assert_eq!(status, InputStatus::Submitted);
+            assert_eq!(buffer, "echo hi");
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_failed_search_keeps_last_match() {
+        let mut history = History::new(10, None);
+        history.add("aé").unwrap();
+        history.add("éa b").unwrap();
+
+        // typing `a` then `x`, the failed search keeps the match of `a`.
+        let found = SearchMatch::find(&history, "a", 1, SearchDirection::Backward).unwrap();
+        assert_eq!(
+            SearchMatch::find(&history, "ax", found.index, SearchDirection::Backward),
+            None
+        );
+        assert_eq!(
+            found.split(&history.entries()[found.index]),
+            ("é", "a", " b")
+        );
+
+        let found = SearchMatch::find(&history, "aé", 1, SearchDirection::Backward).unwrap();
+        assert_eq!(found.index, 0);
+        assert_eq!(found.split("aé"), ("", "aé", ""));
+        assert_eq!(
+            SearchMatch::find(&history, "aéx", 0, SearchDirection::Backward),
+            None
+        );
+    }
+
+    #[test]
+    fn test_split_non_ascii_match() {
+        let found = SearchMatch {
+            index: 0,
+            pos: 1,
+            len: "é".len(),
+        };
+        assert_eq!(found.split("aéb"), ("a", "é", "b"));
+    }
+}
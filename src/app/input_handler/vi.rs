@@ -0,0 +1,1028 @@
+use std::ops::Range;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::{keys::Key, Action, InputHandler};
+use crate::app::{completion::CompletionContext, graphemes};
+
+/// Steady bar cursor, shown in insert mode.
+const INSERT_CURSOR: &str = "\x1b[6 q";
+/// Steady block cursor, shown in normal mode.
+const NORMAL_CURSOR: &str = "\x1b[2 q";
+/// The terminal's default cursor.
+const DEFAULT_CURSOR: &str = "\x1b[0 q";
+/// Largest count of a command, larger counts are clamped to it.
+/// It bounds the work of repeated commands like `p`, `u` and `.`.
+const MAX_COUNT: usize = 9999;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ViMode {
+    /// Typed characters are inserted.
+    #[default]
+    Insert,
+    /// Typed characters are commands.
+    Normal,
+}
+
+/// State of the vi editing mode.
+#[derive(Debug, Default)]
+pub struct ViState {
+    mode: ViMode,
+    /// Keys of the normal mode command that is being typed.
+    pending: Vec<Key>,
+    /// Keys of the change in progress, including the text typed in insert mode.
+    recording: Option<Vec<Key>>,
+    /// Keys of the last completed change, replayed by `.`.
+    last_change: Vec<Key>,
+    /// The line and the cursor position before each change, restored by `u`.
+    undo_stack: Vec<(String, usize)>,
+    /// Text deleted or yanked last, put back with `p` and `P`.
+    register: String,
+    /// The last `f`, `t`, `F` or `T` search, repeated by `;` and `,`.
+    last_find: Option<(FindKind, char)>,
+}
+
+impl ViState {
+    /// Prepares for editing a new line, which starts in insert mode.
+    pub fn start_line(&mut self) {
+        self.pending.clear();
+        self.recording = None;
+        self.undo_stack.clear();
+        self.set_mode(ViMode::Insert);
+    }
+
+    /// Switches the mode and changes the cursor shape to match it.
+    fn set_mode(&mut self, mode: ViMode) {
+        self.mode = mode;
+        match mode {
+            ViMode::Insert => print!("{}", INSERT_CURSOR),
+            ViMode::Normal => print!("{}", NORMAL_CURSOR),
+        }
+    }
+
+    /// Replaces `;` and `,` with the find they repeat and remembers finds for them.
+    fn resolve_motion(&mut self, motion: Motion) -> Option<Motion> {
+        match motion {
+            Motion::RepeatFind => {
+                let (kind, c) = self.last_find?;
+                Some(Motion::Find(kind, c))
+            }
+            Motion::RepeatFindReverse => {
+                let (kind, c) = self.last_find?;
+                Some(Motion::Find(kind.reversed(), c))
+            }
+            Motion::Find(kind, c) => {
+                self.last_find = Some((kind, c));
+                Some(motion)
+            }
+            motion => Some(motion),
+        }
+    }
+}
+
+/// Restores the default cursor shape of the terminal.
+pub fn reset_cursor_shape() {
+    print!("{}", DEFAULT_CURSOR);
+}
+
+/// The `f`, `t`, `F` and `T` searches for a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FindKind {
+    /// `f`: onto the next occurrence.
+    Forward,
+    /// `t`: up to the next occurrence.
+    Till,
+    /// `F`: onto the previous occurrence.
+    Backward,
+    /// `T`: back to just after the previous occurrence.
+    BackTill,
+}
+
+impl FindKind {
+    fn reversed(self) -> Self {
+        match self {
+            FindKind::Forward => FindKind::Backward,
+            FindKind::Till => FindKind::BackTill,
+            FindKind::Backward => FindKind::Forward,
+            FindKind::BackTill => FindKind::Till,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Motion {
+    Left,
+    Right,
+    /// `w` and `W`.
+    WordForward {
+        big: bool,
+    },
+    /// `b` and `B`.
+    WordBackward {
+        big: bool,
+    },
+    /// `e` and `E`.
+    WordEnd {
+        big: bool,
+    },
+    /// `0`.
+    LineStart,
+    /// `^`.
+    FirstNonBlank,
+    /// `$`.
+    LineEnd,
+    Find(FindKind, char),
+    /// `;`.
+    RepeatFind,
+    /// `,`.
+    RepeatFindReverse,
+}
+
+impl Motion {
+    /// Returns `true` if an operator applied with the motion includes the target grapheme.
+    fn is_inclusive(self) -> bool {
+        matches!(
+            self,
+            Motion::WordEnd { .. }
+                | Motion::LineEnd
+                | Motion::Find(FindKind::Forward | FindKind::Till, _)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+/// Where `i`, `a`, `I` and `A` start inserting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InsertPosition {
+    Cursor,
+    AfterCursor,
+    LineStart,
+    LineEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Move(Motion),
+    /// An operator with a motion, or with the whole line (`dd`, `cc`, `yy`).
+    Operate(Operator, Option<Motion>),
+    Insert(InsertPosition),
+    /// `p`, or `P` to put before the cursor.
+    Put {
+        before: bool,
+    },
+    /// `r`.
+    Replace(char),
+    /// `~`.
+    ToggleCase,
+    Undo,
+    /// `.`.
+    Repeat,
+    HistoryPrevious,
+    HistoryNext,
+}
+
+impl Command {
+    /// Returns `true` if the command changes the line, so it can be undone and repeated.
+    fn is_change(self) -> bool {
+        matches!(
+            self,
+            Command::Operate(Operator::Delete | Operator::Change, _)
+                | Command::Insert(_)
+                | Command::Put { .. }
+                | Command::Replace(_)
+                | Command::ToggleCase
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ParsedCommand {
+    count: usize,
+    command: Command,
+}
+
+/// Result of parsing the keys typed so far.
+#[derive(Debug, PartialEq, Eq)]
+enum Parse<T> {
+    Complete(T),
+    /// More keys are needed.
+    Incomplete,
+    /// The keys are not a command.
+    Invalid,
+}
+
+impl<T> Parse<T> {
+    fn map<U>(self, f: impl FnOnce(T) -> U) -> Parse<U> {
+        match self {
+            Parse::Complete(value) => Parse::Complete(f(value)),
+            Parse::Incomplete => Parse::Incomplete,
+            Parse::Invalid => Parse::Invalid,
+        }
+    }
+}
+
+/// Parses a count at `keys[*i]`, a leading `0` is the `0` motion and not a count.
+/// The count is clamped to `MAX_COUNT`.
+fn parse_count(keys: &[Key], i: &mut usize) -> Option<usize> {
+    let mut count: Option<usize> = None;
+
+    while let Some(&Key::Char(c)) = keys.get(*i) {
+        let Some(digit) = c.to_digit(10) else {
+            break;
+        };
+        if digit == 0 && count.is_none() {
+            break;
+        }
+
+        count = Some(
+            count
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit as usize)
+                .min(MAX_COUNT),
+        );
+        *i += 1;
+    }
+
+    count
+}
+
+/// Parses the motion that starts with `key`, `rest` holds the keys typed after it.
+fn parse_motion(key: Key, rest: &[Key]) -> Parse<Motion> {
+    let motion = match key {
+        Key::Char('h') | Key::Left | Key::Backspace => Motion::Left,
+        Key::Char('l') | Key::Right | Key::Char(' ') => Motion::Right,
+        Key::Char('w') => Motion::WordForward { big: false },
+        Key::Char('W') => Motion::WordForward { big: true },
+        Key::Char('b') => Motion::WordBackward { big: false },
+        Key::Char('B') => Motion::WordBackward { big: true },
+        Key::Char('e') => Motion::WordEnd { big: false },
+        Key::Char('E') => Motion::WordEnd { big: true },
+        Key::Char('0') | Key::Home => Motion::LineStart,
+        Key::Char('^') => Motion::FirstNonBlank,
+        Key::Char('$') | Key::End => Motion::LineEnd,
+        Key::Char(';') => Motion::RepeatFind,
+        Key::Char(',') => Motion::RepeatFindReverse,
+        Key::Char(c @ ('f' | 't' | 'F' | 'T')) => {
+            let kind = match c {
+                'f' => FindKind::Forward,
+                't' => FindKind::Till,
+                'F' => FindKind::Backward,
+                _ => FindKind::BackTill,
+            };
+            return match rest.first() {
+                None => Parse::Incomplete,
+                Some(&Key::Char(target)) => Parse::Complete(Motion::Find(kind, target)),
+                Some(_) => Parse::Invalid,
+            };
+        }
+        _ => return Parse::Invalid,
+    };
+
+    Parse::Complete(motion)
+}
+
+/// Parses a normal mode command: `[count] command` or `[count] operator [count] motion`.
+fn parse_command(keys: &[Key]) -> Parse<ParsedCommand> {
+    let mut i = 0;
+    let count = parse_count(keys, &mut i);
+    let Some(&key) = keys.get(i) else {
+        return Parse::Incomplete;
+    };
+    let rest = &keys[i + 1..];
+
+    let operator = match key {
+        Key::Char('d') => Some(Operator::Delete),
+        Key::Char('c') => Some(Operator::Change),
+        Key::Char('y') => Some(Operator::Yank),
+        _ => None,
+    };
+
+    if let Some(operator) = operator {
+        let mut j = 0;
+        let motion_count = parse_count(rest, &mut j);
+        let Some(&motion_key) = rest.get(j) else {
+            return Parse::Incomplete;
+        };
+        let count = count
+            .unwrap_or(1)
+            .saturating_mul(motion_count.unwrap_or(1))
+            .min(MAX_COUNT);
+
+        // a doubled operator (`dd`, `cc`, `yy`) works on the whole line.
+        if motion_key == key {
+            let command = Command::Operate(operator, None);
+            return Parse::Complete(ParsedCommand { count, command });
+        }
+
+        return parse_motion(motion_key, &rest[j + 1..]).map(|motion| ParsedCommand {
+            count,
+            command: Command::Operate(operator, Some(motion)),
+        });
+    }
+
+    let count = count.unwrap_or(1);
+    match parse_motion(key, rest) {
+        Parse::Invalid => {}
+        parse => {
+            return parse.map(|motion| ParsedCommand {
+                count,
+                command: Command::Move(motion),
+            })
+        }
+    }
+
+    let command = match key {
+        Key::Char('x') => Command::Operate(Operator::Delete, Some(Motion::Right)),
+        Key::Char('X') => Command::Operate(Operator::Delete, Some(Motion::Left)),
+        Key::Char('D') => Command::Operate(Operator::Delete, Some(Motion::LineEnd)),
+        Key::Char('C') => Command::Operate(Operator::Change, Some(Motion::LineEnd)),
+        Key::Char('s') => Command::Operate(Operator::Change, Some(Motion::Right)),
+        Key::Char('S') => Command::Operate(Operator::Change, None),
+        Key::Char('Y') => Command::Operate(Operator::Yank, None),
+        Key::Char('i') => Command::Insert(InsertPosition::Cursor),
+        Key::Char('a') => Command::Insert(InsertPosition::AfterCursor),
+        Key::Char('I') => Command::Insert(InsertPosition::LineStart),
+        Key::Char('A') => Command::Insert(InsertPosition::LineEnd),
+        Key::Char('p') => Command::Put { before: false },
+        Key::Char('P') => Command::Put { before: true },
+        Key::Char('r') => {
+            return match rest.first() {
+                None => Parse::Incomplete,
+                Some(&Key::Char(c)) => Parse::Complete(ParsedCommand {
+                    count,
+                    command: Command::Replace(c),
+                }),
+                Some(_) => Parse::Invalid,
+            };
+        }
+        Key::Char('~') => Command::ToggleCase,
+        Key::Char('u') => Command::Undo,
+        Key::Char('.') => Command::Repeat,
+        Key::Char('k') | Key::Up => Command::HistoryPrevious,
+        Key::Char('j') | Key::Down => Command::HistoryNext,
+        _ => return Parse::Invalid,
+    };
+
+    Parse::Complete(ParsedCommand { count, command })
+}
+
+/// Kinds of graphemes that words are made of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Blank,
+    /// Letters, digits and underscores.
+    Word,
+    /// Any other non-blank character.
+    Punctuation,
+}
+
+/// The graphemes of a line, motions work on grapheme indices.
+struct Line<'a> {
+    text: &'a str,
+    /// Byte offsets of the graphemes.
+    starts: Vec<usize>,
+}
+
+impl<'a> Line<'a> {
+    fn new(text: &'a str) -> Self {
+        let starts = text.grapheme_indices(true).map(|(i, _)| i).collect();
+        Self { text, starts }
+    }
+
+    fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Returns the index of the grapheme at byte offset `pos`, the length at the end of the line.
+    fn index_of(&self, pos: usize) -> usize {
+        self.starts.partition_point(|&start| start < pos)
+    }
+
+    /// Returns the byte offset of the grapheme at `index`, the text length past the end.
+    fn offset(&self, index: usize) -> usize {
+        self.starts.get(index).copied().unwrap_or(self.text.len())
+    }
+
+    fn grapheme(&self, index: usize) -> &str {
+        &self.text[self.offset(index)..self.offset(index + 1)]
+    }
+
+    /// Returns the class of the grapheme at `index`, with `big` words are any non-blanks.
+    fn class(&self, index: usize, big: bool) -> CharClass {
+        match self.grapheme(index).chars().next() {
+            Some(c) if c.is_whitespace() => CharClass::Blank,
+            _ if big => CharClass::Word,
+            Some(c) if c.is_alphanumeric() || c == '_' => CharClass::Word,
+            _ => CharClass::Punctuation,
+        }
+    }
+
+    /// `w`: the start of the next word.
+    fn next_word_start(&self, mut i: usize, big: bool) -> usize {
+        let n = self.len();
+        if i >= n {
+            return n;
+        }
+
+        let class = self.class(i, big);
+        if class != CharClass::Blank {
+            while i < n && self.class(i, big) == class {
+                i += 1;
+            }
+        }
+        while i < n && self.class(i, big) == CharClass::Blank {
+            i += 1;
+        }
+        i
+    }
+
+    /// `b`: the start of the current or previous word.
+    fn previous_word_start(&self, mut i: usize, big: bool) -> usize {
+        if i == 0 {
+            return 0;
+        }
+
+        i -= 1;
+        while i > 0 && self.class(i, big) == CharClass::Blank {
+            i -= 1;
+        }
+        let class = self.class(i, big);
+        while i > 0 && self.class(i - 1, big) == class {
+            i -= 1;
+        }
+        i
+    }
+
+    /// `e`: the end of the current or next word.
+    fn word_end(&self, i: usize, big: bool) -> usize {
+        let n = self.len();
+        if i + 1 >= n {
+            return i.min(n.saturating_sub(1));
+        }
+
+        let mut i = i + 1;
+        while i < n && self.class(i, big) == CharClass::Blank {
+            i += 1;
+        }
+        if i == n {
+            return n - 1;
+        }
+        self.end_of_run(i, big)
+    }
+
+    /// The last grapheme of the run of graphemes of the same class that contains `i`.
+    fn end_of_run(&self, mut i: usize, big: bool) -> usize {
+        let class = self.class(i, big);
+        while i + 1 < self.len() && self.class(i + 1, big) == class {
+            i += 1;
+        }
+        i
+    }
+
+    /// Finds the `count`-th occurrence of `c` in the direction of the search.
+    fn find(&self, i: usize, kind: FindKind, c: char, count: usize) -> Option<usize> {
+        let target = c.to_string();
+        let matches = |index: &usize| self.grapheme(*index) == target;
+
+        let found = match kind {
+            FindKind::Forward | FindKind::Till => {
+                (i + 1..self.len()).filter(matches).nth(count - 1)?
+            }
+            FindKind::Backward | FindKind::BackTill => {
+                (0..i).rev().filter(matches).nth(count - 1)?
+            }
+        };
+
+        Some(match kind {
+            FindKind::Forward | FindKind::Backward => found,
+            FindKind::Till => found - 1,
+            FindKind::BackTill => found + 1,
+        })
+    }
+
+    /// Returns the grapheme index a motion moves to from index `i`.
+    fn target(&self, i: usize, motion: Motion, count: usize) -> Option<usize> {
+        // a word motion moves at least one grapheme, so more steps than graphemes change nothing.
+        let steps = count.min(self.len());
+        let repeat = |step: &dyn Fn(usize) -> usize| (0..steps).fold(i, |i, _| step(i));
+
+        let target = match motion {
+            Motion::Left => i.saturating_sub(count),
+            Motion::Right => i.saturating_add(count).min(self.len()),
+            Motion::WordForward { big } => repeat(&|i| self.next_word_start(i, big)),
+            Motion::WordBackward { big } => repeat(&|i| self.previous_word_start(i, big)),
+            Motion::WordEnd { big } => repeat(&|i| self.word_end(i, big)),
+            Motion::LineStart => 0,
+            Motion::FirstNonBlank => (0..self.len())
+                .find(|&i| self.class(i, true) != CharClass::Blank)
+                .unwrap_or(self.len()),
+            Motion::LineEnd => self.len().saturating_sub(1),
+            Motion::Find(kind, c) => self.find(i, kind, c, count)?,
+            Motion::RepeatFind | Motion::RepeatFindReverse => return None,
+        };
+
+        Some(target)
+    }
+}
+
+/// Returns the byte offset a motion moves the cursor to.
+fn motion_target(text: &str, cursor: usize, motion: Motion, count: usize) -> Option<usize> {
+    let line = Line::new(text);
+    let target = line.target(line.index_of(cursor), motion, count)?;
+    Some(line.offset(target))
+}
+
+/// Returns the byte range an operator with a motion works on.
+fn operator_range(
+    text: &str,
+    cursor: usize,
+    operator: Operator,
+    motion: Motion,
+    count: usize,
+) -> Option<Range<usize>> {
+    let line = Line::new(text);
+    let i = line.index_of(cursor);
+
+    // `cw` on a word changes to the end of the word and keeps the following blanks.
+    let (target, inclusive) = match motion {
+        Motion::WordForward { big }
+            if operator == Operator::Change
+                && i < line.len()
+                && line.class(i, big) != CharClass::Blank =>
+        {
+            let first = line.end_of_run(i, big);
+            let steps = count.min(line.len());
+            let target = (1..steps).fold(first, |i, _| line.word_end(i, big));
+            (target, true)
+        }
+        motion => (line.target(i, motion, count)?, motion.is_inclusive()),
+    };
+
+    let (start, end) = (i.min(target), i.max(target));
+    let end = if inclusive { end + 1 } else { end };
+    Some(line.offset(start)..line.offset(end))
+}
+
+/// Keeps the cursor on a grapheme in normal mode, it can't be past the end of the line.
+fn clamp_normal(text: &str, pos: usize) -> usize {
+    if pos >= text.len() {
+        graphemes::previous_boundary(text, text.len())
+    } else {
+        pos
+    }
+}
+
+/// Rings the terminal bell.
+fn bell() {
+    print!("\x07");
+}
+
+impl InputHandler {
+    /// Handles a key with the vi bindings.
+    pub(super) fn handle_vi_key(
+        &mut self,
+        key: Key,
+        buffer: &mut String,
+        completion_context: &CompletionContext,
+    ) -> Action {
+        if self.vi.mode == ViMode::Insert {
+            return self.handle_vi_insert_key(key, buffer, completion_context);
+        }
+
+        match key {
+            Key::Enter => return Action::Accept,
+            Key::Ctrl('c') => return Action::Interrupt,
+            Key::Ctrl('d') if buffer.is_empty() => return Action::Eof,
+            Key::Ctrl('r') | Key::Ctrl('s') => {
+                self.vi.pending.clear();
+                if let Some(key) = self.search_history(buffer, key) {
+                    return self.handle_vi_key(key, buffer, completion_context);
+                }
+                self.cursor_pos = clamp_normal(buffer, self.cursor_pos);
+                return Action::Continue;
+            }
+            Key::Escape => {
+                // Esc cancels a partially typed command.
+                if self.vi.pending.is_empty() {
+                    bell();
+                }
+                self.vi.pending.clear();
+                return Action::Continue;
+            }
+            _ => {}
+        }
+
+        self.vi.pending.push(key);
+        let parsed = match parse_command(&self.vi.pending) {
+            Parse::Complete(parsed) => parsed,
+            Parse::Incomplete => return Action::Continue,
+            Parse::Invalid => {
+                self.vi.pending.clear();
+                bell();
+                return Action::Continue;
+            }
+        };
+
+        let keys = std::mem::take(&mut self.vi.pending);
+        if parsed.command.is_change() {
+            self.vi.undo_stack.push((buffer.clone(), self.cursor_pos));
+            self.vi.recording = Some(keys);
+        }
+
+        if !self.run_vi_command(parsed, buffer, completion_context) {
+            self.vi.recording = None;
+            bell();
+        }
+
+        // a change that entered insert mode is recorded until insert mode is left.
+        if self.vi.mode == ViMode::Normal {
+            if let Some(keys) = self.vi.recording.take() {
+                self.vi.last_change = keys;
+            }
+            self.cursor_pos = clamp_normal(buffer, self.cursor_pos);
+        }
+
+        Action::Continue
+    }
+
+    /// Handles a key in insert mode, where everything but Esc works like in emacs mode.
+    fn handle_vi_insert_key(
+        &mut self,
+        key: Key,
+        buffer: &mut String,
+        completion_context: &CompletionContext,
+    ) -> Action {
+        match key {
+            Key::Escape => {
+                if let Some(mut keys) = self.vi.recording.take() {
+                    keys.push(key);
+                    self.vi.last_change = keys;
+                }
+                self.vi.set_mode(ViMode::Normal);
+                self.cursor_pos = graphemes::previous_boundary(buffer, self.cursor_pos);
+                Action::Continue
+            }
+            // Esc typed quickly before another key arrives as an Alt key.
+            Key::Alt(c) => {
+                self.handle_vi_insert_key(Key::Escape, buffer, completion_context);
+                self.handle_vi_key(Key::Char(c), buffer, completion_context)
+            }
+            Key::AltBackspace => {
+                self.handle_vi_insert_key(Key::Escape, buffer, completion_context);
+                self.handle_vi_key(Key::Backspace, buffer, completion_context)
+            }
+            key => {
+                if let Some(keys) = &mut self.vi.recording {
+                    keys.push(key);
+                }
+                self.handle_emacs_key(key, buffer, completion_context)
+            }
+        }
+    }
+
+    /// Runs a normal mode command, returns `false` if it could not be applied.
+    fn run_vi_command(
+        &mut self,
+        parsed: ParsedCommand,
+        buffer: &mut String,
+        completion_context: &CompletionContext,
+    ) -> bool {
+        let ParsedCommand { count, command } = parsed;
+
+        match command {
+            Command::Move(motion) => {
+                let Some(motion) = self.vi.resolve_motion(motion) else {
+                    return false;
+                };
+                match motion_target(buffer, self.cursor_pos, motion, count) {
+                    Some(target) => self.cursor_pos = target,
+                    None => return false,
+                }
+            }
+            Command::Operate(operator, motion) => {
+                let range = match motion {
+                    None => 0..buffer.len(),
+                    Some(motion) => {
+                        let Some(motion) = self.vi.resolve_motion(motion) else {
+                            return false;
+                        };
+                        match operator_range(buffer, self.cursor_pos, operator, motion, count) {
+                            Some(range) => range,
+                            None => return false,
+                        }
+                    }
+                };
+
+                if !range.is_empty() {
+                    self.vi.register = buffer[range.clone()].to_string();
+                }
+                self.cursor_pos = range.start;
+                match operator {
+                    Operator::Yank => {}
+                    Operator::Delete => buffer.replace_range(range, ""),
+                    Operator::Change => {
+                        buffer.replace_range(range, "");
+                        self.vi.set_mode(ViMode::Insert);
+                    }
+                }
+            }
+            Command::Insert(position) => {
+                self.cursor_pos = match position {
+                    InsertPosition::Cursor => self.cursor_pos,
+                    InsertPosition::AfterCursor => {
+                        graphemes::next_boundary(buffer, self.cursor_pos)
+                    }
+                    InsertPosition::LineStart => {
+                        motion_target(buffer, 0, Motion::FirstNonBlank, 1).unwrap_or(0)
+                    }
+                    InsertPosition::LineEnd => buffer.len(),
+                };
+                self.vi.set_mode(ViMode::Insert);
+            }
+            Command::Put { before } => {
+                if self.vi.register.is_empty() {
+                    return false;
+                }
+
+                let pos = if before {
+                    self.cursor_pos
+                } else {
+                    graphemes::next_boundary(buffer, self.cursor_pos)
+                };
+                let text = self.vi.register.repeat(count);
+                buffer.insert_str(pos, &text);
+                // the cursor ends up on the last put grapheme.
+                self.cursor_pos = graphemes::previous_boundary(buffer, pos + text.len());
+            }
+            Command::Replace(c) => {
+                let line = Line::new(buffer);
+                let i = line.index_of(self.cursor_pos);
+                if count > line.len() - i {
+                    return false;
+                }
+
+                let range = line.offset(i)..line.offset(i + count);
+                buffer.replace_range(range.clone(), &c.to_string().repeat(count));
+                self.cursor_pos = range.start + (count - 1) * c.len_utf8();
+            }
+            Command::ToggleCase => {
+                let line = Line::new(buffer);
+                let i = line.index_of(self.cursor_pos);
+                let range = line.offset(i)..line.offset(i.saturating_add(count).min(line.len()));
+
+                let toggled: String = buffer[range.clone()]
+                    .chars()
+                    .flat_map(|c| {
+                        if c.is_lowercase() {
+                            c.to_uppercase().collect::<Vec<_>>()
+                        } else {
+                            c.to_lowercase().collect()
+                        }
+                    })
+                    .collect();
+                buffer.replace_range(range.clone(), &toggled);
+                self.cursor_pos = range.start + toggled.len();
+            }
+            Command::Undo => {
+                let mut undone = false;
+                for _ in 0..count {
+                    // changes that left the line as it was are skipped.
+                    while let Some((text, cursor)) = self.vi.undo_stack.pop() {
+                        if text != *buffer {
+                            *buffer = text;
+                            self.cursor_pos = cursor;
+                            undone = true;
+                            break;
+                        }
+                    }
+                }
+                return undone;
+            }
+            Command::Repeat => {
+                let keys = self.vi.last_change.clone();
+                if keys.is_empty() {
+                    return false;
+                }
+
+                for _ in 0..count {
+                    for &key in &keys {
+                        self.handle_vi_key(key, buffer, completion_context);
+                    }
+                }
+            }
+            Command::HistoryPrevious | Command::HistoryNext => {
+                for _ in 0..count {
+                    let entry = if command == Command::HistoryPrevious {
+                        self.history.previous(buffer)
+                    } else {
+                        self.history.next()
+                    };
+                    match entry {
+                        Some(entry) => *buffer = entry.to_string(),
+                        None => return false,
+                    }
+                }
+                self.cursor_pos = 0;
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clamp_normal, motion_target, operator_range, parse_command, Command, FindKind, Key, Motion,
+        Operator, Parse, ParsedCommand, MAX_COUNT,
+    };
+
+    fn keys(text: &str) -> Vec<Key> {
+        text.chars().map(Key::Char).collect()
+    }
+
+    fn parse(text: &str) -> Parse<ParsedCommand> {
+        parse_command(&keys(text))
+    }
+
+    fn complete(count: usize, command: Command) -> Parse<ParsedCommand> {
+        Parse::Complete(ParsedCommand { count, command })
+    }
+
+    #[test]
+    fn test_parse_motions_and_counts() {
+        let w = Motion::WordForward { big: false };
+        assert_eq!(parse("w"), complete(1, Command::Move(w)));
+        assert_eq!(parse("3w"), complete(3, Command::Move(w)));
+        assert_eq!(parse("0"), complete(1, Command::Move(Motion::LineStart)));
+        assert_eq!(parse("10l"), complete(10, Command::Move(Motion::Right)));
+        assert_eq!(parse("f"), Parse::Incomplete);
+        assert_eq!(
+            parse("fx"),
+            complete(1, Command::Move(Motion::Find(FindKind::Forward, 'x')))
+        );
+        assert_eq!(parse("3"), Parse::Incomplete);
+        assert_eq!(parse("Q"), Parse::Invalid);
+    }
+
+    #[test]
+    fn test_parse_operators() {
+        let w = Motion::WordForward { big: false };
+        assert_eq!(parse("d"), Parse::Incomplete);
+        assert_eq!(
+            parse("dw"),
+            complete(1, Command::Operate(Operator::Delete, Some(w)))
+        );
+        assert_eq!(
+            parse("2d3w"),
+            complete(6, Command::Operate(Operator::Delete, Some(w)))
+        );
+        assert_eq!(
+            parse("cc"),
+            complete(1, Command::Operate(Operator::Change, None))
+        );
+        assert_eq!(
+            parse("d$"),
+            complete(1, Command::Operate(Operator::Delete, Some(Motion::LineEnd)))
+        );
+        assert_eq!(parse("dt"), Parse::Incomplete);
+        assert_eq!(parse("dQ"), Parse::Invalid);
+        assert_eq!(
+            parse("x"),
+            complete(1, Command::Operate(Operator::Delete, Some(Motion::Right)))
+        );
+        assert_eq!(parse("rz"), complete(1, Command::Replace('z')));
+    }
+
+    #[test]
+    fn test_parse_huge_counts() {
+        let w = Motion::WordForward { big: false };
+        assert_eq!(
+            parse("999999999999w"),
+            complete(MAX_COUNT, Command::Move(w))
+        );
+        assert_eq!(
+            parse("99999999999999999999999l"),
+            complete(MAX_COUNT, Command::Move(Motion::Right))
+        );
+        assert_eq!(
+            parse("99999999999d99999999999w"),
+            complete(MAX_COUNT, Command::Operate(Operator::Delete, Some(w)))
+        );
+        assert_eq!(
+            parse("200d200w"),
+            complete(MAX_COUNT, Command::Operate(Operator::Delete, Some(w)))
+        );
+    }
+
+    #[test]
+    fn test_huge_counts_stop_at_line_end() {
+        let text = "echo foo bar";
+        let w = Motion::WordForward { big: false };
+        let e = Motion::WordEnd { big: false };
+        let b = Motion::WordBackward { big: false };
+        assert_eq!(motion_target(text, 0, w, usize::MAX), Some(text.len()));
+        assert_eq!(motion_target(text, 0, e, usize::MAX), Some(11));
+        assert_eq!(motion_target(text, 11, b, usize::MAX), Some(0));
+        assert_eq!(
+            motion_target(text, 5, Motion::Right, usize::MAX),
+            Some(text.len())
+        );
+        assert_eq!(motion_target(text, 5, Motion::Left, usize::MAX), Some(0));
+        assert_eq!(
+            operator_range(text, 5, Operator::Change, w, usize::MAX),
+            Some(5..12)
+        );
+        assert_eq!(
+            operator_range(text, 0, Operator::Delete, w, usize::MAX),
+            Some(0..12)
+        );
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let text = "echo foo.bar  baz";
+        let w = Motion::WordForward { big: false };
+        let big_w = Motion::WordForward { big: true };
+        assert_eq!(motion_target(text, 0, w, 1), Some(5));
+        assert_eq!(motion_target(text, 5, w, 1), Some(8));
+        assert_eq!(motion_target(text, 5, big_w, 1), Some(14));
+        assert_eq!(motion_target(text, 0, w, 10), Some(text.len()));
+
+        let b = Motion::WordBackward { big: false };
+        assert_eq!(motion_target(text, 14, b, 1), Some(9));
+        assert_eq!(
+            motion_target(text, 14, Motion::WordBackward { big: true }, 1),
+            Some(5)
+        );
+        assert_eq!(motion_target(text, 0, b, 1), Some(0));
+
+        let e = Motion::WordEnd { big: false };
+        assert_eq!(motion_target(text, 0, e, 1), Some(3));
+        assert_eq!(motion_target(text, 3, e, 1), Some(7));
+        assert_eq!(motion_target(text, 3, e, 2), Some(8));
+    }
+
+    #[test]
+    fn test_line_and_find_motions() {
+        let text = "  a-b-c";
+        assert_eq!(motion_target(text, 4, Motion::LineStart, 1), Some(0));
+        assert_eq!(motion_target(text, 4, Motion::FirstNonBlank, 1), Some(2));
+        assert_eq!(motion_target(text, 0, Motion::LineEnd, 1), Some(6));
+
+        let find = |kind| Motion::Find(kind, '-');
+        assert_eq!(motion_target(text, 2, find(FindKind::Forward), 1), Some(3));
+        assert_eq!(motion_target(text, 2, find(FindKind::Forward), 2), Some(5));
+        assert_eq!(motion_target(text, 2, find(FindKind::Till), 2), Some(4));
+        assert_eq!(motion_target(text, 6, find(FindKind::Backward), 1), Some(5));
+        assert_eq!(motion_target(text, 6, find(FindKind::BackTill), 1), Some(6));
+        assert_eq!(motion_target(text, 2, find(FindKind::Forward), 3), None);
+    }
+
+    #[test]
+    fn test_operator_ranges() {
+        let text = "echo foo bar";
+        let w = Motion::WordForward { big: false };
+        assert_eq!(operator_range(text, 5, Operator::Delete, w, 1), Some(5..9));
+        // `cw` keeps the blank after the word.
+        assert_eq!(operator_range(text, 5, Operator::Change, w, 1), Some(5..8));
+        assert_eq!(operator_range(text, 5, Operator::Change, w, 2), Some(5..12));
+        assert_eq!(
+            operator_range(text, 5, Operator::Delete, Motion::LineEnd, 1),
+            Some(5..12)
+        );
+        assert_eq!(
+            operator_range(
+                text,
+                5,
+                Operator::Delete,
+                Motion::WordBackward { big: false },
+                1
+            ),
+            Some(0..5)
+        );
+        assert_eq!(
+            operator_range(text, 11, Operator::Delete, Motion::Right, 1),
+            Some(11..12)
+        );
+    }
+
+    #[test]
+    fn test_unicode_motions() {
+        let text = "日本 ä";
+        assert_eq!(
+            motion_target(text, 0, Motion::WordForward { big: false }, 1),
+            Some(7)
+        );
+        assert_eq!(motion_target(text, 0, Motion::Right, 1), Some(3));
+        assert_eq!(clamp_normal(text, text.len()), 7);
+        assert_eq!(clamp_normal("", 0), 0);
+    }
+}
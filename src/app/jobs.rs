@@ -0,0 +1,558 @@
+use std::{
+    fs::File,
+    io,
+    os::fd::{AsRawFd, RawFd},
+};
+
+use libc::{pid_t, termios};
+
+use super::process;
+
+/// State of a single process of a job.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProcessState {
+    Running,
+    Stopped,
+    /// Terminated with the raw wait status.
+    Terminated(i32),
+}
+
+/// State of a job, derived from the states of its processes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    /// At least one process is still running.
+    Running,
+    /// No process is running and at least one is stopped.
+    Stopped,
+    /// Every process terminated, holds the raw wait status of the last one.
+    Done(i32),
+}
+
+impl JobState {
+    /// Returns the exit code for the state, `128 + signo` for a stopped job.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            JobState::Done(raw_status) => process::exit_code(raw_status),
+            JobState::Stopped => process::exit_code_for_signal(libc::SIGTSTP),
+            JobState::Running => 0,
+        }
+    }
+
+    /// Whether the last process was killed by SIGINT (Ctrl-C).
+    pub fn interrupted(self) -> bool {
+        matches!(self, JobState::Done(raw_status) if process::interrupted(raw_status))
+    }
+}
+
+/// A pipeline started by the shell, running in its own process group.
+pub struct Job {
+    /// The job number, `0` until the job is added to the job table.
+    pub id: usize,
+    /// The process group of the job, the pid of its first process.
+    pub pgid: pid_t,
+    /// Processes of the job, in pipeline order.
+    processes: Vec<(pid_t, ProcessState)>,
+    /// The command line of the job.
+    pub command: String,
+    /// Terminal modes of the job when it was stopped, restored when it is continued in the foreground.
+    termios: Option<termios>,
+    /// When the job was last started, stopped or continued, the newest is the current job.
+    last_active: u64,
+    /// Whether the last state change has been reported to the user.
+    notified: bool,
+}
+
+impl Job {
+    /// Creates a new running job.
+    pub fn new(pgid: pid_t, pids: Vec<pid_t>, command: String) -> Self {
+        Self {
+            id: 0,
+            pgid,
+            processes: pids
+                .into_iter()
+                .map(|pid| (pid, ProcessState::Running))
+                .collect(),
+            command,
+            termios: None,
+            last_active: 0,
+            notified: true,
+        }
+    }
+
+    /// Returns the state of the job.
+    pub fn state(&self) -> JobState {
+        let states = || self.processes.iter().map(|(_, state)| *state);
+
+        if states().any(|state| state == ProcessState::Running) {
+            JobState::Running
+        } else if states().any(|state| state == ProcessState::Stopped) {
+            JobState::Stopped
+        } else {
+            match states().next_back() {
+                Some(ProcessState::Terminated(raw_status)) => JobState::Done(raw_status),
+                _ => JobState::Done(0),
+            }
+        }
+    }
+
+    /// Returns the pids of the processes of the job.
+    pub fn pids(&self) -> impl Iterator<Item = pid_t> + '_ {
+        self.processes.iter().map(|(pid, _)| *pid)
+    }
+
+    /// Returns the exit code of the job, `128 + signo` while it is stopped.
+    pub fn exit_code(&self) -> i32 {
+        self.state().exit_code()
+    }
+
+    /// Formats the job like the `jobs` builtin lists it, `marker` is `+`, `-` or a space.
+    /// The `long` format includes the process group id.
+    pub fn format(&self, marker: char, long: bool) -> String {
+        let state = match self.state() {
+            JobState::Running => "Running".to_string(),
+            JobState::Stopped => "Stopped".to_string(),
+            JobState::Done(raw_status) if libc::WIFSIGNALED(raw_status) => {
+                signal_description(libc::WTERMSIG(raw_status))
+            }
+            JobState::Done(raw_status) => match process::exit_code(raw_status) {
+                0 => "Done".to_string(),
+                code => format!("Exit {}", code),
+            },
+        };
+        let background = if self.state() == JobState::Running {
+            " &"
+        } else {
+            ""
+        };
+
+        let pgid = if long {
+            format!("{} ", self.pgid)
+        } else {
+            String::new()
+        };
+
+        format!(
+            "[{}]{}  {}{:<24}{}{}",
+            self.id, marker, pgid, state, self.command, background
+        )
+    }
+
+    /// Records a state change reported by `waitpid`.
+    fn update_process(&mut self, pid: pid_t, raw_status: i32) {
+        let Some((_, state)) = self.processes.iter_mut().find(|(p, _)| *p == pid) else {
+            return;
+        };
+
+        *state = if libc::WIFSTOPPED(raw_status) {
+            ProcessState::Stopped
+        } else if libc::WIFCONTINUED(raw_status) {
+            ProcessState::Running
+        } else {
+            ProcessState::Terminated(raw_status)
+        };
+    }
+
+    /// Marks every stopped process as running again.
+    fn mark_continued(&mut self) {
+        for (_, state) in &mut self.processes {
+            if *state == ProcessState::Stopped {
+                *state = ProcessState::Running;
+            }
+        }
+    }
+
+    /// Waits for a state change of any process of the job.
+    /// Returns `false` if there is nothing left to wait for.
+    fn wait(&mut self, options: i32) -> bool {
+        let mut raw_status = 0;
+        let pid = loop {
+            let pid = unsafe { libc::waitpid(-self.pgid, &mut raw_status, options) };
+            if pid != -1 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                break pid;
+            }
+        };
+
+        match pid {
+            -1 => {
+                // the processes are gone, e.g. reaped by someone else.
+                for (_, state) in &mut self.processes {
+                    if !matches!(state, ProcessState::Terminated(_)) {
+                        *state = ProcessState::Terminated(0);
+                    }
+                }
+                false
+            }
+            0 => false,
+            pid => {
+                self.update_process(pid, raw_status);
+                true
+            }
+        }
+    }
+}
+
+/// Returns the description of a signal that terminated a job.
+fn signal_description(signal: i32) -> String {
+    match signal {
+        libc::SIGHUP => "Hangup".to_string(),
+        libc::SIGINT => "Interrupt".to_string(),
+        libc::SIGQUIT => "Quit".to_string(),
+        libc::SIGKILL => "Killed".to_string(),
+        libc::SIGSEGV => "Segmentation fault".to_string(),
+        libc::SIGPIPE => "Broken pipe".to_string(),
+        libc::SIGTERM => "Terminated".to_string(),
+        signal => format!("Signal {}", signal),
+    }
+}
+
+/// The jobs of the shell and the terminal they share.
+#[derive(Default)]
+pub struct JobTable {
+    jobs: Vec<Job>,
+    /// The controlling terminal, `None` if job control is disabled.
+    terminal: Option<File>,
+    /// The process group of the shell.
+    shell_pgid: pid_t,
+    /// Terminal modes of the shell, restored whenever it takes the terminal back.
+    shell_termios: Option<termios>,
+    /// Counter for `Job::last_active`.
+    activity: u64,
+}
+
+impl JobTable {
+    /// Puts the shell into its own process group and makes it the foreground process group
+    /// of the controlling terminal.
+    pub fn enable_job_control(&mut self) -> io::Result<()> {
+        let terminal = File::open("/dev/tty")?;
+
+        // fails if the shell already leads a session, which is fine.
+        let _ = process::set_process_group(0, 0);
+        self.shell_pgid = unsafe { libc::getpgrp() };
+        process::set_foreground_group(terminal.as_raw_fd(), self.shell_pgid)?;
+
+        self.shell_termios = get_termios(terminal.as_raw_fd());
+        self.terminal = Some(terminal);
+        Ok(())
+    }
+
+    /// Disables job control, forked children of the shell don't do it themselves.
+    pub fn disable_job_control(&mut self) {
+        self.terminal = None;
+    }
+
+    /// Returns `true` if the shell controls the terminal.
+    pub fn job_control(&self) -> bool {
+        self.terminal.is_some()
+    }
+
+    /// Returns the descriptor of the controlling terminal if job control is enabled.
+    pub fn terminal_fd(&self) -> Option<RawFd> {
+        self.terminal.as_ref().map(File::as_raw_fd)
+    }
+
+    /// Adds a job to the table, giving it the next free job number.
+    /// Returns the job number.
+    pub fn add(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+        self.activity += 1;
+        job.last_active = self.activity;
+
+        let id = job.id;
+        let position = self.jobs.partition_point(|other| other.id < id);
+        self.jobs.insert(position, job);
+        id
+    }
+
+    /// Removes a job from the table.
+    pub fn remove(&mut self, id: usize) -> Option<Job> {
+        let position = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(position))
+    }
+
+    /// Returns the jobs, ordered by job number.
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Returns the marker of a job: `+` for the current job, `-` for the previous one.
+    pub fn marker(&self, id: usize) -> char {
+        let (current, previous) = self.current_and_previous();
+        if current == Some(id) {
+            '+'
+        } else if previous == Some(id) {
+            '-'
+        } else {
+            ' '
+        }
+    }
+
+    /// Returns the current and previous job numbers.
+    /// The current job is the most recently stopped job, or the most recently started one.
+    fn current_and_previous(&self) -> (Option<usize>, Option<usize>) {
+        let mut jobs: Vec<&Job> = self.jobs.iter().collect();
+        jobs.sort_by_key(|job| (job.state() == JobState::Stopped, job.last_active));
+
+        let mut ids = jobs.iter().rev().map(|job| job.id);
+        (ids.next(), ids.next())
+    }
+
+    /// Finds the job number for a job spec (`%n`, `%+`, `%%`, `%-`, `%prefix` or `%?text`).
+    /// Without a spec the current job is used.
+    pub fn find(&self, spec: Option<&str>) -> Result<usize, String> {
+        let (current, previous) = self.current_and_previous();
+        let Some(spec) = spec else {
+            return current.ok_or_else(|| "no current job".to_string());
+        };
+        let no_such_job = || format!("{}: no such job", spec);
+
+        let spec = spec.strip_prefix('%').unwrap_or(spec);
+
+        let found = match spec {
+            "" | "%" | "+" => current,
+            "-" => previous,
+            _ => {
+                if let Ok(id) = spec.parse::<usize>() {
+                    self.jobs.iter().find(|job| job.id == id).map(|job| job.id)
+                } else {
+                    let matching: Vec<usize> = match spec.strip_prefix('?') {
+                        Some(text) => self
+                            .jobs
+                            .iter()
+                            .filter(|job| job.command.contains(text))
+                            .map(|job| job.id)
+                            .collect(),
+                        None => self
+                            .jobs
+                            .iter()
+                            .filter(|job| job.command.starts_with(spec))
+                            .map(|job| job.id)
+                            .collect(),
+                    };
+                    match matching.as_slice() {
+                        [id] => Some(*id),
+                        [] => None,
+                        _ => return Err(format!("{}: ambiguous job spec", spec)),
+                    }
+                }
+            }
+        };
+
+        found.ok_or_else(no_such_job)
+    }
+
+    /// Returns the job that contains the process `pid`.
+    pub fn find_by_pid(&self, pid: pid_t) -> Option<usize> {
+        self.jobs
+            .iter()
+            .find(|job| job.pids().any(|p| p == pid))
+            .map(|job| job.id)
+    }
+
+    /// Runs a job in the foreground until it terminates or stops, and returns its state.
+    /// The job gets the terminal while it runs. A stopped job is added to the job table.
+    pub fn run_in_foreground(&mut self, mut job: Job) -> JobState {
+        if let Some(fd) = self.terminal_fd() {
+            let _ = process::set_foreground_group(fd, job.pgid);
+            if let Some(termios) = job.termios.take() {
+                set_termios(fd, &termios);
+            }
+        }
+
+        while job.state() == JobState::Running && job.wait(libc::WUNTRACED) {}
+
+        if let Some(fd) = self.terminal_fd() {
+            let _ = process::set_foreground_group(fd, self.shell_pgid);
+            if job.state() == JobState::Stopped {
+                job.termios = get_termios(fd);
+            }
+            if let Some(termios) = &self.shell_termios {
+                set_termios(fd, termios);
+            }
+        }
+
+        let state = job.state();
+        if state == JobState::Stopped {
+            let id = self.add(job);
+            let line = self.jobs[self.index(id)].format(self.marker(id), false);
+            println!("\n{}", line);
+        }
+        state
+    }
+
+    /// Continues a stopped job in the background.
+    pub fn continue_in_background(&mut self, id: usize) -> io::Result<()> {
+        let index = self.index(id);
+        process::continue_group(self.jobs[index].pgid)?;
+
+        self.activity += 1;
+        let job = &mut self.jobs[index];
+        job.mark_continued();
+        job.last_active = self.activity;
+        Ok(())
+    }
+
+    /// Continues a job in the foreground, see `run_in_foreground`.
+    pub fn continue_in_foreground(&mut self, id: usize) -> io::Result<JobState> {
+        let Some(mut job) = self.remove(id) else {
+            return Ok(JobState::Done(0));
+        };
+
+        println!("{}", job.command);
+        if let Err(err) = process::continue_group(job.pgid) {
+            self.add(job);
+            return Err(err);
+        }
+        job.mark_continued();
+
+        Ok(self.run_in_foreground(job))
+    }
+
+    /// Waits until the job terminates or stops and returns its exit code.
+    /// A terminated job is removed from the table.
+    pub fn wait_for(&mut self, id: usize) -> i32 {
+        let index = self.index(id);
+        let job = &mut self.jobs[index];
+        while job.state() == JobState::Running && job.wait(libc::WUNTRACED) {}
+
+        let code = job.exit_code();
+        if let JobState::Done(_) = job.state() {
+            self.jobs.remove(index);
+        } else {
+            job.notified = false;
+        }
+        code
+    }
+
+    /// Collects the state changes of all jobs without blocking.
+    pub fn update(&mut self) {
+        for job in &mut self.jobs {
+            let before = job.state();
+            while job.wait(libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED) {}
+            if job.state() != before {
+                job.notified = false;
+            }
+        }
+    }
+
+    /// Returns the report lines of jobs that changed their state since the last report.
+    /// Jobs that are done are removed after they have been reported.
+    pub fn take_notifications(&mut self) -> Vec<String> {
+        let lines = self
+            .jobs
+            .iter()
+            .filter(|job| !job.notified)
+            .map(|job| job.format(self.marker(job.id), false))
+            .collect();
+
+        self.mark_notified();
+        lines
+    }
+
+    /// Marks all state changes as reported and removes the jobs that are done.
+    pub fn mark_notified(&mut self) {
+        self.jobs
+            .retain(|job| !matches!(job.state(), JobState::Done(_)));
+        for job in &mut self.jobs {
+            job.notified = true;
+        }
+    }
+
+    fn index(&self, id: usize) -> usize {
+        self.jobs
+            .iter()
+            .position(|job| job.id == id)
+            .expect("job ids come from the job table")
+    }
+}
+
+fn get_termios(fd: RawFd) -> Option<termios> {
+    unsafe {
+        let mut term = std::mem::zeroed::<termios>();
+        (libc::tcgetattr(fd, &mut term) == 0).then_some(term)
+    }
+}
+
+fn set_termios(fd: RawFd, term: &termios) {
+    unsafe { libc::tcsetattr(fd, libc::TCSADRAIN, term) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(commands: &[&str]) -> JobTable {
+        let mut table = JobTable::default();
+        for (i, command) in commands.iter().enumerate() {
+            let pid = 1000 + i as pid_t;
+            table.add(Job::new(pid, vec![pid], command.to_string()));
+        }
+        table
+    }
+
+    #[test]
+    fn test_add_numbers_jobs() {
+        let mut table = table(&["sleep 1", "sleep 2"]);
+        assert_eq!(
+            table.jobs().iter().map(|job| job.id).collect::<Vec<_>>(),
+            [1, 2]
+        );
+
+        table.remove(1);
+        assert_eq!(table.add(Job::new(1, vec![1], "yes".to_string())), 3);
+    }
+
+    #[test]
+    fn test_current_and_previous() {
+        let mut table = table(&["sleep 1", "sleep 2", "sleep 3"]);
+        assert_eq!(table.marker(3), '+');
+        assert_eq!(table.marker(2), '-');
+        assert_eq!(table.marker(1), ' ');
+
+        // a stopped job becomes the current job.
+        table.jobs[0].processes[0].1 = ProcessState::Stopped;
+        assert_eq!(table.marker(1), '+');
+        assert_eq!(table.marker(3), '-');
+    }
+
+    #[test]
+    fn test_find() {
+        let table = table(&["sleep 10", "vim notes", "sleep 20"]);
+
+        assert_eq!(table.find(None), Ok(3));
+        assert_eq!(
+            JobTable::default().find(None),
+            Err("no current job".to_string())
+        );
+        assert_eq!(table.find(Some("%%")), Ok(3));
+        assert_eq!(table.find(Some("%+")), Ok(3));
+        assert_eq!(table.find(Some("%-")), Ok(2));
+        assert_eq!(table.find(Some("%1")), Ok(1));
+        assert_eq!(table.find(Some("2")), Ok(2));
+        assert_eq!(table.find(Some("%vim")), Ok(2));
+        assert_eq!(table.find(Some("%?20")), Ok(3));
+        assert_eq!(table.find(Some("%4")), Err("%4: no such job".to_string()));
+        assert_eq!(
+            table.find(Some("%sleep")),
+            Err("sleep: ambiguous job spec".to_string())
+        );
+        assert_eq!(table.find_by_pid(1001), Some(2));
+        assert_eq!(table.find_by_pid(42), None);
+    }
+
+    #[test]
+    fn test_format() {
+        let mut table = table(&["sleep 10"]);
+        assert_eq!(
+            table.jobs[0].format('+', false),
+            "[1]+  Running                 sleep 10 &"
+        );
+
+        table.jobs[0].processes[0].1 = ProcessState::Terminated(1 << 8);
+        assert_eq!(
+            table.jobs[0].format('+', true),
+            "[1]+  1000 Exit 1                  sleep 10"
+        );
+    }
+}
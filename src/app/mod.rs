@@ -1,9 +1,29 @@
+mod brace;
+mod completion;
 mod error;
+mod expansion;
+mod glob;
+mod graphemes;
+mod history;
+mod input;
 mod input_handler;
+mod jobs;
+mod options;
 mod output;
+mod pattern;
+pub mod process;
+pub mod quoting;
 mod shell;
+mod startup;
+mod variables;
 
+pub use completion::{ArgCompletion, CompletionContext};
 pub use error::ShellError;
-pub use input_handler::InputHandler;
+pub use history::History;
+pub use input::ShellInput;
+pub use input_handler::{InputHandler, InputStatus};
+pub use jobs::{Job, JobState};
+pub use options::{EditingMode, ShellOptions};
 pub use output::ShellOutput;
-pub use shell::Shell;
+pub use shell::{ControlFlow, Shell};
+pub use variables::Variables;
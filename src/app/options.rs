@@ -0,0 +1,57 @@
+/// Key bindings used by the line editor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EditingMode {
+    /// Readline-style bindings (Ctrl-A, Ctrl-K, ...).
+    #[default]
+    Emacs,
+    /// Modal editing with insert and normal modes.
+    Vi,
+}
+
+/// Options toggled with `set -o name` and `set +o name`.
+#[derive(Debug, Default)]
+pub struct ShellOptions {
+    pub editing_mode: EditingMode,
+    /// Patterns match names starting with `.` too.
+    pub dotglob: bool,
+    /// A pattern without matches is an error.
+    pub failglob: bool,
+    /// Patterns are not expanded.
+    pub noglob: bool,
+    /// A pattern without matches expands to nothing instead of itself.
+    pub nullglob: bool,
+}
+
+impl ShellOptions {
+    /// Names of all options, in the order they are listed by `set -o`.
+    pub const NAMES: [&'static str; 6] =
+        ["dotglob", "emacs", "failglob", "noglob", "nullglob", "vi"];
+
+    /// Returns whether the named option is enabled, `None` if there is no such option.
+    pub fn get(&self, name: &str) -> Option<bool> {
+        match name {
+            "emacs" => Some(self.editing_mode == EditingMode::Emacs),
+            "vi" => Some(self.editing_mode == EditingMode::Vi),
+            "dotglob" => Some(self.dotglob),
+            "failglob" => Some(self.failglob),
+            "noglob" => Some(self.noglob),
+            "nullglob" => Some(self.nullglob),
+            _ => None,
+        }
+    }
+
+    /// Enables or disables the named option, returns `false` if there is no such option.
+    /// `emacs` and `vi` exclude each other, disabling one of them enables the other.
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        match (name, enabled) {
+            ("emacs", true) | ("vi", false) => self.editing_mode = EditingMode::Emacs,
+            ("vi", true) | ("emacs", false) => self.editing_mode = EditingMode::Vi,
+            ("dotglob", _) => self.dotglob = enabled,
+            ("failglob", _) => self.failglob = enabled,
+            ("noglob", _) => self.noglob = enabled,
+            ("nullglob", _) => self.nullglob = enabled,
+            _ => return false,
+        }
+        true
+    }
+}
@@ -1,16 +1,17 @@
 use std::{
     fs::File,
-    io::{self, stderr, stdout, StderrLock, StdoutLock, Write},
+    io::{self, stderr, stdout, IsTerminal, StderrLock, StdoutLock, Write},
+    os::fd::AsFd,
     process::Stdio,
 };
 
 /// The output type of the shell.
-/// Can be either a standard output, a standard error or a file.
+/// Can be either a standard output, a standard error, a file or closed (`>&-`).
 pub enum ShellOutput {
     Stdout(StdoutLock<'static>),
     Stderr(StderrLock<'static>),
-    #[allow(dead_code)]
     File(File),
+    Closed,
 }
 
 impl ShellOutput {
@@ -22,21 +23,40 @@ impl ShellOutput {
         ShellOutput::Stderr(stderr().lock())
     }
 
-    #[allow(dead_code)]
-    pub fn file(path: String) -> Self {
-        ShellOutput::File(File::create(path).unwrap())
+    /// Writes a string followed by a newline to the output.
+    /// Writing to a closed output fails with `EBADF`.
+    pub fn writeln(&mut self, s: &str) -> io::Result<()> {
+        writeln!(self, "{}", s)
     }
 
-    /// Writes a string to the output.
-    pub fn writeln(&mut self, s: &str) {
-        writeln!(self, "{}", s).expect("should be able to write");
+    /// Returns `true` if the output is a terminal.
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            ShellOutput::Stdout(writer) => writer.is_terminal(),
+            ShellOutput::Stderr(writer) => writer.is_terminal(),
+            ShellOutput::File(file) => file.is_terminal(),
+            ShellOutput::Closed => false,
+        }
+    }
+
+    /// Returns a new file handle for the same open file, used by `>&` and `<&`.
+    pub fn try_clone_file(&self) -> io::Result<File> {
+        match self {
+            ShellOutput::Stdout(_) => Ok(File::from(stdout().as_fd().try_clone_to_owned()?)),
+            ShellOutput::Stderr(_) => Ok(File::from(stderr().as_fd().try_clone_to_owned()?)),
+            ShellOutput::File(file) => file.try_clone(),
+            ShellOutput::Closed => Err(io::Error::from_raw_os_error(libc::EBADF)),
+        }
     }
 
     /// Converts the `ShellOutput` into a `Stdio`.
+    /// A closed output is inherited here, it has to be closed in the child process.
     pub fn as_stdio(&mut self) -> io::Result<Stdio> {
         match self {
             ShellOutput::File(ref mut file) => Ok(Stdio::from(file.try_clone()?)),
-            ShellOutput::Stdout(_) | ShellOutput::Stderr(_) => Ok(Stdio::inherit()),
+            ShellOutput::Stdout(_) | ShellOutput::Stderr(_) | ShellOutput::Closed => {
+                Ok(Stdio::inherit())
+            }
         }
     }
 }
@@ -47,6 +67,7 @@ impl Write for ShellOutput {
             ShellOutput::Stdout(ref mut writer) => writer.write(buf),
             ShellOutput::Stderr(ref mut writer) => writer.write(buf),
             ShellOutput::File(ref mut writer) => writer.write(buf),
+            ShellOutput::Closed => Err(io::Error::from_raw_os_error(libc::EBADF)),
         }
     }
 
@@ -55,6 +76,7 @@ impl Write for ShellOutput {
             ShellOutput::Stdout(ref mut writer) => writer.flush(),
             ShellOutput::Stderr(ref mut writer) => writer.flush(),
             ShellOutput::File(ref mut writer) => writer.flush(),
+            ShellOutput::Closed => Ok(()),
         }
     }
 }
@@ -0,0 +1,187 @@
+/// Returns `true` if the whole `text` matches the shell `pattern`.
+/// Supports `*`, `?`, bracket expressions (`[abc]`, `[a-z]`, `[!a]`, `[[:digit:]]`) and backslash escapes.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut pi, mut ti) = (0, 0);
+    // position of the last `*` in the pattern and the text position it currently stands for.
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while ti < text.len() {
+        if pi < pattern.len() {
+            if pattern[pi] == '*' {
+                pi += 1;
+                backtrack = Some((pi, ti));
+                continue;
+            }
+            if let Some(next) = match_single(&pattern, pi, text[ti]) {
+                pi = next;
+                ti += 1;
+                continue;
+            }
+        }
+
+        // let the last `*` swallow one more character.
+        match backtrack {
+            Some((star_pi, star_ti)) => {
+                pi = star_pi;
+                ti = star_ti + 1;
+                backtrack = Some((star_pi, ti));
+            }
+            None => return false,
+        }
+    }
+
+    pattern[pi..].iter().all(|&c| c == '*')
+}
+
+/// Matches a single non-`*` pattern element at `pi` against `c`.
+/// Returns the position of the next pattern element on success.
+fn match_single(pattern: &[char], pi: usize, c: char) -> Option<usize> {
+    match pattern[pi] {
+        '?' => Some(pi + 1),
+        '[' => match match_bracket(pattern, pi, c) {
+            Some((true, next)) => Some(next),
+            Some((false, _)) => None,
+            // an unterminated bracket expression is a literal `[`.
+            None => (c == '[').then_some(pi + 1),
+        },
+        '\\' if pi + 1 < pattern.len() => (pattern[pi + 1] == c).then_some(pi + 2),
+        literal => (literal == c).then_some(pi + 1),
+    }
+}
+
+/// Matches the bracket expression starting at `start` against `c`.
+/// Returns whether it matched and the position after the closing `]`,
+/// or `None` if the expression is not terminated.
+fn match_bracket(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start + 1;
+    let negate = matches!(pattern.get(i), Some('!' | '^'));
+    if negate {
+        i += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        let current = *pattern.get(i)?;
+        if current == ']' && !first {
+            return Some((matched != negate, i + 1));
+        }
+        first = false;
+
+        // character classes like `[:digit:]`.
+        if current == '[' && pattern.get(i + 1) == Some(&':') {
+            if let Some(len) = pattern[i + 2..].windows(2).position(|w| w == [':', ']']) {
+                let class: String = pattern[i + 2..i + 2 + len].iter().collect();
+                matched |= class_matches(&class, c);
+                i += len + 4;
+                continue;
+            }
+        }
+
+        let (low, next) = match current {
+            '\\' => (*pattern.get(i + 1)?, i + 2),
+            _ => (current, i + 1),
+        };
+        i = next;
+
+        // ranges like `a-z`, a `-` right before the closing `]` is a literal.
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).is_some_and(|&c| c != ']') {
+            let (high, next) = match pattern[i + 1] {
+                '\\' => (*pattern.get(i + 2)?, i + 3),
+                high => (high, i + 2),
+            };
+            i = next;
+            matched |= low <= c && c <= high;
+        } else {
+            matched |= low == c;
+        }
+    }
+}
+
+/// Returns `true` if `c` belongs to the named POSIX character class.
+fn class_matches(class: &str, c: char) -> bool {
+    match class {
+        "alnum" => c.is_alphanumeric(),
+        "alpha" => c.is_alphabetic(),
+        "blank" => c == ' ' || c == '\t',
+        "cntrl" => c.is_control(),
+        "digit" => c.is_ascii_digit(),
+        "graph" => !c.is_control() && !c.is_whitespace(),
+        "lower" => c.is_lowercase(),
+        "print" => !c.is_control(),
+        "punct" => c.is_ascii_punctuation(),
+        "space" => c.is_whitespace(),
+        "upper" => c.is_uppercase(),
+        "xdigit" => c.is_ascii_hexdigit(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn test_literal() {
+        assert!(matches("abc", "abc"));
+        assert!(!matches("abc", "abd"));
+        assert!(!matches("abc", "ab"));
+        assert!(matches("", ""));
+    }
+
+    #[test]
+    fn test_star() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "anything"));
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "main.rc"));
+        assert!(matches("a*b*c", "aXXbYYc"));
+        assert!(matches("a*b", "abbb"));
+        assert!(!matches("a*b", "abba"));
+    }
+
+    #[test]
+    fn test_question_mark() {
+        assert!(matches("?", "x"));
+        assert!(!matches("?", ""));
+        assert!(matches("f??", "foo"));
+        assert!(!matches("f??", "fooo"));
+    }
+
+    #[test]
+    fn test_bracket() {
+        assert!(matches("[abc]", "b"));
+        assert!(!matches("[abc]", "d"));
+        assert!(matches("[a-z]x", "qx"));
+        assert!(matches("[!a-z]", "Q"));
+        assert!(!matches("[^a-z]", "q"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[a-]", "-"));
+        assert!(matches("[[:digit:]][[:alpha:]]", "1a"));
+        assert!(!matches("[[:digit:]]", "a"));
+    }
+
+    #[test]
+    fn test_unterminated_bracket_is_literal() {
+        assert!(matches("[abc", "[abc"));
+        assert!(!matches("[abc", "a"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+        assert!(matches("a\\?", "a?"));
+        assert!(matches("[\\]]", "]"));
+    }
+
+    #[test]
+    fn test_unicode() {
+        assert!(matches("ü*", "über"));
+        assert!(matches("?", "ß"));
+    }
+}
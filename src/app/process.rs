@@ -0,0 +1,199 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    os::fd::{AsRawFd, FromRawFd, RawFd},
+};
+
+use libc::pid_t;
+
+/// The result of a successful `fork` call.
+pub enum Fork {
+    /// Returned in the newly created child process.
+    Child,
+    /// Returned in the parent process, holds the pid of the child.
+    Parent(pid_t),
+}
+
+/// Creates an anonymous pipe and returns its `(read, write)` ends.
+/// Both ends are marked close-on-exec, so they are not leaked into spawned programs.
+pub fn pipe() -> io::Result<(File, File)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let (read, write) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+    Ok((read, write))
+}
+
+/// Forks the current process.
+/// Buffered stdout is flushed first, so the child does not print it a second time.
+pub fn fork() -> io::Result<Fork> {
+    io::stdout().flush()?;
+
+    match unsafe { libc::fork() } {
+        -1 => Err(io::Error::last_os_error()),
+        0 => Ok(Fork::Child),
+        pid => Ok(Fork::Parent(pid)),
+    }
+}
+
+/// Makes `target` refer to the same open file as `file`.
+/// The duplicated descriptor is inherited by spawned programs.
+pub fn redirect_fd(file: &File, target: RawFd) -> io::Result<()> {
+    if unsafe { libc::dup2(file.as_raw_fd(), target) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Duplicates `file` to a close-on-exec descriptor numbered at least `min`.
+pub fn duplicate_above(file: &File, min: RawFd) -> io::Result<File> {
+    let fd = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, min) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Makes `target` refer to the same open file as `source`, or closes it if there is no source.
+/// Only makes async-signal-safe calls, so it can run in a child between `fork` and `exec`.
+pub fn set_fd(target: RawFd, source: Option<RawFd>) -> io::Result<()> {
+    let result = match source {
+        Some(source) => unsafe { libc::dup2(source, target) },
+        None => unsafe { libc::close(target) },
+    };
+    if result == -1 && source.is_some() {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Waits for the child with the given pid to terminate.
+/// Returns the raw wait status.
+pub fn wait_pid(pid: pid_t) -> io::Result<i32> {
+    let mut status = 0;
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, libc::WUNTRACED) } != -1 {
+            if !libc::WIFSTOPPED(status) {
+                return Ok(status);
+            }
+            // a stopped child could never be resumed, so it is continued right away.
+            unsafe { libc::kill(pid, libc::SIGCONT) };
+            continue;
+        }
+
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    }
+}
+
+/// Offset added to the signal number for processes killed by a signal.
+const SIGNAL_EXIT_OFFSET: i32 = 128;
+
+/// Exit code of a process killed by SIGINT (Ctrl-C).
+pub const INTERRUPTED_EXIT_CODE: i32 = exit_code_for_signal(libc::SIGINT);
+
+/// Signals the terminal sends for Ctrl-C, Ctrl-\ and Ctrl-Z,
+/// and to background process groups that read from or configure it.
+const TERMINAL_SIGNALS: [libc::c_int; 5] = [
+    libc::SIGINT,
+    libc::SIGQUIT,
+    libc::SIGTSTP,
+    libc::SIGTTIN,
+    libc::SIGTTOU,
+];
+
+/// Makes the interactive shell ignore the signals sent by the terminal.
+/// They still reach the commands in the foreground process group of the terminal.
+pub fn ignore_terminal_signals() {
+    for signal in TERMINAL_SIGNALS {
+        unsafe { libc::signal(signal, libc::SIG_IGN) };
+    }
+}
+
+/// Restores the default action of the signals sent by the terminal.
+/// Ignored signals stay ignored across `exec`, so this runs in every child before it runs a command.
+pub fn restore_terminal_signals() {
+    for signal in TERMINAL_SIGNALS {
+        unsafe { libc::signal(signal, libc::SIG_DFL) };
+    }
+}
+
+/// Converts a raw wait status into the exit code of the process.
+/// Processes killed by a signal report `128 + signo`, like in other shells.
+pub fn exit_code(raw_status: i32) -> i32 {
+    if libc::WIFEXITED(raw_status) {
+        libc::WEXITSTATUS(raw_status)
+    } else if libc::WIFSIGNALED(raw_status) {
+        exit_code_for_signal(libc::WTERMSIG(raw_status))
+    } else {
+        1
+    }
+}
+
+/// Whether a raw wait status is the one of a process killed by SIGINT (Ctrl-C).
+pub fn interrupted(raw_status: i32) -> bool {
+    libc::WIFSIGNALED(raw_status) && libc::WTERMSIG(raw_status) == libc::SIGINT
+}
+
+/// Returns the exit code reported for a process killed or stopped by a signal.
+pub const fn exit_code_for_signal(signal: i32) -> i32 {
+    SIGNAL_EXIT_OFFSET + signal
+}
+
+/// Moves the process `pid` (0 for the calling process) into the process group `pgid`.
+/// A `pgid` of 0 starts a new process group led by the process.
+pub fn set_process_group(pid: pid_t, pgid: pid_t) -> io::Result<()> {
+    if unsafe { libc::setpgid(pid, pgid) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Makes `pgid` the foreground process group of the terminal,
+/// which receives the signals of Ctrl-C and Ctrl-Z and may read from the terminal.
+pub fn set_foreground_group(terminal: RawFd, pgid: pid_t) -> io::Result<()> {
+    if unsafe { libc::tcsetpgrp(terminal, pgid) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Continues every process of a stopped process group.
+pub fn continue_group(pgid: pid_t) -> io::Result<()> {
+    if unsafe { libc::killpg(pgid, libc::SIGCONT) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Terminates a forked child process with the given status code.
+/// Flushes stdout and stderr, but skips the destructors and exit handlers inherited from the parent.
+pub fn exit_child(code: i32) -> ! {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+    unsafe { libc::_exit(code) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{exit_code, exit_code_for_signal, INTERRUPTED_EXIT_CODE};
+
+    #[test]
+    fn test_exit_code_exited() {
+        assert_eq!(exit_code(0), 0);
+        assert_eq!(exit_code(1 << 8), 1);
+        assert_eq!(exit_code(255 << 8), 255);
+    }
+
+    #[test]
+    fn test_exit_code_signaled() {
+        assert_eq!(exit_code(libc::SIGINT), INTERRUPTED_EXIT_CODE);
+        assert_eq!(INTERRUPTED_EXIT_CODE, 130);
+        assert_eq!(exit_code(libc::SIGKILL), 137);
+        assert_eq!(exit_code_for_signal(libc::SIGTSTP), 128 + libc::SIGTSTP);
+    }
+}
@@ -0,0 +1,61 @@
+/// Quotes a string so that the shell reads it back as a single word.
+/// Strings made only of characters without special meaning are returned unchanged.
+pub fn quote(s: &str) -> String {
+    let is_plain = |c: char| c.is_alphanumeric() || "_-./:,+@%=".contains(c);
+
+    if !s.is_empty() && s.chars().all(is_plain) {
+        return s.to_string();
+    }
+
+    // a single quote can't be escaped inside single quotes, so it is closed and reopened around it.
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::quote;
+    use crate::{app::Shell, parser};
+
+    const STRINGS: [&str; 8] = [
+        "plain",
+        "",
+        "two words",
+        "it's",
+        "'''",
+        "$HOME `x` \\ \"q\"",
+        "*?[a]~{b,c}",
+        "a;b|c&d>e<f\nnewline",
+    ];
+
+    #[test]
+    fn test_quote_plain() {
+        assert_eq!(quote("a-b_c/d.txt"), "a-b_c/d.txt");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_quote_parses_as_single_word() {
+        for s in STRINGS {
+            let quoted = quote(s);
+            let list = parser::parse(&format!("echo {}", quoted)).unwrap();
+            assert_eq!(list.items.len(), 1, "{:?}", quoted);
+
+            let parser::Command::Simple(command) = &list.items[0].and_or.first.commands[0] else {
+                panic!("not a simple command: {:?}", quoted);
+            };
+            let words: Vec<&str> = command.words.iter().map(|word| word.raw.as_str()).collect();
+            assert_eq!(words, ["echo", quoted.as_str()]);
+        }
+    }
+
+    #[test]
+    fn test_quote_round_trip() {
+        for s in STRINGS {
+            let mut shell = Shell::new();
+            let script = format!("v={}", quote(s));
+            shell.run_script(script, "test".to_string(), Vec::new());
+            assert_eq!(shell.variables.get("v"), Some(s));
+        }
+    }
+}
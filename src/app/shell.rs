@@ -1,73 +1,1015 @@
-use std::io::{self, Write};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    mem,
+    os::fd::RawFd,
+    path::Path,
+    sync::Arc,
+};
 
 use levenshtein::Levenshtein;
 
-use super::{InputHandler, ShellError, ShellOutput};
-use crate::{commands::CommandsRegistry, dprintln, dprintln_err};
+use super::{
+    history::DEFAULT_HISTORY_SIZE,
+    input,
+    jobs::{Job, JobState, JobTable},
+    options::ShellOptions,
+    pattern,
+    process::{self, Fork},
+    startup,
+    variables::Variables,
+    CompletionContext, History, InputHandler, InputStatus, ShellError, ShellInput, ShellOutput,
+};
+use crate::{
+    commands::{CommandsRegistry, FunctionCommand},
+    dprintln, dprintln_err,
+    parser::{
+        self, AndOr, CaseItem, Command, CommandList, CompoundCommand, Connector, ListItem,
+        ParseError, Pipeline, Redirection, RedirectionKind, SimpleCommand, Word,
+    },
+};
+
+/// The prompt for continuation lines, used if `PS2` is not set.
+const DEFAULT_PS2: &str = "> ";
+
+/// Maximum nesting of function calls, deeper calls fail instead of overflowing the stack.
+const MAX_FUNCTION_DEPTH: usize = 200;
+
+/// Reading input lines is only possible in the REPL, which creates the input handler.
+const INPUT_HANDLER_MISSING: &str = "input handler is created by run_repl";
+
+/// The original standard streams of the shell, saved while they are redirected.
+#[derive(Default)]
+struct SavedStreams {
+    stdin: Option<ShellInput>,
+    stdout: Option<ShellOutput>,
+    stderr: Option<ShellOutput>,
+    /// Descriptors above 2 with the files they had before, `None` if they were not open.
+    extra_fds: Vec<(RawFd, Option<File>)>,
+}
+
+/// A `break`, `continue` or `return` that is leaving the commands it was run in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlFlow {
+    /// Exits the given number of enclosing loops.
+    Break(usize),
+    /// Exits the given number of enclosing loops minus one and resumes the next iteration of the last one.
+    Continue(usize),
+    /// Exits the running function.
+    Return,
+    /// Exits the shell, after an error that ends a shell that is not interactive.
+    Exit,
+}
+
+/// What a loop does after running its condition or body.
+enum LoopFlow {
+    Proceed,
+    NextIteration,
+    Exit,
+}
 
 pub struct Shell {
+    /// The standard input of the shell.
+    pub stdin: ShellInput,
     /// The standard output of the shell.
     pub stdout: ShellOutput,
     /// The standard error output of the shell.
     pub stderr: ShellOutput,
+    /// Redirected file descriptors above 2, they are passed on to external commands.
+    pub extra_fds: BTreeMap<RawFd, File>,
     /// Registry of all registered commands (builtin and external).
     pub cmd_registry: CommandsRegistry,
+    /// Shell-local and exported variables.
+    pub variables: Variables,
+    /// Exit status of the last executed pipeline, available as `$?`.
+    pub last_status: i32,
+    /// Background and stopped jobs.
+    pub jobs: JobTable,
+    /// Options set with `set -o`.
+    pub options: ShellOptions,
+    /// Whether an external command replaces the shell process instead of running in a child.
+    /// Set in a forked pipeline stage that only runs a simple command.
+    pub exec_external: bool,
+    /// Number of loops running in the current function, `break` and `continue` only work inside one.
+    pub loop_depth: usize,
+    /// Number of functions that are running, `return` and `local` only work inside one.
+    pub function_depth: usize,
+    /// Number of files that are being sourced, `return` also leaves them.
+    pub source_depth: usize,
+    /// A pending `break`, `continue` or `return`, the running lists stop until it reaches its loop or function.
+    pub control_flow: Option<ControlFlow>,
+    /// Set when Ctrl-C kills a foreground command, the running commands stop up to the prompt.
+    interrupted: bool,
+    /// Whether the shell reads commands from the terminal, set by `run_repl`.
+    interactive: bool,
+    /// Aliases whose values are running, they are not expanded again.
+    active_aliases: Vec<String>,
+    /// The name of the shell or of the running script, available as `$0`.
+    pub(super) shell_name: String,
+    /// The positional parameters `$1`, `$2`, ..., the arguments of the running function or script.
+    pub positional_parameters: Vec<String>,
+    /// Exit status of the last command substitution of the current command,
+    /// it is the status of a command that only assigns variables.
+    substitution_status: Option<i32>,
+    /// The name of the running script or sourced file and the line of its running command,
+    /// error messages start with them.
+    source_location: Option<(String, usize)>,
     /// Buffer for storing user input.
     input_buffer: String,
-    input_handler: InputHandler,
+    /// The line editor, only created for interactive sessions by `run_repl`.
+    input_handler: Option<InputHandler>,
 }
 
 impl Shell {
     /// Creates a new instance of the `Shell` struct.
     pub fn new() -> Self {
+        let variables = Variables::from_env();
+
         Self {
+            stdin: ShellInput::stdin(),
             stdout: ShellOutput::stdout(),
             stderr: ShellOutput::stderr(),
+            extra_fds: BTreeMap::new(),
             cmd_registry: CommandsRegistry::default(),
+            input_handler: None,
+            variables,
+            last_status: 0,
+            jobs: JobTable::default(),
+            options: ShellOptions::default(),
+            exec_external: false,
+            loop_depth: 0,
+            function_depth: 0,
+            source_depth: 0,
+            control_flow: None,
+            interrupted: false,
+            interactive: false,
+            active_aliases: Vec::new(),
+            shell_name: env!("CARGO_PKG_NAME").to_string(),
+            positional_parameters: Vec::new(),
+            substitution_status: None,
+            source_location: None,
             input_buffer: String::new(),
-            input_handler: InputHandler::new(),
         }
     }
 
+    /// Creates the command history and loads the history file.
+    /// The file is `$HISTFILE` or `~/.shellrs_history`, the size is `$HISTSIZE`.
+    fn load_history(variables: &Variables) -> History {
+        let path = match variables.get("HISTFILE") {
+            Some(path) => Some(path.into()),
+            None => History::default_path(variables.get("HOME")),
+        };
+        let capacity = variables
+            .get("HISTSIZE")
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(DEFAULT_HISTORY_SIZE);
+
+        let mut history = History::new(capacity, path);
+        if let Err(err) = history.load() {
+            eprintln!("failed to load history: {}", err);
+        }
+        history
+    }
+
     /// Runs the shell REPL (Read-Eval-Print-Loop).
-    pub fn run_repl(&mut self) {
+    /// The profile is sourced first for a login shell, then the rc file.
+    /// The history is loaded afterwards, so `HISTFILE` and `HISTSIZE` may be set in them.
+    pub fn run_repl(&mut self, login: bool) {
         dprintln!("starting repl");
+        self.interactive = true;
+        process::ignore_terminal_signals();
+        if let Err(err) = self.jobs.enable_job_control() {
+            eprintln!("failed to enable job control: {}", err);
+        }
+
+        if login {
+            self.source_profile();
+        }
+        if let Some(path) = startup::rc_file(&self.variables) {
+            self.source_startup_file(&path);
+        }
+        self.input_handler = Some(InputHandler::new(Self::load_history(&self.variables)));
+
         loop {
+            self.report_jobs();
             self.handle_input();
-            if let Err(err) = self.eval() {
-                self.handle_eval_error(err);
-            }
+            self.run_input();
             self.input_buffer.clear();
         }
     }
 
-    /// Handles user input
-    fn handle_input(&mut self) {
-        let prompt = "> ";
+    /// Runs a script or a `-c` command string without prompts and returns the exit status of its last command.
+    /// `name` becomes `$0` and `args` the positional parameters.
+    pub fn run_script(&mut self, source: String, name: String, args: Vec<String>) -> i32 {
+        dprintln!("running script {}", name);
+        self.shell_name = name.clone();
+        self.positional_parameters = args;
+
+        self.run_source(&source, name);
+        self.last_status
+    }
+
+    /// Sources the profile of login shells if it exists.
+    pub fn source_profile(&mut self) {
+        if let Some(path) = startup::profile_file(&self.variables) {
+            self.source_startup_file(&path);
+        }
+    }
+
+    fn source_startup_file(&mut self, path: &Path) {
+        dprintln!("sourcing startup file {}", path.display());
+        if let Err(err) = self.source_file(path, Vec::new()) {
+            self.handle_eval_error(err);
+        }
+    }
+
+    /// Runs the commands of a file in the current shell and returns the exit status of the last one.
+    /// Non-empty `args` replace the positional parameters while the file runs.
+    pub fn source_file(&mut self, path: &Path, args: Vec<String>) -> Result<i32, ShellError> {
+        let source = fs::read_to_string(path).map_err(|err| {
+            ShellError::CommandExecutionFail(format!("{}: {}", path.display(), err))
+        })?;
+
+        let saved_parameters =
+            (!args.is_empty()).then(|| mem::replace(&mut self.positional_parameters, args));
+        self.source_depth += 1;
+
+        self.run_source(&source, path.display().to_string());
+
+        self.source_depth -= 1;
+        if let Some(parameters) = saved_parameters {
+            self.positional_parameters = parameters;
+        }
+        if self.control_flow == Some(ControlFlow::Return) {
+            self.control_flow = None;
+        }
+
+        Ok(self.last_status)
+    }
+
+    /// Runs a script one complete command at a time, so that a command runs before the next one is parsed.
+    /// Errors are prefixed with `name` and the line of the command.
+    /// A syntax error stops the script with status 2.
+    fn run_source(&mut self, source: &str, name: String) {
+        let saved_location = self.source_location.take();
+        let saved_input = mem::take(&mut self.input_buffer);
+        let mut lines = source.split_inclusive('\n');
+        let mut next_line = 1;
+
+        loop {
+            let start_line = next_line;
+            let mut command = String::new();
+            // lines are added while the command is incomplete, e.g. an open `if` or here-document.
+            let parsed = loop {
+                let Some(line) = lines.next() else {
+                    break parser::parse(&command);
+                };
+                command.push_str(line);
+                next_line += 1;
+
+                match parser::parse(&command) {
+                    Err(err) if err.is_incomplete() => continue,
+                    parsed => break parsed,
+                }
+            };
+
+            if let Err(err) = parsed {
+                let error_line = start_line + command[..err.span.start].matches('\n').count();
+                self.source_location = Some((name, error_line));
+                self.last_status = 2;
+                self.handle_eval_error(ShellError::ParsingFail(err.to_string()));
+                break;
+            }
+            if command.is_empty() {
+                break;
+            }
+
+            self.source_location = Some((name.clone(), start_line));
+            self.input_buffer = command;
+            self.run_input();
+
+            // `return` leaves a sourced file, Ctrl-C and fatal errors stop the script.
+            if self.control_flow.is_some() || self.interrupted {
+                break;
+            }
+        }
+
+        self.input_buffer = saved_input;
+        self.source_location = saved_location;
+    }
 
+    /// Evaluates the input buffer and reports errors that stop the whole input.
+    fn run_input(&mut self) {
+        if let Err(err) = self.eval() {
+            if !matches!(err, ShellError::EmptyInput) {
+                self.last_status = err.exit_status();
+            }
+            self.handle_eval_error(err);
+        }
+    }
+
+    /// Runs `command` in a forked child and returns its standard output without trailing newlines.
+    /// The exit status of the child becomes the last exit status.
+    pub(super) fn command_substitution(&mut self, command: &str) -> Result<String, ShellError> {
+        let exec_fail = |err: io::Error| ShellError::CommandExecutionFail(err.to_string());
+        let (mut read, write) = process::pipe().map_err(exec_fail)?;
+
+        match process::fork().map_err(exec_fail)? {
+            Fork::Child => {
+                drop(read);
+                self.jobs.disable_job_control();
+                process::restore_terminal_signals();
+
+                if let Err(err) = process::redirect_fd(&write, libc::STDOUT_FILENO) {
+                    self.handle_eval_error(exec_fail(err));
+                    process::exit_child(1);
+                }
+                drop(write);
+                self.stdout = ShellOutput::stdout();
+
+                self.input_buffer = command.to_string();
+                self.run_input();
+                process::exit_child(self.last_status);
+            }
+            Fork::Parent(pid) => {
+                drop(write);
+                let mut output = Vec::new();
+                let read_result = read.read_to_end(&mut output);
+
+                if let Ok(raw_status) = process::wait_pid(pid) {
+                    self.last_status = self.foreground_status(JobState::Done(raw_status));
+                    self.substitution_status = Some(self.last_status);
+                }
+                read_result.map_err(exec_fail)?;
+
+                let output = String::from_utf8_lossy(&output);
+                Ok(output.trim_end_matches('\n').to_string())
+            }
+        }
+    }
+
+    /// Reports background jobs that finished or stopped since the last prompt.
+    fn report_jobs(&mut self) {
+        self.jobs.update();
+        for line in self.jobs.take_notifications() {
+            println!("{}", line);
+        }
+    }
+
+    /// Handles user input.
+    /// While the command is incomplete (e.g. an unclosed quote or a trailing `|`),
+    /// more lines are read with the `PS2` prompt.
+    fn handle_input(&mut self) {
         self.print_shell_header();
+
+        let mut buffer = std::mem::take(&mut self.input_buffer);
+        let mut status = self.read_line(&mut buffer, "> ");
+
+        while status == InputStatus::Submitted
+            && parser::parse(&buffer).is_err_and(|err| err.is_incomplete())
+        {
+            let prompt = self.variables.get("PS2").unwrap_or(DEFAULT_PS2).to_string();
+            let mut line = String::new();
+            status = self.read_line(&mut line, &prompt);
+
+            buffer.push('\n');
+            buffer.push_str(&line);
+        }
+        self.input_buffer = buffer;
+
+        if status == InputStatus::Interrupted {
+            self.input_buffer.clear();
+            self.last_status = process::INTERRUPTED_EXIT_CODE;
+            return;
+        }
+
+        let input_handler = self.input_handler.as_mut().expect(INPUT_HANDLER_MISSING);
+        if let Err(err) = input_handler.history.add(&self.input_buffer) {
+            let _ = self
+                .stderr
+                .writeln(&format!("failed to save history: {}", err));
+        }
+    }
+
+    /// Prints the prompt and reads a line of input into `buffer`.
+    fn read_line(&mut self, buffer: &mut String, prompt: &str) -> InputStatus {
         print!("{}", prompt);
         io::stdout().flush().unwrap();
 
-        self.input_handler
-            .input_loop(&mut self.input_buffer, prompt);
+        let completion_context = CompletionContext {
+            registry: &self.cmd_registry,
+            variables: &self.variables,
+        };
+        let input_handler = self.input_handler.as_mut().expect(INPUT_HANDLER_MISSING);
+        input_handler.input_loop(
+            buffer,
+            prompt,
+            &completion_context,
+            self.options.editing_mode,
+        )
     }
 
     /// Evaluates the current input stored in `self.input_buffer`.
     fn eval(&mut self) -> Result<(), ShellError> {
         dprintln!("eval input: {:?}", self.input_buffer);
-        let tokens = self.parse_shell_input();
-        dprintln!("parsed tokens: {:?}", tokens);
+        let list = parser::parse(&self.input_buffer).map_err(|err| self.syntax_error(err))?;
+        dprintln!("command list: {:?}", list);
 
-        if tokens.is_empty() {
+        if list.is_empty() {
             return Err(ShellError::EmptyInput);
         }
 
-        let (command_tokens, stdout_redirect, stderr_redirect) =
-            self.process_redirections(tokens)?;
+        self.interrupted = false;
+        self.run_list(&list);
+        if self.interrupted {
+            println!();
+        }
+
+        Ok(())
+    }
+
+    /// Converts a parse error into a shell error.
+    /// The line of the error is included if the input has more than one line.
+    fn syntax_error(&self, err: ParseError) -> ShellError {
+        let source = &self.input_buffer;
+        if !source.trim_end().contains('\n') {
+            return ShellError::ParsingFail(err.to_string());
+        }
+
+        let line = source[..err.span.start].matches('\n').count() + 1;
+        ShellError::ParsingFail(format!("line {}: {}", line, err))
+    }
+
+    /// Runs the and-or lists of a command list and returns the exit status of the last one.
+    fn run_list(&mut self, list: &CommandList) -> i32 {
+        for item in &list.items {
+            self.last_status = if item.background {
+                self.run_in_background(&item.and_or)
+                    .unwrap_or_else(|err| self.report_error(err))
+            } else {
+                self.run_and_or(&item.and_or)
+            };
+
+            // Ctrl-C stops the whole command list, not only the running command.
+            if self.interrupted || self.control_flow.is_some() {
+                break;
+            }
+        }
+
+        self.last_status
+    }
+
+    /// Runs the pipelines of an and-or list depending on the exit status of the previous one.
+    fn run_and_or(&mut self, and_or: &AndOr) -> i32 {
+        self.last_status = self.run_pipeline_reporting_errors(&and_or.first);
+
+        for (connector, pipeline) in &and_or.rest {
+            if self.interrupted || self.control_flow.is_some() {
+                break;
+            }
+            let should_run = match connector {
+                Connector::And => self.last_status == 0,
+                Connector::Or => self.last_status != 0,
+            };
+            if should_run {
+                self.last_status = self.run_pipeline_reporting_errors(pipeline);
+            }
+        }
+
+        self.last_status
+    }
+
+    /// Runs a pipeline, an error is reported and turned into its exit status.
+    fn run_pipeline_reporting_errors(&mut self, pipeline: &Pipeline) -> i32 {
+        let status = self
+            .run_pipeline(pipeline)
+            .unwrap_or_else(|err| self.report_error(err));
+        dprintln!("exit status: {}", status);
+        status
+    }
+
+    /// Reports an error of a command and returns the exit status for it.
+    /// Builtins use it for errors that don't stop them, like one of several names that is missing.
+    pub fn report_error(&mut self, err: ShellError) -> i32 {
+        let status = err.exit_status();
+        self.handle_eval_error(err);
+        status
+    }
+
+    /// Runs a pipeline in the foreground and returns the exit status of its last command,
+    /// inverted if the pipeline starts with `!`.
+    /// A single command runs in the shell process itself, so builtins like `cd` can change its state.
+    fn run_pipeline(&mut self, pipeline: &Pipeline) -> Result<i32, ShellError> {
+        let result = match pipeline.commands.as_slice() {
+            [command] => self.run_command(command),
+            commands => self.run_job(commands, pipeline.to_string(), false),
+        };
+        if !pipeline.negated {
+            return result;
+        }
+
+        let status = result.unwrap_or_else(|err| self.report_error(err));
+        Ok(i32::from(status == 0))
+    }
+
+    /// Runs an and-or list in the background as a job, its exit status is zero.
+    fn run_in_background(&mut self, and_or: &AndOr) -> Result<i32, ShellError> {
+        if and_or.rest.is_empty() {
+            let pipeline = &and_or.first;
+            return self.run_job(&pipeline.commands, pipeline.to_string(), true);
+        }
+
+        // the whole and-or list runs in one forked child, like a brace group.
+        let group = Command::Compound(
+            CompoundCommand::BraceGroup(CommandList {
+                items: vec![ListItem {
+                    and_or: and_or.clone(),
+                    background: false,
+                }],
+            }),
+            Vec::new(),
+        );
+        self.run_job(&[group], and_or.to_string(), true)
+    }
+
+    /// Forks the commands of a pipeline and returns the exit status of the last one.
+    /// A background pipeline is added to the job table and its exit status is zero.
+    fn run_job(
+        &mut self,
+        commands: &[Command],
+        text: String,
+        background: bool,
+    ) -> Result<i32, ShellError> {
+        let mut pids = Vec::with_capacity(commands.len());
+        let result = self.spawn_pipeline(commands, background, &mut pids);
+        let (Some(&pgid), Some(&last_pid)) = (pids.first(), pids.last()) else {
+            return result.map(|()| 0);
+        };
+
+        if background {
+            let id = self.jobs.add(Job::new(pgid, pids, text));
+            // like other job notifications, only shown with job control.
+            if self.jobs.job_control() {
+                println!("[{}] {}", id, last_pid);
+            }
+            return result.map(|()| 0);
+        }
+
+        if self.jobs.job_control() {
+            let state = self.jobs.run_in_foreground(Job::new(pgid, pids, text));
+            return result.map(|()| self.foreground_status(state));
+        }
+
+        // wait for every spawned stage, even if spawning a later one failed.
+        let mut status = 0;
+        for pid in pids {
+            match process::wait_pid(pid) {
+                Ok(raw_status) => status = self.foreground_status(JobState::Done(raw_status)),
+                // only reported in debug builds.
+                Err(_err) => {
+                    dprintln_err!("failed to wait for pid {}: {}", pid, _err);
+                }
+            }
+        }
+
+        result.map(|()| status)
+    }
+
+    /// Returns the exit code of a foreground command that terminated or stopped.
+    /// If Ctrl-C killed it, the running commands stop as well.
+    pub fn foreground_status(&mut self, state: JobState) -> i32 {
+        self.interrupted |= state.interrupted();
+        state.exit_code()
+    }
+
+    /// Forks a child process for every pipeline stage and collects their pids.
+    /// The stdout of each stage is connected to the stdin of the next one with a pipe.
+    /// With job control, and for background pipelines, the stages get their own process group.
+    fn spawn_pipeline(
+        &mut self,
+        stages: &[Command],
+        background: bool,
+        pids: &mut Vec<libc::pid_t>,
+    ) -> Result<(), ShellError> {
+        let exec_fail = |err: io::Error| ShellError::CommandExecutionFail(err.to_string());
+
+        let last = stages.len() - 1;
+        let mut stage_stdin: Option<File> = None;
+        let own_group = background || self.jobs.job_control();
+        // the process group of the pipeline, led by the first stage.
+        let mut pgid = 0;
+
+        for (i, stage) in stages.iter().enumerate() {
+            let (next_stdin, stage_stdout) = if i < last {
+                let (read, write) = process::pipe().map_err(exec_fail)?;
+                (Some(read), Some(write))
+            } else {
+                (None, None)
+            };
+
+            match process::fork().map_err(exec_fail)? {
+                Fork::Child => {
+                    drop(next_stdin);
+                    self.exec_external = matches!(stage, Command::Simple(_));
+
+                    if own_group {
+                        let _ = process::set_process_group(0, pgid);
+                        if let (false, Some(terminal)) = (background, self.jobs.terminal_fd()) {
+                            let _ =
+                                process::set_foreground_group(terminal, unsafe { libc::getpgrp() });
+                        }
+                    }
+                    self.jobs.disable_job_control();
+                    process::restore_terminal_signals();
+
+                    let result = self
+                        .setup_stage_io(stage_stdin, stage_stdout)
+                        .map_err(exec_fail)
+                        .and_then(|()| self.run_command(stage));
+
+                    let code = result.unwrap_or_else(|err| self.report_error(err));
+                    process::exit_child(code);
+                }
+                Fork::Parent(pid) => {
+                    // set in the parent as well, so the group exists before anyone waits on it.
+                    if own_group {
+                        if pgid == 0 {
+                            pgid = pid;
+                        }
+                        let _ = process::set_process_group(pid, pgid);
+                    }
+                    pids.push(pid);
+                    // the parent's copies of the pipe ends are closed here,
+                    // so the stages see EOF once their writers are done.
+                    stage_stdin = next_stdin;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Connects the standard streams of a forked pipeline stage to its pipes.
+    fn setup_stage_io(&mut self, stdin: Option<File>, stdout: Option<File>) -> io::Result<()> {
+        if let Some(stdin) = stdin {
+            process::redirect_fd(&stdin, libc::STDIN_FILENO)?;
+        }
+        if let Some(stdout) = stdout {
+            process::redirect_fd(&stdout, libc::STDOUT_FILENO)?;
+        }
+        Ok(())
+    }
+
+    /// Runs a single command of a pipeline and returns its exit status.
+    fn run_command(&mut self, command: &Command) -> Result<i32, ShellError> {
+        match command {
+            Command::Simple(simple) => self.run_simple_command(simple),
+            Command::Compound(compound, redirections) => {
+                let saved_streams = self.redirect_streams(redirections)?;
+                let result = self.run_compound_command(compound);
+                self.restore_streams(saved_streams);
+                result
+            }
+            Command::Function { name, body } => {
+                let function = FunctionCommand::new(name.clone(), (**body).clone());
+                self.cmd_registry.register_function(Arc::new(function));
+                Ok(0)
+            }
+        }
+    }
+
+    fn run_compound_command(&mut self, compound: &CompoundCommand) -> Result<i32, ShellError> {
+        match compound {
+            CompoundCommand::BraceGroup(list) => Ok(self.run_list(list)),
+            CompoundCommand::Subshell(list) => self.run_subshell(list),
+            CompoundCommand::If {
+                branches,
+                else_branch,
+            } => Ok(self.run_if(branches, else_branch.as_ref())),
+            CompoundCommand::Loop {
+                condition,
+                body,
+                until,
+            } => Ok(self.run_loop(condition, body, *until)),
+            CompoundCommand::For { name, words, body } => {
+                self.run_for(name, words.as_deref(), body)
+            }
+            CompoundCommand::Case { word, items } => self.run_case(word, items),
+        }
+    }
+
+    /// Runs the list of the first branch whose condition succeeds, or the else branch.
+    /// The exit status is zero if no list runs.
+    fn run_if(
+        &mut self,
+        branches: &[(CommandList, CommandList)],
+        else_branch: Option<&CommandList>,
+    ) -> i32 {
+        for (condition, body) in branches {
+            let status = self.run_list(condition);
+            if self.interrupted || self.control_flow.is_some() {
+                return status;
+            }
+            if status == 0 {
+                return self.run_list(body);
+            }
+        }
+
+        match else_branch {
+            Some(list) => self.run_list(list),
+            None => 0,
+        }
+    }
+
+    /// Runs the body while the condition succeeds, or until it succeeds for an until loop.
+    /// The exit status is the one of the last body run, or zero if it never runs.
+    fn run_loop(&mut self, condition: &CommandList, body: &CommandList, until: bool) -> i32 {
+        self.loop_depth += 1;
+        let mut status = 0;
+
+        loop {
+            let condition_status = self.run_list(condition);
+            match self.loop_flow() {
+                LoopFlow::Proceed => {}
+                LoopFlow::NextIteration => continue,
+                LoopFlow::Exit => break,
+            }
+            if (condition_status == 0) == until {
+                break;
+            }
+
+            status = self.run_list(body);
+            if let LoopFlow::Exit = self.loop_flow() {
+                break;
+            }
+        }
+
+        self.loop_depth -= 1;
+        status
+    }
+
+    /// Runs the body once for each expanded word, with the variable `name` set to it.
+    /// Without words it loops over the positional parameters.
+    fn run_for(
+        &mut self,
+        name: &str,
+        words: Option<&[Word]>,
+        body: &CommandList,
+    ) -> Result<i32, ShellError> {
+        let values = match words {
+            Some(words) => {
+                let mut values = Vec::new();
+                for word in words {
+                    values.extend(self.expand_word(&word.parts)?);
+                }
+                values
+            }
+            None => self.positional_parameters.clone(),
+        };
+
+        self.loop_depth += 1;
+        let mut status = 0;
+
+        for value in values {
+            self.variables.set(name, value);
+            status = self.run_list(body);
+            if let LoopFlow::Exit = self.loop_flow() {
+                break;
+            }
+        }
+
+        self.loop_depth -= 1;
+        Ok(status)
+    }
+
+    /// Runs the list of the first item with a pattern that matches the expanded word.
+    /// The exit status is zero if no pattern matches.
+    fn run_case(&mut self, word: &Word, items: &[CaseItem]) -> Result<i32, ShellError> {
+        let text = self.expand_word_to_string(&word.parts)?;
+
+        for item in items {
+            for pattern in &item.patterns {
+                if pattern::matches(&self.expand_pattern(&pattern.parts)?, &text) {
+                    self.last_status = 0;
+                    return Ok(self.run_list(&item.body));
+                }
+            }
+        }
+
+        Ok(0)
+    }
+
+    /// Consumes the pending `break` or `continue` that targets the running loop and tells how the loop goes on.
+    /// Ctrl-C, `return` and fatal errors exit every loop.
+    fn loop_flow(&mut self) -> LoopFlow {
+        if self.interrupted {
+            return LoopFlow::Exit;
+        }
+
+        match self.control_flow.take() {
+            None => LoopFlow::Proceed,
+            Some(ControlFlow::Continue(1)) => LoopFlow::NextIteration,
+            Some(ControlFlow::Break(1)) => LoopFlow::Exit,
+            Some(ControlFlow::Break(levels)) => {
+                self.control_flow = Some(ControlFlow::Break(levels - 1));
+                LoopFlow::Exit
+            }
+            Some(ControlFlow::Continue(levels)) => {
+                self.control_flow = Some(ControlFlow::Continue(levels - 1));
+                LoopFlow::Exit
+            }
+            Some(flow @ (ControlFlow::Return | ControlFlow::Exit)) => {
+                self.control_flow = Some(flow);
+                LoopFlow::Exit
+            }
+        }
+    }
+
+    /// Runs the body of the function `name` with `args` as positional parameters
+    /// and a new scope for local variables.
+    pub fn run_function(
+        &mut self,
+        name: &str,
+        body: &Command,
+        args: Vec<String>,
+    ) -> Result<i32, ShellError> {
+        if self.function_depth >= MAX_FUNCTION_DEPTH {
+            return Err(ShellError::CommandExecutionFail(format!(
+                "{}: maximum function nesting level exceeded ({})",
+                name, MAX_FUNCTION_DEPTH
+            )));
+        }
+
+        let saved_parameters = mem::replace(&mut self.positional_parameters, args);
+        // loops of the caller cannot be left from inside the function.
+        let saved_loop_depth = mem::take(&mut self.loop_depth);
+        // the function may run more than one external command, even in a pipeline stage.
+        let saved_exec_external = mem::take(&mut self.exec_external);
+        self.function_depth += 1;
+        self.variables.push_scope();
+
+        let result = self.run_command(body);
+
+        self.variables.pop_scope();
+        self.function_depth -= 1;
+        self.exec_external = saved_exec_external;
+        self.loop_depth = saved_loop_depth;
+        self.positional_parameters = saved_parameters;
+        if self.control_flow == Some(ControlFlow::Return) {
+            self.control_flow = None;
+        }
+
+        result
+    }
+
+    /// Runs a list in a forked child, so it cannot change the state of the shell.
+    fn run_subshell(&mut self, list: &CommandList) -> Result<i32, ShellError> {
+        let exec_fail = |err: io::Error| ShellError::CommandExecutionFail(err.to_string());
+
+        match process::fork().map_err(exec_fail)? {
+            Fork::Child => {
+                self.jobs.disable_job_control();
+                process::restore_terminal_signals();
+
+                let status = self.run_list(list);
+                process::exit_child(status);
+            }
+            Fork::Parent(pid) => {
+                let raw_status = process::wait_pid(pid).map_err(exec_fail)?;
+                Ok(self.foreground_status(JobState::Done(raw_status)))
+            }
+        }
+    }
+
+    /// Runs a simple command with its assignments and redirections and returns its exit status.
+    fn run_simple_command(&mut self, command: &SimpleCommand) -> Result<i32, ShellError> {
+        if let Some((names, list)) = self.expand_aliases(command)? {
+            // an alias is not expanded again in its own value.
+            let active = self.active_aliases.len();
+            self.active_aliases.extend(names);
+            // the value may run more than one external command, even in a pipeline stage.
+            let saved_exec_external = mem::take(&mut self.exec_external);
 
+            let status = self.run_list(&list);
+
+            self.exec_external = saved_exec_external;
+            self.active_aliases.truncate(active);
+            return Ok(status);
+        }
+
+        self.substitution_status = None;
+
+        let mut assignments = Vec::with_capacity(command.assignments.len());
+        for word in &command.assignments {
+            let (name, value) = word.assignment().unwrap();
+            assignments.push((name.to_string(), self.expand_word_to_string(&value)?));
+        }
+
+        let mut command_tokens = Vec::new();
+        for word in &command.words {
+            command_tokens.extend(self.expand_word(&word.parts)?);
+        }
+
+        // swap out the standard streams while the command runs.
+        let saved_streams = self.redirect_streams(&command.redirections)?;
+        let result = self.run_expanded_command(assignments, command_tokens);
+        self.restore_streams(saved_streams);
+
+        result
+    }
+
+    /// Replaces an alias in the first word of a command by its value, before the command is looked up.
+    /// If the value ends with a blank, an alias in the next word is replaced too.
+    /// Returns the names of the replaced aliases and the command list to run instead,
+    /// or `None` if the command does not start with an alias.
+    fn expand_aliases(
+        &self,
+        command: &SimpleCommand,
+    ) -> Result<Option<(Vec<String>, CommandList)>, ShellError> {
+        let mut names: Vec<String> = Vec::new();
+        let mut text = String::new();
+        for word in &command.assignments {
+            text.push_str(&word.raw);
+            text.push(' ');
+        }
+
+        let mut words = command.words.iter().peekable();
+        while let Some(word) = words.peek() {
+            // quoting any part of the word prevents the expansion, e.g. `\ls`.
+            let Some(name) = word.as_literal() else {
+                break;
+            };
+            if self
+                .active_aliases
+                .iter()
+                .chain(&names)
+                .any(|active| active == name)
+            {
+                break;
+            }
+            let Some(value) = self.cmd_registry.get_alias(name) else {
+                break;
+            };
+
+            dprintln!("expanding alias {}={:?}", name, value);
+            names.push(name.to_string());
+            text.push_str(value);
+            words.next();
+            if !value.ends_with([' ', '\t']) {
+                break;
+            }
+        }
+
+        if names.is_empty() {
+            return Ok(None);
+        }
+
+        for word in words {
+            text.push(' ');
+            text.push_str(&word.raw);
+        }
+
+        let mut list = parser::parse(&text)
+            .map_err(|err| ShellError::ParsingFail(format!("alias {}: {}", names[0], err)))?;
+
+        // the redirections, with their here-document bodies, belong to the last command of the value.
+        let last_command = list.items.last_mut().and_then(|item| {
+            let pipeline = match item.and_or.rest.last_mut() {
+                Some((_, pipeline)) => pipeline,
+                None => &mut item.and_or.first,
+            };
+            pipeline.commands.last_mut()
+        });
+        match last_command {
+            Some(Command::Simple(SimpleCommand { redirections, .. }))
+            | Some(Command::Compound(_, redirections)) => {
+                redirections.extend(command.redirections.iter().cloned());
+            }
+            _ => {}
+        }
+
+        Ok(Some((names, list)))
+    }
+
+    /// Runs an expanded command, or sets shell variables if there is only a list of assignments.
+    fn run_expanded_command(
+        &mut self,
+        assignments: Vec<(String, String)>,
+        command_tokens: Vec<String>,
+    ) -> Result<i32, ShellError> {
+        // without a command the assignments set shell variables.
+        if command_tokens.is_empty() && !assignments.is_empty() {
+            for (name, value) in assignments {
+                dprintln!("setting variable {}={:?}", name, value);
+                self.variables.set(&name, value);
+            }
+            return Ok(self.substitution_status.unwrap_or(0));
+        }
+
+        // all words may expand to nothing, e.g. an unset variable or an empty substitution.
         if command_tokens.is_empty() {
-            return Err(ShellError::EmptyInput);
+            return self.substitution_status.ok_or(ShellError::EmptyInput);
         }
 
         let command_name = &command_tokens[0];
@@ -75,134 +1017,269 @@ impl Shell {
 
         dprintln!("cmd name: {}", command_name);
         dprintln!("args: {:?}", args);
-        dprintln!("stdout redirection: {:?}", stdout_redirect);
-        dprintln!("stderr redirection: {:?}", stderr_redirect);
 
         let command = self
             .cmd_registry
-            .get_command(command_name)
-            .cloned()
+            .get_command(command_name, self.variables.get("PATH"))
             .ok_or_else(|| ShellError::CommandNotFound {
                 command_name: command_name.clone(),
             })?;
 
-        // swap out stdout and stderr if redirection is specified.
-        let original_stdout = stdout_redirect
-            .map(|file| std::mem::replace(&mut self.stdout, ShellOutput::file(file)));
-        let original_stderr = stderr_redirect
-            .map(|file| std::mem::replace(&mut self.stderr, ShellOutput::file(file)));
-
-        // execute the command.
+        // execute the command, prefix assignments only apply to its environment.
+        let saved_variables = self.variables.set_temporary(assignments);
         let result = command.run(args, self);
-
-        // restore original outputs.
-        if let Some(stdout) = original_stdout {
-            self.stdout = stdout;
-        }
-        if let Some(stderr) = original_stderr {
-            self.stderr = stderr;
-        }
+        self.variables.restore(saved_variables);
 
         result
     }
 
-    /// Processes tokens to separate redirection tokens from command tokens.
-    fn process_redirections(
-        &self,
-        tokens: Vec<String>,
-    ) -> Result<(Vec<String>, Option<String>, Option<String>), ShellError> {
-        let mut command_tokens = Vec::new();
-        let mut stdout_redirect = None;
-        let mut stderr_redirect = None;
-
-        let mut iter = tokens.into_iter();
-        while let Some(token) = iter.next() {
-            match token.as_str() {
-                "&>" => {
-                    let file = iter.next().ok_or_else(|| {
-                        ShellError::ParsingFail(
-                            "no file specified for output redirection".to_string(),
-                        )
-                    })?;
-                    stdout_redirect = Some(file.clone());
-                    stderr_redirect = Some(file);
-                }
-                ">" | "1>" => {
-                    stdout_redirect = Some(iter.next().ok_or_else(|| {
-                        ShellError::ParsingFail(
-                            "no file specified for output redirection".to_string(),
-                        )
-                    })?);
-                }
-                "2>" => {
-                    stderr_redirect = Some(iter.next().ok_or_else(|| {
-                        ShellError::ParsingFail(
-                            "no file specified for error output redirection".to_string(),
-                        )
-                    })?);
-                }
-                _ => command_tokens.push(token),
+    /// Applies redirections to the standard streams of the shell, in order.
+    /// Returns the original streams, which have to be restored after the command ran.
+    fn redirect_streams(
+        &mut self,
+        redirections: &[Redirection],
+    ) -> Result<SavedStreams, ShellError> {
+        let mut saved = SavedStreams::default();
+
+        for redirection in redirections {
+            dprintln!("redirection: {}", redirection);
+            if let Err(err) = self.redirect_stream(redirection, &mut saved) {
+                self.restore_streams(saved);
+                return Err(err);
             }
         }
 
-        Ok((command_tokens, stdout_redirect, stderr_redirect))
+        Ok(saved)
     }
 
-    /// Parses the shell input into tokens.
-    fn parse_shell_input(&self) -> Vec<String> {
-        let mut tokens = Vec::new();
-        let mut current = String::new();
-
-        let mut in_single_quote = false;
-        let mut in_double_quote = false;
-        let mut chars = self.input_buffer.chars().peekable();
+    /// Applies a single redirection, the replaced stream is saved unless it already was.
+    fn redirect_stream(
+        &mut self,
+        redirection: &Redirection,
+        saved: &mut SavedStreams,
+    ) -> Result<(), ShellError> {
+        let text_fail =
+            |err: io::Error| ShellError::CommandExecutionFail(format!("here-document: {}", err));
 
-        while let Some(c) = chars.next() {
-            match c {
-                '\'' if !in_double_quote => in_single_quote = !in_single_quote,
-                '"' if !in_single_quote => in_double_quote = !in_double_quote,
-                '\\' => {
-                    if let Some(escaped_char) = chars.next() {
-                        current.push(escaped_char);
+        let file = match &redirection.kind {
+            RedirectionKind::Input(word) => {
+                self.open_redirection_target(word, OpenOptions::new().read(true))?
+            }
+            RedirectionKind::Output(word) => self.open_redirection_target(
+                word,
+                OpenOptions::new().write(true).create(true).truncate(true),
+            )?,
+            RedirectionKind::Append(word) => {
+                self.open_redirection_target(word, OpenOptions::new().append(true).create(true))?
+            }
+            RedirectionKind::OutputAll(word) => {
+                let mut options = OpenOptions::new();
+                options.write(true).create(true).truncate(true);
+                return self.redirect_output_and_error(word, &options, saved);
+            }
+            RedirectionKind::AppendAll(word) => {
+                let mut options = OpenOptions::new();
+                options.append(true).create(true);
+                return self.redirect_output_and_error(word, &options, saved);
+            }
+            RedirectionKind::DuplicateInput(word) | RedirectionKind::DuplicateOutput(word) => {
+                match self.expand_redirection_target(word)? {
+                    source if source == "-" => {
+                        self.replace_fd(redirection.fd, None, saved);
+                        return Ok(());
                     }
+                    source => self.duplicate_fd(&source)?,
                 }
-                ' ' | '\t' if !in_single_quote && !in_double_quote => {
-                    if !current.is_empty() {
-                        tokens.push(current.clone());
-                        current.clear();
-                    }
-                    // skip additional whitespaces
-                    while let Some(&next_char) = chars.peek() {
-                        if next_char == ' ' || next_char == '\t' {
-                            chars.next();
-                        } else {
-                            break;
-                        }
-                    }
+            }
+            RedirectionKind::HereDocument(here_document) => {
+                let text = if here_document.expand {
+                    self.expand_here_document(&here_document.body)?
+                } else {
+                    here_document.body.clone()
+                };
+                input::text_file(&text).map_err(text_fail)?
+            }
+            RedirectionKind::HereString(word) => {
+                let text = self.expand_word_to_string(&word.parts)? + "\n";
+                input::text_file(&text).map_err(text_fail)?
+            }
+        };
+
+        self.replace_fd(redirection.fd, Some(file), saved);
+        Ok(())
+    }
+
+    /// Redirects both stdout and stderr to a file (`&>` and `&>>`).
+    fn redirect_output_and_error(
+        &mut self,
+        word: &Word,
+        options: &OpenOptions,
+        saved: &mut SavedStreams,
+    ) -> Result<(), ShellError> {
+        let file = self.open_redirection_target(word, options)?;
+        // both streams share one file description, so their writes do not overwrite each other.
+        let copy = file
+            .try_clone()
+            .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
+
+        self.replace_fd(1, Some(file), saved);
+        self.replace_fd(2, Some(copy), saved);
+        Ok(())
+    }
+
+    /// Expands the target of a redirection and opens it with the given options.
+    fn open_redirection_target(
+        &mut self,
+        word: &Word,
+        options: &OpenOptions,
+    ) -> Result<File, ShellError> {
+        let path = self.expand_redirection_target(word)?;
+        options
+            .open(&path)
+            .map_err(|err| ShellError::CommandExecutionFail(format!("{}: {}", path, err)))
+    }
+
+    /// Returns a new file handle for the open file of the descriptor `source` (e.g. `1` in `2>&1`).
+    fn duplicate_fd(&self, source: &str) -> Result<File, ShellError> {
+        let bad_fd =
+            || ShellError::CommandExecutionFail(format!("{}: bad file descriptor", source));
+
+        let fd: RawFd = source
+            .parse()
+            .map_err(|_| ShellError::ExpansionFail(format!("{}: ambiguous redirect", source)))?;
+        let file = match fd {
+            0 => self.stdin.try_clone_file(),
+            1 => self.stdout.try_clone_file(),
+            2 => self.stderr.try_clone_file(),
+            _ => match self.extra_fds.get(&fd) {
+                Some(file) => file.try_clone(),
+                None => return Err(bad_fd()),
+            },
+        };
+        file.map_err(|_| bad_fd())
+    }
+
+    /// Makes `file` the descriptor `fd` of the shell, or closes the descriptor if there is no file.
+    /// The replaced stream is saved unless it already was.
+    fn replace_fd(&mut self, fd: RawFd, file: Option<File>, saved: &mut SavedStreams) {
+        match fd {
+            0 => {
+                let input = file.map_or(ShellInput::Closed, ShellInput::File);
+                saved
+                    .stdin
+                    .get_or_insert(std::mem::replace(&mut self.stdin, input));
+            }
+            1 => {
+                let output = file.map_or(ShellOutput::Closed, ShellOutput::File);
+                saved
+                    .stdout
+                    .get_or_insert(std::mem::replace(&mut self.stdout, output));
+            }
+            2 => {
+                let output = file.map_or(ShellOutput::Closed, ShellOutput::File);
+                saved
+                    .stderr
+                    .get_or_insert(std::mem::replace(&mut self.stderr, output));
+            }
+            _ => {
+                let previous = match file {
+                    Some(file) => self.extra_fds.insert(fd, file),
+                    None => self.extra_fds.remove(&fd),
+                };
+                if !saved.extra_fds.iter().any(|(saved_fd, _)| *saved_fd == fd) {
+                    saved.extra_fds.push((fd, previous));
                 }
-                _ => current.push(c),
             }
         }
+    }
+
+    /// Returns the file descriptors an external command gets besides its standard streams:
+    /// the redirected descriptors above 2, and `None` for closed standard streams.
+    /// The files are duplicated above all of the descriptors, so they can be set up in any order.
+    pub fn child_fds(&self) -> io::Result<Vec<(RawFd, Option<File>)>> {
+        let min = self.extra_fds.keys().max().map_or(3, |fd| fd + 1);
 
-        if !current.is_empty() {
-            tokens.push(current);
+        let mut fds = Vec::new();
+        for (&fd, file) in &self.extra_fds {
+            fds.push((fd, Some(process::duplicate_above(file, min)?)));
+        }
+
+        let closed = [
+            matches!(self.stdin, ShellInput::Closed),
+            matches!(self.stdout, ShellOutput::Closed),
+            matches!(self.stderr, ShellOutput::Closed),
+        ];
+        for (fd, closed) in (0..).zip(closed) {
+            if closed {
+                fds.push((fd, None));
+            }
+        }
+
+        Ok(fds)
+    }
+
+    /// Restores the standard streams replaced by `redirect_streams`.
+    fn restore_streams(&mut self, saved: SavedStreams) {
+        if let Some(stdin) = saved.stdin {
+            self.stdin = stdin;
+        }
+        if let Some(stdout) = saved.stdout {
+            self.stdout = stdout;
+        }
+        if let Some(stderr) = saved.stderr {
+            self.stderr = stderr;
+        }
+        for (fd, file) in saved.extra_fds {
+            match file {
+                Some(file) => self.extra_fds.insert(fd, file),
+                None => self.extra_fds.remove(&fd),
+            };
         }
-        tokens
+    }
+
+    /// Expands the target of a redirection, which must result in exactly one field.
+    fn expand_redirection_target(&mut self, word: &Word) -> Result<String, ShellError> {
+        let mut fields = self.expand_word(&word.parts)?;
+        if fields.len() != 1 {
+            return Err(ShellError::ExpansionFail(format!(
+                "{}: ambiguous redirect",
+                word
+            )));
+        }
+        Ok(fields.remove(0))
     }
 
     /// Prints the shell header (current working directory in bold green).
+    /// A non-zero exit status of the last command is shown next to it in red.
     fn print_shell_header(&self) {
         if let Ok(path) = std::env::current_dir() {
-            println!("  \x1b[1;32m{}\x1b[0m", path.display());
+            print!("  \x1b[1;32m{}\x1b[0m", path.display());
+        }
+        if self.last_status != 0 {
+            print!(" \x1b[31m[{}]\x1b[0m", self.last_status);
         }
+        println!();
     }
 
     /// Handles the result of evaluating a command.
     fn handle_eval_error(&mut self, error: ShellError) {
+        // an unset `${VAR:?}` ends a script, but not the interactive shell.
+        if matches!(error, ShellError::ParameterNotSet(_)) && !self.interactive {
+            self.control_flow = Some(ControlFlow::Exit);
+        }
+
+        // errors of scripts tell where they happened, like `script.sh: line 2: `.
+        let location = match &self.source_location {
+            Some((name, line)) => format!("{}: line {}: ", name, line),
+            None => String::new(),
+        };
+
+        // error messages are lost if stderr is closed, like with `2>&-`.
         match error {
             ShellError::CommandNotFound { command_name } => {
-                self.stderr
-                    .writeln(&format!("{}: command not found", command_name));
+                let _ = self
+                    .stderr
+                    .writeln(&format!("{}{}: command not found", location, command_name));
 
                 if command_name.len() <= 2 {
                     return;
@@ -210,21 +1287,584 @@ impl Shell {
 
                 let levenshtein_threshold = if command_name.len() < 4 { 1 } else { 2 };
 
+                // an alias whose value runs a missing command is itself a registered name.
                 if let Some(closest) = Levenshtein::get_closest_with_threshold(
                     &command_name,
                     &self.cmd_registry.registered_names,
                     levenshtein_threshold,
-                ) {
-                    self.stderr
+                )
+                .filter(|closest| *closest != command_name)
+                {
+                    let _ = self
+                        .stderr
                         .writeln(&format!("did you mean \"{}\"?", closest));
                 }
             }
             ShellError::EmptyInput => {
                 dprintln_err!("empty input error");
             }
+            err if self.stderr.is_terminal() => {
+                let _ = self
+                    .stderr
+                    .writeln(&format!("\x1b[31m{}{}\x1b[0m", location, err));
+            }
             err => {
-                self.stderr.writeln(&err.to_string());
+                let _ = self.stderr.writeln(&format!("{}{}", location, err));
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf, thread};
+
+    use super::{JobState, Shell, MAX_FUNCTION_DEPTH};
+
+    /// Functions returning a fixed status, since `true` and `false` are not builtins.
+    const STATUS_FUNCTIONS: &str = "t() { return 0; }; f() { return 1; }\n";
+
+    /// Runs `source` as a script in a new shell, after defining `STATUS_FUNCTIONS`.
+    fn run(source: &str) -> Shell {
+        let mut shell = Shell::new();
+        let source = format!("{}{}", STATUS_FUNCTIONS, source);
+        shell.run_script(source, "test".to_string(), Vec::new());
+        shell
+    }
+
+    /// Creates a new empty directory for the files of a test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("shellrs-{}-{:x}", name, rand::random::<u64>()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_pipeline_status() {
+        assert_eq!(run("t | f").last_status, 1);
+        assert_eq!(run("f | t").last_status, 0);
+        assert_eq!(run("! t | f").last_status, 0);
+        assert_eq!(run("echo x | f | t").last_status, 0);
+    }
+
+    #[test]
+    fn test_prefix_assignments_are_restored() {
+        let shell = run("a=1\n\
+             g() { seen_a=$a; seen_b=$b; }\n\
+             a=2 b=3 g");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("seen_a"), Some("2"));
+        assert_eq!(var("seen_b"), Some("3"));
+        assert_eq!(var("a"), Some("1"));
+        assert_eq!(var("b"), None);
+    }
+
+    #[test]
+    fn test_and_or_short_circuit() {
+        let shell = run("f && a=1; s1=$?\n\
+             t && b=1\n\
+             t || c=1\n\
+             f || d=1; s2=$?\n\
+             f && e=1 || g=1\n\
+             t || h=1 && i=1");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("a"), None);
+        assert_eq!(var("s1"), Some("1"));
+        assert_eq!(var("b"), Some("1"));
+        assert_eq!(var("c"), None);
+        assert_eq!(var("d"), Some("1"));
+        assert_eq!(var("s2"), Some("0"));
+        assert_eq!(var("e"), None);
+        assert_eq!(var("g"), Some("1"));
+        assert_eq!(var("h"), None);
+        assert_eq!(var("i"), Some("1"));
+    }
+
+    #[test]
+    fn test_and_or_status() {
+        assert_eq!(run("t && f").last_status, 1);
+        assert_eq!(run("f || t").last_status, 0);
+        assert_eq!(run("f && t").last_status, 1);
+        assert_eq!(run("t; f").last_status, 1);
+    }
+
+    #[test]
+    fn test_redirections() {
+        let dir = temp_dir("redirections");
+        let shell = run(&format!(
+            "d={}\n\
+             echo one >$d/out; echo two >>$d/out\n\
+             echo three 3>$d/fd3 >&3\n\
+             {{ echo four >&2; }} >$d/both 2>&1\n\
+             {{ echo five >&-; }} 2>$d/err; closed=$?",
+            dir.display()
+        ));
+        let read = |name| fs::read_to_string(dir.join(name)).unwrap();
+
+        assert_eq!(read("out"), "one\ntwo\n");
+        assert_eq!(read("fd3"), "three\n");
+        assert_eq!(read("both"), "four\n");
+        assert!(read("err").contains("echo: write error"));
+        assert_eq!(shell.variables.get("closed"), Some("1"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_cd_updates_pwd_and_oldpwd() {
+        let dir = temp_dir("cd");
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::create_dir_all(dir.join("b")).unwrap();
+        let dir = dir.canonicalize().unwrap();
+
+        // the subshell changes the directory of a forked child, tests running in parallel keep theirs.
+        let shell = run(&format!(
+            "d={}\n\
+             (\n\
+               cd $d/a; cd $d/b\n\
+               echo \"$PWD $OLDPWD\" >$d/first\n\
+               cd - >/dev/null\n\
+               echo \"$PWD $OLDPWD\" ~- >$d/second\n\
+             )",
+            dir.display()
+        ));
+        let read = |name| fs::read_to_string(dir.join(name)).unwrap();
+
+        assert_eq!(shell.last_status, 0);
+        assert_eq!(read("first"), format!("{0}/b {0}/a\n", dir.display()));
+        assert_eq!(
+            read("second"),
+            format!("{0}/a {0}/b {0}/b\n", dir.display())
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_break_and_continue_levels() {
+        let shell = run("for i in 1 2 3; do\n\
+               for j in a b c; do\n\
+                 case $j in b) continue 2;; esac\n\
+                 continued=$continued$i$j\n\
+               done\n\
+               continued=${continued}x\n\
+             done\n\
+             for i in 1 2; do\n\
+               for j in a b; do\n\
+                 broken=$broken$i$j\n\
+                 case $j in b) break 2;; esac\n\
+               done\n\
+             done");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("continued"), Some("1a2a3a"));
+        assert_eq!(var("broken"), Some("1a1b"));
+        assert_eq!(shell.loop_depth, 0);
+    }
+
+    #[test]
+    fn test_break_and_continue_levels_are_clamped() {
+        let shell = run("for i in 1 2; do\n\
+               for j in a b; do broken=$broken$i$j; break 5; done\n\
+               broken=${broken}x\n\
+             done; after=$?\n\
+             for i in 1 2; do\n\
+               for j in a b; do continued=$continued$i$j; continue 9; done\n\
+               continued=${continued}x\n\
+             done");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("broken"), Some("1a"));
+        assert_eq!(var("after"), Some("0"));
+        assert_eq!(var("continued"), Some("1a2a"));
+        assert_eq!(shell.control_flow, None);
+    }
+
+    #[test]
+    fn test_break_and_continue_outside_a_loop() {
+        let dir = temp_dir("break");
+        let shell = run(&format!(
+            "d={}\n\
+             {{ break; }} 2>$d/break; broken=$?\n\
+             {{ continue; }} 2>$d/continue; continued=$?; after=1",
+            dir.display()
+        ));
+        let var = |name| shell.variables.get(name);
+        let read = |name| fs::read_to_string(dir.join(name)).unwrap();
+
+        assert_eq!(var("broken"), Some("1"));
+        assert_eq!(var("continued"), Some("1"));
+        assert_eq!(var("after"), Some("1"));
+        assert_eq!(
+            read("break"),
+            "test: line 3: break: only meaningful in a for, while or until loop\n"
+        );
+        assert!(read("continue").starts_with("test: line 4: continue: "));
+        assert_eq!(shell.control_flow, None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_while_and_until() {
+        let shell = run("until case $n in xxx) t;; *) f;; esac; do n=${n}x; done\n\
+             while case $m in yy) f;; *) t;; esac; do m=${m}y; done\n\
+             f; until t; do never=1; done; status=$?");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("n"), Some("xxx"));
+        assert_eq!(var("m"), Some("yy"));
+        assert_eq!(var("never"), None);
+        assert_eq!(var("status"), Some("0"));
+    }
+
+    #[test]
+    fn test_case() {
+        let shell = run(
+            "case foo.rs in *.txt) kind=text;; *.rs|*.c) kind=code;; *) kind=other;; esac\n\
+             f; case z in a) matched=1;; esac; no_match=$?\n\
+             case a in a) f;; esac; body=$?",
+        );
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("kind"), Some("code"));
+        assert_eq!(var("matched"), None);
+        assert_eq!(var("no_match"), Some("0"));
+        assert_eq!(var("body"), Some("1"));
+    }
+
+    #[test]
+    fn test_commands_with_a_path_and_from_a_new_path() {
+        let dir = temp_dir("commands");
+        std::os::unix::fs::symlink("/bin/sh", dir.join("tool")).unwrap();
+        let shell = run(&format!(
+            "{0}/tool -c 'exit 3' && t || direct=$?\n\
+             {0}/missing; missing=$?\n\
+             PATH={0}:$PATH\n\
+             tool -c 'exit 4'; searched=$?",
+            dir.display()
+        ));
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("direct"), Some("3"));
+        assert_eq!(var("missing"), Some("127"));
+        assert_eq!(var("searched"), Some("4"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_status_130_is_not_an_interrupt() {
+        let shell = run("g() { return 130; }\n\
+             g; after=1\n\
+             g && and=1 || or=1\n\
+             for i in 1 2; do g; done; last=$i\n\
+             if g; then t; else else_branch=1; fi");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("after"), Some("1"));
+        assert_eq!(var("and"), None);
+        assert_eq!(var("or"), Some("1"));
+        assert_eq!(var("last"), Some("2"));
+        assert_eq!(var("else_branch"), Some("1"));
+        assert!(!shell.interrupted);
+    }
+
+    #[test]
+    fn test_local_variables_are_dynamically_scoped() {
+        let shell = run("x=global\n\
+             show() { seen=$seen$x,; }\n\
+             inner() { local x=inner; show; }\n\
+             outer() { local x=outer; show; inner; show; local y; y=set; }\n\
+             outer; show; y=$y");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("seen"), Some("outer,inner,outer,global,"));
+        assert_eq!(var("x"), Some("global"));
+        assert_eq!(var("y"), Some(""));
+    }
+
+    #[test]
+    fn test_positional_parameters_are_restored() {
+        let mut shell = Shell::new();
+        let args = ["a", "b c"].map(str::to_string).to_vec();
+        let script = "g() { inside=\"$#:$1:$2\"; third=$3; }\n\
+             g x; after=\"$#:$1:$2\"";
+        shell.run_script(script.to_string(), "test".to_string(), args);
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("inside"), Some("1:x:"));
+        assert_eq!(var("third"), Some(""));
+        assert_eq!(var("after"), Some("2:a:b c"));
+    }
+
+    #[test]
+    fn test_quoted_all_parameters() {
+        let expansions = |args: &str| {
+            let shell = run(&format!(
+                "count() {{ n=$#; first=$1; }}\n\
+                 args() {{ count \"$@\"; at=$n:$first; count \"$*\"; star=$n:$first; }}\n\
+                 args {}",
+                args
+            ));
+            let var = |name| shell.variables.get(name).map(str::to_string);
+            (var("at"), var("star"))
+        };
+
+        let (at, star) = expansions("'a b' c");
+        assert_eq!(at.as_deref(), Some("2:a b"));
+        assert_eq!(star.as_deref(), Some("1:a b c"));
+
+        // without arguments `"$@"` is no word at all and `"$*"` an empty one.
+        let (at, star) = expansions("");
+        assert_eq!(at.as_deref(), Some("0:"));
+        assert_eq!(star.as_deref(), Some("1:"));
+    }
+
+    #[test]
+    fn test_return_status() {
+        let status = |body: &str| run(&format!("g() {{ {}; }}; g", body)).last_status;
+
+        assert_eq!(status("return 3"), 3);
+        assert_eq!(status("return 300"), 44);
+        assert_eq!(status("return -1"), 255);
+        assert_eq!(status("f; return"), 1);
+        assert_eq!(status("return 2; never=1"), 2);
+    }
+
+    #[test]
+    fn test_function_nesting_limit() {
+        // the default stack of test threads is too small for the deepest calls in debug builds.
+        let (depth, status, function_depth) = thread::Builder::new()
+            .stack_size(64 << 20)
+            .spawn(|| {
+                let shell = run("depth() { d=$d.; depth; }; depth; status=$?");
+                let var = |name| shell.variables.get(name).map(str::to_string);
+                (
+                    var("d").map(|d| d.len()),
+                    var("status"),
+                    shell.function_depth,
+                )
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(depth, Some(MAX_FUNCTION_DEPTH));
+        assert_eq!(status.as_deref(), Some("1"));
+        assert_eq!(function_depth, 0);
+    }
+
+    #[test]
+    fn test_script_runs_commands_before_a_syntax_error() {
+        let shell = run("before=1\n\
+             if t; then\n\
+               nested=1\n\
+             fi\n\
+             oops )\n\
+             after=1");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("before"), Some("1"));
+        assert_eq!(var("nested"), Some("1"));
+        assert_eq!(var("after"), None);
+        assert_eq!(shell.last_status, 2);
+    }
+
+    #[test]
+    fn test_script_errors_have_location() {
+        let dir = temp_dir("errors");
+        let shell = run(&format!(
+            "d={}\n\
+             \n\
+             {{ missing_command_x; }} 2>$d/not_found\n\
+             {{ cd $d/missing; }} 2>$d/cd",
+            dir.display()
+        ));
+        let read = |name| fs::read_to_string(dir.join(name)).unwrap();
+
+        // line 1 of the script defines `t` and `f`.
+        assert!(
+            read("not_found").starts_with("test: line 4: missing_command_x: command not found\n")
+        );
+        let cd = read("cd");
+        assert!(cd.starts_with("test: line 5: "), "{:?}", cd);
+        assert!(!cd.contains('\x1b'));
+        assert_eq!(shell.last_status, 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_source_file() {
+        let dir = temp_dir("source");
+        fs::write(dir.join("lib.sh"), "inside=\"$#:$1\"\nreturn 3\nnever=1\n").unwrap();
+        let shell = run(&format!(
+            "outer() {{\n\
+             . {0}/lib.sh x; status=$?; with_args=$inside; after=\"$#:$1\"\n\
+             source {0}/lib.sh; without_args=$inside\n\
+             }}\n\
+             outer a b",
+            dir.display()
+        ));
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("status"), Some("3"));
+        assert_eq!(var("with_args"), Some("1:x"));
+        assert_eq!(var("after"), Some("2:a"));
+        assert_eq!(var("without_args"), Some("2:a"));
+        // `return` leaves the sourced file, not the function that sourced it.
+        assert_eq!(var("never"), None);
+        assert_eq!(shell.source_depth, 0);
+        assert_eq!(shell.control_flow, None);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_alias_expansion() {
+        let shell = run("args() { seen=\"$seen[$*]\"; }; cycle() { args \"$@\"; }\n\
+             alias one='two x' two='args' loop='loop l' cycle='back c' back='cycle b'\n\
+             alias run='args ' plain='args' ll='args long'\n\
+             one y\n\
+             loop z\n\
+             loop_status=$?\n\
+             cycle d\n\
+             run ll\n\
+             plain ll\n\
+             \\one 'one'\n\
+             o_status=$?");
+        let var = |name| shell.variables.get(name);
+
+        // the value of an alias may start with another alias, but not with itself.
+        // after a blank at the end of a value the next word is expanded too.
+        assert_eq!(var("seen"), Some("[x y][b c d][args long][ll]"));
+        assert_eq!(var("loop_status"), Some("127"));
+        // quoting the name prevents the expansion.
+        assert_eq!(var("o_status"), Some("127"));
+    }
+
+    #[test]
+    fn test_alias_redirections_apply_to_the_last_command() {
+        let dir = temp_dir("alias");
+        let shell = run(&format!(
+            "alias both='echo one >{0}/first; echo two'\n\
+             both >{0}/last",
+            dir.display()
+        ));
+
+        assert_eq!(shell.last_status, 0);
+        assert_eq!(fs::read_to_string(dir.join("first")).unwrap(), "one\n");
+        assert_eq!(fs::read_to_string(dir.join("last")).unwrap(), "two\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_set_positional_parameters() {
+        let shell = run("set -- a 'b c'; count=$#; first=$1; second=$2\n\
+             inner() { set -- x; inside=$*; }; inner; outside=$*\n\
+             set -o noglob -- y; with_option=$*\n\
+             set --; none=$#");
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("count"), Some("2"));
+        assert_eq!(var("first"), Some("a"));
+        assert_eq!(var("second"), Some("b c"));
+        // a function only replaces its own arguments.
+        assert_eq!(var("inside"), Some("x"));
+        assert_eq!(var("outside"), Some("a b c"));
+        assert_eq!(var("with_option"), Some("y"));
+        assert!(shell.options.noglob);
+        assert_eq!(var("none"), Some("0"));
+    }
+
+    #[test]
+    fn test_unset_parameter_error_ends_the_script() {
+        let dir = temp_dir("unset");
+        fs::write(dir.join("lib.sh"), "echo ${nope:?missing}\nin_lib=1\n").unwrap();
+        let script = format!(
+            "d={}\n\
+             outer() {{\n\
+               for i in 1 2; do . $d/lib.sh; looped=$i; done\n\
+               after_loop=1\n\
+             }}\n\
+             {{ outer; }} 2>$d/err\n\
+             after=1",
+            dir.display()
+        );
+        let shell = run(&script);
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(shell.last_status, 1);
+        for name in ["in_lib", "looped", "after_loop", "after"] {
+            assert_eq!(var(name), None, "{}", name);
+        }
+        assert_eq!(
+            fs::read_to_string(dir.join("err")).unwrap(),
+            format!("{}/lib.sh: line 1: nope: missing\n", dir.display())
+        );
+
+        // the interactive shell only stops the command.
+        let mut shell = Shell::new();
+        shell.interactive = true;
+        shell.run_script(script, "test".to_string(), Vec::new());
+        assert_eq!(shell.variables.get("looped"), Some("2"));
+        assert_eq!(shell.variables.get("after"), Some("1"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_background_jobs() {
+        let dir = temp_dir("jobs");
+        let mut shell = run(&format!(
+            "d={}\n\
+             /bin/sh -c 'exit 3' &\n\
+             /bin/sh -c 'exit 5' &\n\
+             started=$?\n\
+             wait %1; first=$?\n\
+             wait %2; second=$?\n\
+             {{ wait %1; }} 2>/dev/null; gone=$?\n\
+             sleep 10 &\n\
+             jobs >$d/jobs",
+            dir.display()
+        ));
+        let var = |name| shell.variables.get(name);
+
+        assert_eq!(var("started"), Some("0"));
+        assert_eq!(var("first"), Some("3"));
+        assert_eq!(var("second"), Some("5"));
+        // jobs that are done leave the job table.
+        assert_eq!(var("gone"), Some("1"));
+        let jobs = fs::read_to_string(dir.join("jobs")).unwrap();
+        assert!(jobs.starts_with("[1]+  Running "), "{:?}", jobs);
+        assert!(jobs.ends_with(" sleep 10 &\n"), "{:?}", jobs);
+
+        let [job] = shell.jobs.jobs() else {
+            panic!("one job is left");
+        };
+        let (id, pgid) = (job.id, job.pgid);
+        assert_eq!(job.state(), JobState::Running);
+        unsafe { libc::kill(pgid, libc::SIGKILL) };
+        assert_eq!(shell.jobs.wait_for(id), 128 + libc::SIGKILL);
+        assert!(shell.jobs.jobs().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_aliases_are_errors() {
+        let dir = temp_dir("unalias");
+        let shell = run(&format!(
+            "d={}\n\
+             {{ alias nope kept=args gone=args; }} 2>$d/alias; alias_status=$?\n\
+             {{ unalias nope gone; }} 2>$d/unalias; unalias_status=$?",
+            dir.display()
+        ));
+        let var = |name| shell.variables.get(name);
+        let read = |name| fs::read_to_string(dir.join(name)).unwrap();
+
+        // the names after the missing one are still handled.
+        assert_eq!(var("alias_status"), Some("1"));
+        assert_eq!(var("unalias_status"), Some("1"));
+        assert_eq!(shell.cmd_registry.get_alias("kept"), Some("args"));
+        assert_eq!(shell.cmd_registry.get_alias("gone"), None);
+        assert_eq!(read("alias"), "test: line 3: alias: nope: not found\n");
+        assert_eq!(read("unalias"), "test: line 4: unalias: nope: not found\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
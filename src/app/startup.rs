@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use super::Variables;
+
+/// Name of the rc file in the home directory, used if there is none in the config directory.
+const RC_FILE_NAME: &str = ".shellrsrc";
+/// Name of the profile in the home directory, used if there is none in the config directory.
+const PROFILE_FILE_NAME: &str = ".shellrs_profile";
+
+/// Returns the rc file sourced by interactive sessions, if it exists.
+/// It is `$XDG_CONFIG_HOME/shellrs/config` (`~/.config/shellrs/config` by default), otherwise `~/.shellrsrc`.
+pub fn rc_file(variables: &Variables) -> Option<PathBuf> {
+    find_file(variables, "config", RC_FILE_NAME)
+}
+
+/// Returns the profile sourced by login shells, if it exists.
+/// It is `$XDG_CONFIG_HOME/shellrs/profile` (`~/.config/shellrs/profile` by default), otherwise `~/.shellrs_profile`.
+pub fn profile_file(variables: &Variables) -> Option<PathBuf> {
+    find_file(variables, "profile", PROFILE_FILE_NAME)
+}
+
+fn find_file(variables: &Variables, config_name: &str, home_name: &str) -> Option<PathBuf> {
+    candidates(
+        variables.get("XDG_CONFIG_HOME"),
+        variables.get("HOME"),
+        config_name,
+        home_name,
+    )
+    .into_iter()
+    .find(|path| path.is_file())
+}
+
+/// Returns the paths where a startup file is looked for, in order.
+fn candidates(
+    config_home: Option<&str>,
+    home: Option<&str>,
+    config_name: &str,
+    home_name: &str,
+) -> Vec<PathBuf> {
+    let config_home = match config_home {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => home.map(|home| PathBuf::from(home).join(".config")),
+    };
+
+    let mut paths = Vec::new();
+    if let Some(dir) = config_home {
+        paths.push(dir.join("shellrs").join(config_name));
+    }
+    if let Some(home) = home {
+        paths.push(PathBuf::from(home).join(home_name));
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::candidates;
+
+    #[test]
+    fn test_candidates() {
+        assert_eq!(
+            candidates(Some("/xdg"), Some("/home/u"), "config", ".shellrsrc"),
+            [
+                PathBuf::from("/xdg/shellrs/config"),
+                PathBuf::from("/home/u/.shellrsrc")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_candidates_default_config_home() {
+        for config_home in [None, Some("")] {
+            assert_eq!(
+                candidates(config_home, Some("/home/u"), "profile", ".shellrs_profile"),
+                [
+                    PathBuf::from("/home/u/.config/shellrs/profile"),
+                    PathBuf::from("/home/u/.shellrs_profile")
+                ]
+            );
+        }
+        assert!(candidates(None, None, "config", ".shellrsrc").is_empty());
+    }
+}
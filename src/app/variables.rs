@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+
+/// A shell variable.
+#[derive(Debug, Clone)]
+pub struct Variable {
+    /// The value of the variable.
+    pub value: String,
+    /// Whether the variable is passed to the environment of external commands.
+    pub exported: bool,
+}
+
+/// Previous state of variables overridden by `Variables::set_temporary`.
+pub type SavedVariables = Vec<(String, Option<Variable>)>;
+
+/// Storage for shell-local and exported variables.
+#[derive(Debug, Default)]
+pub struct Variables {
+    /// The key is the variable name and the value is the variable itself.
+    vars: HashMap<String, Variable>,
+    /// Previous state of the variables made local in each running function, innermost last.
+    scopes: Vec<SavedVariables>,
+}
+
+impl Variables {
+    /// Creates a new instance of the `Variables` struct.
+    /// Every variable of the process environment is imported as exported.
+    pub fn from_env() -> Self {
+        let vars = std::env::vars()
+            .map(|(name, value)| {
+                let variable = Variable {
+                    value,
+                    exported: true,
+                };
+                (name, variable)
+            })
+            .collect();
+
+        Self {
+            vars,
+            scopes: Vec::new(),
+        }
+    }
+
+    /// Returns the value of the variable with the given name if it is set.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.vars.get(name).map(|var| var.value.as_str())
+    }
+
+    /// Sets the value of a variable.
+    /// A new variable is shell-local, an existing one keeps its export flag.
+    pub fn set(&mut self, name: &str, value: String) {
+        match self.vars.get_mut(name) {
+            Some(var) => var.value = value,
+            None => {
+                let variable = Variable {
+                    value,
+                    exported: false,
+                };
+                self.vars.insert(name.to_string(), variable);
+            }
+        }
+    }
+
+    /// Marks a variable as exported, optionally setting its value.
+    /// An unknown variable without a value is created empty.
+    pub fn export(&mut self, name: &str, value: Option<String>) {
+        let var = self
+            .vars
+            .entry(name.to_string())
+            .or_insert_with(|| Variable {
+                value: String::new(),
+                exported: true,
+            });
+
+        var.exported = true;
+        if let Some(value) = value {
+            var.value = value;
+        }
+    }
+
+    /// Removes the export flag of a variable, keeping it as a shell-local variable.
+    pub fn unexport(&mut self, name: &str) {
+        if let Some(var) = self.vars.get_mut(name) {
+            var.exported = false;
+        }
+    }
+
+    /// Removes the variable with the given name.
+    pub fn unset(&mut self, name: &str) {
+        self.vars.remove(name);
+    }
+
+    /// Sets exported variables for the duration of a single command (`NAME=value command`).
+    /// Returns the previous state, which has to be passed to `restore` afterwards.
+    pub fn set_temporary(&mut self, assignments: Vec<(String, String)>) -> SavedVariables {
+        assignments
+            .into_iter()
+            .map(|(name, value)| {
+                let variable = Variable {
+                    value,
+                    exported: true,
+                };
+                let previous = self.vars.insert(name.clone(), variable);
+                (name, previous)
+            })
+            .collect()
+    }
+
+    /// Restores variables overridden by `set_temporary`.
+    pub fn restore(&mut self, saved: SavedVariables) {
+        for (name, previous) in saved.into_iter().rev() {
+            match previous {
+                Some(variable) => self.vars.insert(name, variable),
+                None => self.vars.remove(&name),
+            };
+        }
+    }
+
+    /// Starts the scope of local variables of a function call.
+    pub fn push_scope(&mut self) {
+        self.scopes.push(SavedVariables::new());
+    }
+
+    /// Ends the innermost scope, restoring the variables that were made local in it.
+    pub fn pop_scope(&mut self) {
+        if let Some(saved) = self.scopes.pop() {
+            self.restore(saved);
+        }
+    }
+
+    /// Makes a variable local to the innermost scope, it is restored when the scope ends.
+    /// Without a value the local variable is unset.
+    /// Outside of any scope the variable is set globally.
+    pub fn make_local(&mut self, name: &str, value: Option<String>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if !scope.iter().any(|(saved, _)| saved == name) {
+                scope.push((name.to_string(), self.vars.get(name).cloned()));
+            }
+        }
+
+        match value {
+            Some(value) => {
+                let variable = Variable {
+                    value,
+                    exported: false,
+                };
+                self.vars.insert(name.to_string(), variable);
+            }
+            None => self.unset(name),
+        }
+    }
+
+    /// Returns all variables sorted by name.
+    pub fn iter_sorted(&self) -> Vec<(&str, &Variable)> {
+        let mut vars: Vec<(&str, &Variable)> = self
+            .vars
+            .iter()
+            .map(|(name, var)| (name.as_str(), var))
+            .collect();
+        vars.sort_by_key(|(name, _)| *name);
+        vars
+    }
+
+    /// Returns the names and values of all exported variables, sorted by name.
+    pub fn exported(&self) -> Vec<(&str, &str)> {
+        self.iter_sorted()
+            .into_iter()
+            .filter(|(_, var)| var.exported)
+            .map(|(name, var)| (name, var.value.as_str()))
+            .collect()
+    }
+
+    /// Splits a raw `NAME=value` assignment word into the name and the raw value.
+    /// Returns `None` if the word is not an assignment.
+    pub fn parse_assignment(word: &str) -> Option<(&str, &str)> {
+        let (name, value) = word.split_once('=')?;
+        Self::is_valid_name(name).then_some((name, value))
+    }
+
+    /// Returns `true` if the given string is a valid variable name.
+    /// Valid names consist of ASCII alphanumerics and underscores and do not start with a digit.
+    pub fn is_valid_name(name: &str) -> bool {
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Variables;
+
+    fn is_exported(variables: &Variables, name: &str) -> bool {
+        variables
+            .exported()
+            .iter()
+            .any(|(exported, _)| *exported == name)
+    }
+
+    #[test]
+    fn test_set_keeps_export_flag() {
+        let mut variables = Variables::default();
+        variables.set("A", "1".to_string());
+        assert!(!is_exported(&variables, "A"));
+
+        variables.export("A", None);
+        variables.set("A", "2".to_string());
+        assert_eq!(variables.get("A"), Some("2"));
+        assert!(is_exported(&variables, "A"));
+    }
+
+    #[test]
+    fn test_export_and_unexport() {
+        let mut variables = Variables::default();
+        variables.export("EMPTY", None);
+        variables.export("B", Some("x".to_string()));
+        assert_eq!(variables.exported(), [("B", "x"), ("EMPTY", "")]);
+
+        variables.unexport("B");
+        assert_eq!(variables.get("B"), Some("x"));
+        assert_eq!(variables.exported(), [("EMPTY", "")]);
+    }
+
+    #[test]
+    fn test_set_temporary_and_restore() {
+        let mut variables = Variables::default();
+        variables.set("A", "old".to_string());
+
+        let saved = variables.set_temporary(vec![
+            ("A".to_string(), "new".to_string()),
+            ("B".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+        ]);
+        assert_eq!(variables.get("A"), Some("new"));
+        assert_eq!(variables.get("B"), Some("2"));
+        assert_eq!(variables.exported(), [("A", "new"), ("B", "2")]);
+
+        variables.restore(saved);
+        assert_eq!(variables.get("A"), Some("old"));
+        assert_eq!(variables.get("B"), None);
+        assert!(variables.exported().is_empty());
+    }
+
+    #[test]
+    fn test_parse_assignment() {
+        assert_eq!(Variables::parse_assignment("A=1=2"), Some(("A", "1=2")));
+        assert_eq!(Variables::parse_assignment("_a1="), Some(("_a1", "")));
+        assert_eq!(Variables::parse_assignment("1A=x"), None);
+        assert_eq!(Variables::parse_assignment("echo"), None);
+    }
+}
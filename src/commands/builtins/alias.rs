@@ -0,0 +1,97 @@
+use crate::{
+    app::{quoting, Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct AliasCommand;
+
+impl Command for AliasCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            let lines: Vec<String> = shell
+                .cmd_registry
+                .aliases()
+                .iter()
+                .map(|(name, value)| format_alias(name, value))
+                .collect();
+
+            for line in lines {
+                shell
+                    .stdout
+                    .writeln(&line)
+                    .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+        for arg in args {
+            let Some((name, value)) = arg.split_once('=') else {
+                match shell.cmd_registry.get_alias(arg) {
+                    Some(value) => {
+                        let line = format_alias(arg, value);
+                        shell
+                            .stdout
+                            .writeln(&line)
+                            .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+                    }
+                    None => {
+                        status = shell.report_error(ShellError::CommandExecutionFail(format!(
+                            "{}: {}: not found",
+                            self.get_name(),
+                            arg
+                        )));
+                    }
+                }
+                continue;
+            };
+
+            if !is_valid_alias_name(name) {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "{}: `{}`: invalid alias name",
+                    self.get_name(),
+                    name
+                )));
+            }
+
+            shell
+                .cmd_registry
+                .set_alias(name.to_string(), value.to_string());
+        }
+
+        Ok(status)
+    }
+
+    fn get_name(&self) -> String {
+        "alias".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [name[=value] ...]\n", self.get_name()).as_str());
+        help_message.push_str("defines aliases, a command that starts with the name of an alias\n");
+        help_message.push_str("runs with the name replaced by the value.\n");
+        help_message.push_str(
+            "if the value ends with a space, the next word is checked for an alias too.\n",
+        );
+        help_message
+            .push_str("without a value the alias is printed, without arguments all aliases are.");
+
+        Ok(help_message)
+    }
+}
+
+/// Formats an alias the way it is defined.
+fn format_alias(name: &str, value: &str) -> String {
+    format!("alias {}={}", name, quoting::quote(value))
+}
+
+/// Returns `true` if the name has no quotes, blanks, `/` or characters that end a word.
+fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "|&;<>()$`\\\"'/".contains(c))
+}
@@ -0,0 +1,57 @@
+use crate::{
+    app::{JobState, Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct BgCommand;
+
+impl Command for BgCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let fail = |err: String| ShellError::CommandExecutionFail(format!("bg: {}", err));
+
+        shell.jobs.update();
+
+        let specs = if args.is_empty() {
+            vec![None]
+        } else {
+            args.into_iter().map(Some).collect()
+        };
+        for spec in specs {
+            let id = shell.jobs.find(spec).map_err(fail)?;
+
+            let job = shell.jobs.jobs().iter().find(|job| job.id == id).unwrap();
+            if job.state() == JobState::Running {
+                return Err(fail(format!("job {} already in background", id)));
+            }
+
+            shell
+                .jobs
+                .continue_in_background(id)
+                .map_err(|err| fail(err.to_string()))?;
+
+            let job = shell.jobs.jobs().iter().find(|job| job.id == id).unwrap();
+            let line = format!("[{}]{} {} &", id, shell.jobs.marker(id), job.command);
+            shell
+                .stdout
+                .writeln(&line)
+                .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+        }
+
+        Ok(0)
+    }
+
+    fn get_name(&self) -> String {
+        "bg".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [job ...]\n", self.get_name()).as_str());
+        help_message
+            .push_str("continues stopped jobs in the background, by default the current job.");
+
+        Ok(help_message)
+    }
+}
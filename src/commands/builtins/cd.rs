@@ -1,5 +1,5 @@
 use crate::{
-    app::{Shell, ShellError},
+    app::{ArgCompletion, Shell, ShellError},
     commands::Command,
     dprintln,
 };
@@ -8,19 +8,44 @@ use crate::{
 pub struct CdCommand;
 
 impl Command for CdCommand {
-    fn run(&self, args: Vec<&str>, _: &mut Shell) -> Result<(), ShellError> {
-        let target_dir = match args.get(0) {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let target_dir = match args.first() {
+            Some(&"-") => {
+                let previous = shell.variables.get("OLDPWD").ok_or_else(|| {
+                    ShellError::CommandExecutionFail("cd: OLDPWD not set".to_string())
+                })?;
+                previous.to_string()
+            }
             Some(arg) => arg.to_string(),
-            None => std::env::var("HOME").unwrap_or_else(|_| "/".to_string()),
+            None => shell.variables.get("HOME").unwrap_or("/").to_string(),
         };
 
         let target_dir = std::path::Path::new(&target_dir);
         dprintln!("changing directory to {:?}", target_dir);
 
+        let previous_dir = std::env::current_dir().ok();
         std::env::set_current_dir(target_dir)
             .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
 
-        Ok(())
+        // `~-` and `~+` expand to these.
+        if let Some(previous_dir) = previous_dir {
+            shell
+                .variables
+                .set("OLDPWD", previous_dir.display().to_string());
+        }
+        if let Ok(current_dir) = std::env::current_dir() {
+            if args.first() == Some(&"-") {
+                shell
+                    .stdout
+                    .writeln(&current_dir.display().to_string())
+                    .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+            }
+            shell
+                .variables
+                .set("PWD", current_dir.display().to_string());
+        }
+
+        Ok(0)
     }
 
     fn get_name(&self) -> String {
@@ -34,8 +59,13 @@ impl Command for CdCommand {
         help_message
             .push_str("changes the current working directory to the specified directory.\n");
         help_message
-            .push_str("if no directory is specified, the HOME environment variable is used.");
+            .push_str("if no directory is specified, the HOME environment variable is used.\n");
+        help_message.push_str("cd - changes to the previous directory, which is kept in OLDPWD.");
 
         Ok(help_message)
     }
+
+    fn arg_completion(&self) -> ArgCompletion {
+        ArgCompletion::Directories
+    }
 }
@@ -0,0 +1,132 @@
+use crate::{
+    app::{ControlFlow, Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct BreakCommand;
+
+#[derive(Debug)]
+pub struct ContinueCommand;
+
+#[derive(Debug)]
+pub struct ReturnCommand;
+
+impl Command for BreakCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        leave_loops(&self.get_name(), &args, shell, ControlFlow::Break)
+    }
+
+    fn get_name(&self) -> String {
+        "break".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [n]\n", self.get_name()).as_str());
+        help_message.push_str("exits the n innermost for, while or until loops, by default 1.");
+
+        Ok(help_message)
+    }
+}
+
+impl Command for ContinueCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        leave_loops(&self.get_name(), &args, shell, ControlFlow::Continue)
+    }
+
+    fn get_name(&self) -> String {
+        "continue".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [n]\n", self.get_name()).as_str());
+        help_message
+            .push_str("resumes the next iteration of the n-th enclosing loop, by default 1.");
+
+        Ok(help_message)
+    }
+}
+
+impl Command for ReturnCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let status = match args.first() {
+            Some(arg) => arg.parse::<i32>().map_err(|_| {
+                ShellError::CommandExecutionFail(format!(
+                    "{}: {}: numeric argument required",
+                    self.get_name(),
+                    arg
+                ))
+            })?,
+            None => shell.last_status,
+        };
+
+        if shell.function_depth == 0 && shell.source_depth == 0 {
+            return Err(ShellError::CommandExecutionFail(format!(
+                "{}: can only return from a function or sourced file",
+                self.get_name()
+            )));
+        }
+
+        shell.control_flow = Some(ControlFlow::Return);
+        // like exit statuses, the status is truncated to 8 bits.
+        Ok(status & 0xff)
+    }
+
+    fn get_name(&self) -> String {
+        "return".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [n]\n", self.get_name()).as_str());
+        help_message
+            .push_str("exits the running function or sourced file with the exit status n.\n");
+        help_message
+            .push_str("if n is not specified, the exit status of the last command is used.");
+
+        Ok(help_message)
+    }
+}
+
+/// Parses the loop count and makes the running loops stop.
+/// A count above the number of running loops applies to the outermost one.
+fn leave_loops(
+    name: &str,
+    args: &[&str],
+    shell: &mut Shell,
+    control: fn(usize) -> ControlFlow,
+) -> Result<i32, ShellError> {
+    let levels = match args.first() {
+        Some(arg) => match arg.parse::<usize>() {
+            Ok(0) => {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "{}: {}: loop count out of range",
+                    name, arg
+                )))
+            }
+            Ok(levels) => levels,
+            Err(_) => {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "{}: {}: numeric argument required",
+                    name, arg
+                )))
+            }
+        },
+        None => 1,
+    };
+
+    if shell.loop_depth == 0 {
+        return Err(ShellError::CommandExecutionFail(format!(
+            "{}: only meaningful in a for, while or until loop",
+            name
+        )));
+    }
+
+    shell.control_flow = Some(control(levels.min(shell.loop_depth)));
+    Ok(0)
+}
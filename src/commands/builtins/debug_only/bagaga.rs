@@ -16,11 +16,12 @@ const RESPONSES: [&str; 4] = [
 pub struct BagagaCommand;
 
 impl Command for BagagaCommand {
-    fn run(&self, _: Vec<&str>, shell: &mut Shell) -> Result<(), ShellError> {
+    fn run(&self, _: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
         shell
             .stdout
-            .writeln(RESPONSES[rand::rng().random_range(0..RESPONSES.len())]);
-        Ok(())
+            .writeln(RESPONSES[rand::rng().random_range(0..RESPONSES.len())])
+            .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+        Ok(0)
     }
 
     fn get_name(&self) -> String {
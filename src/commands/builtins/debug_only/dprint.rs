@@ -1,5 +1,5 @@
 use crate::{
-    app::{Shell, ShellError},
+    app::{ArgCompletion, Shell, ShellError},
     commands::Command,
 };
 
@@ -7,7 +7,7 @@ use crate::{
 pub struct DebugPrintCommand;
 
 impl Command for DebugPrintCommand {
-    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<(), ShellError> {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
         if args.is_empty() {
             return Err(ShellError::CommandExecutionFail(
                 "example usage: dprint <command name>".to_string(),
@@ -16,15 +16,21 @@ impl Command for DebugPrintCommand {
 
         let command_name = args[0];
 
-        if let Some(command) = shell.cmd_registry.get_command(command_name) {
-            shell.stdout.writeln(&command.debug_print_message());
+        if let Some(command) = shell
+            .cmd_registry
+            .get_command(command_name, shell.variables.get("PATH"))
+        {
+            shell
+                .stdout
+                .writeln(&command.debug_print_message())
+                .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
         } else {
             return Err(ShellError::CommandNotFound {
                 command_name: command_name.to_string(),
             });
         }
 
-        Ok(())
+        Ok(0)
     }
 
     fn get_name(&self) -> String {
@@ -39,4 +45,8 @@ impl Command for DebugPrintCommand {
 
         Ok(help_message)
     }
+
+    fn arg_completion(&self) -> ArgCompletion {
+        ArgCompletion::Commands
+    }
 }
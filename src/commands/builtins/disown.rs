@@ -0,0 +1,47 @@
+use crate::{
+    app::{Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct DisownCommand;
+
+impl Command for DisownCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let ids: Vec<usize> = match args.as_slice() {
+            ["-a"] => shell.jobs.jobs().iter().map(|job| job.id).collect(),
+            [] => vec![shell.jobs.find(None).map_err(|err| {
+                ShellError::CommandExecutionFail(format!("{}: {}", self.get_name(), err))
+            })?],
+            specs => specs
+                .iter()
+                .map(|spec| shell.jobs.find(Some(spec)))
+                .collect::<Result<_, _>>()
+                .map_err(|err| {
+                    ShellError::CommandExecutionFail(format!("{}: {}", self.get_name(), err))
+                })?,
+        };
+
+        for id in ids {
+            shell.jobs.remove(id);
+        }
+
+        Ok(0)
+    }
+
+    fn get_name(&self) -> String {
+        "disown".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [-a] [job ...]\n", self.get_name()).as_str());
+        help_message.push_str("removes jobs from the job table, by default the current job.\n");
+        help_message
+            .push_str("the processes keep running, but are no longer reported by the shell.\n");
+        help_message.push_str("with -a all jobs are removed.");
+
+        Ok(help_message)
+    }
+}
@@ -7,9 +7,12 @@ use crate::{
 pub struct EchoCommand;
 
 impl Command for EchoCommand {
-    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<(), ShellError> {
-        shell.stdout.writeln(&args.join(" "));
-        Ok(())
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        shell
+            .stdout
+            .writeln(&args.join(" "))
+            .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+        Ok(0)
     }
 
     fn get_name(&self) -> String {
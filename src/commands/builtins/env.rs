@@ -0,0 +1,76 @@
+use crate::{
+    app::{Shell, ShellError, Variables},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct EnvCommand;
+
+impl Command for EnvCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let assignment_count = args
+            .iter()
+            .take_while(|arg| Variables::parse_assignment(arg).is_some())
+            .count();
+
+        let assignments = args[..assignment_count]
+            .iter()
+            .filter_map(|arg| Variables::parse_assignment(arg))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        let saved_variables = shell.variables.set_temporary(assignments);
+
+        let result = match args.get(assignment_count) {
+            Some(command_name) => match shell
+                .cmd_registry
+                .get_command(command_name, shell.variables.get("PATH"))
+            {
+                Some(command) => command.run(args[assignment_count + 1..].to_vec(), shell),
+                None => Err(ShellError::CommandNotFound {
+                    command_name: command_name.to_string(),
+                }),
+            },
+            None => {
+                let lines: Vec<String> = shell
+                    .variables
+                    .exported()
+                    .into_iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect();
+
+                for line in lines {
+                    shell
+                        .stdout
+                        .writeln(&line)
+                        .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+                }
+                Ok(0)
+            }
+        };
+
+        shell.variables.restore(saved_variables);
+        result
+    }
+
+    fn get_name(&self) -> String {
+        "env".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(
+            format!(
+                "usage: {} [name=value ...] [command [args ...]]\n",
+                self.get_name()
+            )
+            .as_str(),
+        );
+        help_message
+            .push_str("runs the command with the specified variables added to its environment.\n");
+        help_message.push_str("if no command is specified, the resulting environment is printed.");
+
+        Ok(help_message)
+    }
+}
@@ -8,10 +8,10 @@ use crate::{
 pub struct ExitCommand;
 
 impl Command for ExitCommand {
-    fn run(&self, args: Vec<&str>, _: &mut Shell) -> Result<(), ShellError> {
-        let status_code_parse = match args.get(0) {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let status_code_parse = match args.first() {
             Some(arg) => arg.parse::<i32>(),
-            None => Ok(0),
+            None => Ok(shell.last_status),
         };
 
         match status_code_parse {
@@ -19,9 +19,7 @@ impl Command for ExitCommand {
                 dprintln!("exiting with status code {}", status_code);
                 std::process::exit(status_code);
             }
-            Err(err) => {
-                return Err(ShellError::CommandExecutionFail(err.to_string()));
-            }
+            Err(err) => Err(ShellError::CommandExecutionFail(err.to_string())),
         }
     }
 
@@ -34,7 +32,9 @@ impl Command for ExitCommand {
 
         help_message.push_str(format!("usage: {} <status code>\n", self.get_name()).as_str());
         help_message.push_str("exits the shell with the specified status code.\n");
-        help_message.push_str("if no status code is specified, the exit status is 0.\n\n");
+        help_message.push_str(
+            "if no status code is specified, the exit status of the last command is used.\n\n",
+        );
         help_message.push_str("panics if the status code parsing fails.");
 
         Ok(help_message)
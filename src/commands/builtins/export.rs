@@ -0,0 +1,75 @@
+use crate::{
+    app::{quoting, Shell, ShellError, Variables},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct ExportCommand;
+
+impl Command for ExportCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let (unexport, args) = match args.first() {
+            Some(&"-n") => (true, &args[1..]),
+            _ => (false, &args[..]),
+        };
+
+        if args.is_empty() {
+            let lines: Vec<String> = shell
+                .variables
+                .exported()
+                .into_iter()
+                .map(|(name, value)| format!("export {}={}", name, quoting::quote(value)))
+                .collect();
+
+            for line in lines {
+                shell
+                    .stdout
+                    .writeln(&line)
+                    .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+            }
+            return Ok(0);
+        }
+
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (*arg, None),
+            };
+
+            if !Variables::is_valid_name(name) {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "{}: `{}`: not a valid identifier",
+                    self.get_name(),
+                    arg
+                )));
+            }
+
+            if unexport {
+                shell.variables.unexport(name);
+            } else {
+                shell.variables.export(name, value);
+            }
+        }
+
+        Ok(0)
+    }
+
+    fn get_name(&self) -> String {
+        "export".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message
+            .push_str(format!("usage: {} [-n] [name[=value] ...]\n", self.get_name()).as_str());
+        help_message
+            .push_str("marks variables to be passed to the environment of external commands.\n");
+        help_message.push_str("if a value is given, it is assigned to the variable first.\n");
+        help_message
+            .push_str("with -n the variables are unexported but kept as shell variables.\n");
+        help_message.push_str("if no names are specified, all exported variables are listed.");
+
+        Ok(help_message)
+    }
+}
@@ -0,0 +1,40 @@
+use crate::{
+    app::{Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct FgCommand;
+
+impl Command for FgCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let fail = |err: String| ShellError::CommandExecutionFail(format!("fg: {}", err));
+
+        if !shell.jobs.job_control() {
+            return Err(fail("no job control".to_string()));
+        }
+
+        shell.jobs.update();
+        let id = shell.jobs.find(args.first().copied()).map_err(fail)?;
+
+        let state = shell
+            .jobs
+            .continue_in_foreground(id)
+            .map_err(|err| fail(err.to_string()))?;
+        Ok(shell.foreground_status(state))
+    }
+
+    fn get_name(&self) -> String {
+        "fg".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [job]\n", self.get_name()).as_str());
+        help_message.push_str("continues a job in the foreground, by default the current job.\n");
+        help_message.push_str("the exit status is the one of the job.");
+
+        Ok(help_message)
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    app::{Shell, ShellError},
+    app::{ArgCompletion, Shell, ShellError},
     commands::Command,
 };
 
@@ -7,8 +7,8 @@ use crate::{
 pub struct HelpCommand;
 
 impl Command for HelpCommand {
-    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<(), ShellError> {
-        let command_name = match args.get(0) {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let command_name = match args.first() {
             Some(arg) => arg,
             None => {
                 return Err(ShellError::CommandExecutionFail(
@@ -17,16 +17,22 @@ impl Command for HelpCommand {
             }
         };
 
-        if let Some(command) = shell.cmd_registry.get_command(command_name) {
+        if let Some(command) = shell
+            .cmd_registry
+            .get_command(command_name, shell.variables.get("PATH"))
+        {
             let message = &command.clone().get_help_message(shell)?;
-            shell.stdout.writeln(message);
+            shell
+                .stdout
+                .writeln(message)
+                .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
         } else {
             return Err(ShellError::CommandNotFound {
                 command_name: command_name.to_string(),
             });
         }
 
-        Ok(())
+        Ok(0)
     }
 
     fn get_name(&self) -> String {
@@ -42,4 +48,8 @@ impl Command for HelpCommand {
 
         Ok(help_message)
     }
+
+    fn arg_completion(&self) -> ArgCompletion {
+        ArgCompletion::Commands
+    }
 }
@@ -0,0 +1,87 @@
+use crate::{
+    app::{Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct JobsCommand;
+
+impl Command for JobsCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let mut long = false;
+        let mut pgids_only = false;
+        let mut specs = Vec::new();
+
+        for arg in args {
+            match arg {
+                "-l" => long = true,
+                "-p" => pgids_only = true,
+                _ if arg.starts_with('-') => {
+                    return Err(ShellError::CommandExecutionFail(format!(
+                        "{}: {}: invalid option",
+                        self.get_name(),
+                        arg
+                    )));
+                }
+                spec => specs.push(spec),
+            }
+        }
+
+        shell.jobs.update();
+
+        let ids: Vec<usize> = if specs.is_empty() {
+            shell.jobs.jobs().iter().map(|job| job.id).collect()
+        } else {
+            specs
+                .into_iter()
+                .map(|spec| shell.jobs.find(Some(spec)))
+                .collect::<Result<_, _>>()
+                .map_err(|err| {
+                    ShellError::CommandExecutionFail(format!("{}: {}", self.get_name(), err))
+                })?
+        };
+
+        let lines: Vec<String> = shell
+            .jobs
+            .jobs()
+            .iter()
+            .filter(|job| ids.contains(&job.id))
+            .map(|job| {
+                if pgids_only {
+                    job.pgid.to_string()
+                } else {
+                    job.format(shell.jobs.marker(job.id), long)
+                }
+            })
+            .collect();
+
+        for line in lines {
+            shell
+                .stdout
+                .writeln(&line)
+                .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+        }
+
+        // jobs that are done are listed once.
+        shell.jobs.mark_notified();
+
+        Ok(0)
+    }
+
+    fn get_name(&self) -> String {
+        "jobs".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [-l|-p] [job ...]\n", self.get_name()).as_str());
+        help_message.push_str("lists the background and stopped jobs, or only the given ones.\n");
+        help_message.push_str("-l also lists the process group ids, -p lists only them.\n");
+        help_message
+            .push_str("jobs are given as %n, %+ (the current job), %- (the previous job),\n");
+        help_message.push_str("%prefix (the job whose command starts with prefix) or %?text.");
+
+        Ok(help_message)
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{
+    app::{Shell, ShellError, Variables},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct LocalCommand;
+
+impl Command for LocalCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        if shell.function_depth == 0 {
+            return Err(ShellError::CommandExecutionFail(format!(
+                "{}: can only be used in a function",
+                self.get_name()
+            )));
+        }
+
+        for arg in args {
+            let (name, value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            if !Variables::is_valid_name(name) {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "{}: `{}`: not a valid identifier",
+                    self.get_name(),
+                    arg
+                )));
+            }
+
+            shell.variables.make_local(name, value);
+        }
+
+        Ok(0)
+    }
+
+    fn get_name(&self) -> String {
+        "local".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} name[=value] ...\n", self.get_name()).as_str());
+        help_message.push_str("creates variables that are only visible in the running function\n");
+        help_message.push_str("and the functions it calls, until it returns.\n");
+        help_message.push_str("without a value the local variable is unset.");
+
+        Ok(help_message)
+    }
+}
@@ -3,14 +3,42 @@ mod debug_only;
 #[cfg(debug_assertions)]
 pub use debug_only::*;
 
+mod alias;
+mod bg;
 mod cd;
+mod control_flow;
+mod disown;
 mod echo;
+mod env;
 mod exit;
+mod export;
+mod fg;
 mod help;
+mod jobs;
+mod local;
 mod pwd;
+mod set;
+mod source;
+mod unalias;
+mod unset;
+mod wait;
 
+pub use alias::AliasCommand;
+pub use bg::BgCommand;
 pub use cd::CdCommand;
+pub use control_flow::{BreakCommand, ContinueCommand, ReturnCommand};
+pub use disown::DisownCommand;
 pub use echo::EchoCommand;
+pub use env::EnvCommand;
 pub use exit::ExitCommand;
+pub use export::ExportCommand;
+pub use fg::FgCommand;
 pub use help::HelpCommand;
+pub use jobs::JobsCommand;
+pub use local::LocalCommand;
 pub use pwd::PwdCommand;
+pub use set::SetCommand;
+pub use source::SourceCommand;
+pub use unalias::UnaliasCommand;
+pub use unset::UnsetCommand;
+pub use wait::WaitCommand;
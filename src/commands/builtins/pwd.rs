@@ -7,13 +7,16 @@ use crate::{
 pub struct PwdCommand;
 
 impl Command for PwdCommand {
-    fn run(&self, _: Vec<&str>, shell: &mut Shell) -> Result<(), ShellError> {
+    fn run(&self, _: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
         let pwd = std::env::current_dir()
             .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
 
-        shell.stdout.writeln(&pwd.display().to_string());
+        shell
+            .stdout
+            .writeln(&pwd.display().to_string())
+            .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
 
-        Ok(())
+        Ok(0)
     }
 
     fn get_name(&self) -> String {
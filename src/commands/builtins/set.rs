@@ -0,0 +1,124 @@
+use crate::{
+    app::{quoting, Shell, ShellError, ShellOptions},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct SetCommand;
+
+impl SetCommand {
+    /// Lists all shell variables as `name=value`.
+    fn list_variables(&self, shell: &mut Shell) -> Result<(), ShellError> {
+        let lines: Vec<String> = shell
+            .variables
+            .iter_sorted()
+            .into_iter()
+            .map(|(name, var)| format!("{}={}", name, quoting::quote(&var.value)))
+            .collect();
+
+        for line in lines {
+            shell
+                .stdout
+                .writeln(&line)
+                .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+        }
+        Ok(())
+    }
+
+    /// Enables the options named after `-o` or disables the ones after `+o`,
+    /// without names the options are listed.
+    fn set_options(&self, args: &[&str], shell: &mut Shell) -> Result<(), ShellError> {
+        let enabled = match args[0] {
+            "-o" => true,
+            "+o" => false,
+            _ => {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "example usage: {} -o vi",
+                    self.get_name()
+                )))
+            }
+        };
+
+        let names = &args[1..];
+        if names.is_empty() {
+            return self.list_options(shell, !enabled);
+        }
+
+        for name in names {
+            if !shell.options.set(name, enabled) {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "{}: invalid option name",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lists all options, as a table for `set -o` or as commands for `set +o`.
+    fn list_options(&self, shell: &mut Shell, as_commands: bool) -> Result<(), ShellError> {
+        for name in ShellOptions::NAMES {
+            let enabled = shell.options.get(name).unwrap_or_default();
+            let line = if as_commands {
+                format!("set {}o {}", if enabled { '-' } else { '+' }, name)
+            } else {
+                format!("{:<15} {}", name, if enabled { "on" } else { "off" })
+            };
+            shell
+                .stdout
+                .writeln(&line)
+                .map_err(|err| ShellError::write_error(&self.get_name(), err))?;
+        }
+        Ok(())
+    }
+}
+
+impl Command for SetCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        // `--` ends the options, the arguments after it become the positional parameters.
+        let (options, parameters) = match args.iter().position(|arg| *arg == "--") {
+            Some(end) => (&args[..end], Some(&args[end + 1..])),
+            None => (args.as_slice(), None),
+        };
+
+        if !options.is_empty() {
+            self.set_options(options, shell)?;
+        } else if parameters.is_none() {
+            self.list_variables(shell)?;
+        }
+
+        if let Some(parameters) = parameters {
+            shell.positional_parameters = parameters.iter().map(|arg| arg.to_string()).collect();
+        }
+
+        Ok(0)
+    }
+
+    fn get_name(&self) -> String {
+        "set".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(
+            format!(
+                "usage: {} [-o|+o [option ...]] [-- [arg ...]]\n",
+                self.get_name()
+            )
+            .as_str(),
+        );
+        help_message.push_str(
+            "without arguments, lists all shell variables, both shell-local and exported.\n",
+        );
+        help_message.push_str("variables are defined with name=value.\n");
+        help_message.push_str(
+            "-o enables and +o disables the given options, without options they are listed.\n",
+        );
+        help_message
+            .push_str("the arguments after -- become the positional parameters $1, $2, ...\n");
+        help_message.push_str(format!("options: {}.", ShellOptions::NAMES.join(", ")).as_str());
+
+        Ok(help_message)
+    }
+}
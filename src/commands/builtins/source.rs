@@ -0,0 +1,118 @@
+use std::path::PathBuf;
+
+use crate::{
+    app::{Shell, ShellError},
+    commands::Command,
+};
+
+/// `source` and its POSIX name `.`.
+#[derive(Debug)]
+pub struct SourceCommand {
+    name: &'static str,
+}
+
+impl SourceCommand {
+    pub fn new(name: &'static str) -> Self {
+        Self { name }
+    }
+}
+
+impl Command for SourceCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let Some(file) = args.first() else {
+            return Err(ShellError::CommandExecutionFail(format!(
+                "{}: filename argument required",
+                self.get_name()
+            )));
+        };
+
+        let path = find_file(file, shell.variables.get("PATH")).ok_or_else(|| {
+            ShellError::CommandExecutionFail(format!(
+                "{}: {}: file not found",
+                self.get_name(),
+                file
+            ))
+        })?;
+
+        let args = args[1..].iter().map(|arg| arg.to_string()).collect();
+        shell.source_file(&path, args).map_err(|err| {
+            ShellError::CommandExecutionFail(format!("{}: {}", self.get_name(), err))
+        })
+    }
+
+    fn get_name(&self) -> String {
+        self.name.to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} <file> [args ...]\n", self.get_name()).as_str());
+        help_message.push_str("runs the commands of the file in the current shell,\n");
+        help_message.push_str("so the variables, functions and options it sets persist.\n");
+        help_message.push_str(
+            "a file name without `/` is looked up in PATH, then in the current directory.\n",
+        );
+        help_message.push_str("the args become the positional parameters while the file runs.");
+
+        Ok(help_message)
+    }
+}
+
+/// Returns the path of the file to source.
+fn find_file(file: &str, path_var: Option<&str>) -> Option<PathBuf> {
+    if file.contains('/') {
+        return Some(PathBuf::from(file));
+    }
+
+    path_var
+        .into_iter()
+        .flat_map(|paths| paths.split(':'))
+        .map(|dir| PathBuf::from(dir).join(file))
+        .find(|path| path.is_file())
+        .or_else(|| Some(PathBuf::from(file)).filter(|path| path.is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::find_file;
+
+    #[test]
+    fn test_find_file() {
+        let dir = env::temp_dir().join(format!("shellrs-find-{:x}", rand::random::<u64>()));
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        fs::create_dir_all(&first).unwrap();
+        fs::create_dir_all(&second).unwrap();
+        fs::write(first.join("both.sh"), "").unwrap();
+        fs::write(second.join("both.sh"), "").unwrap();
+        fs::write(second.join("second.sh"), "").unwrap();
+        fs::create_dir(first.join("second.sh")).unwrap();
+        let path = format!("{}:{}", first.display(), second.display());
+
+        // the first directory of PATH wins, and directories are skipped.
+        assert_eq!(
+            find_file("both.sh", Some(&path)),
+            Some(first.join("both.sh"))
+        );
+        assert_eq!(
+            find_file("second.sh", Some(&path)),
+            Some(second.join("second.sh"))
+        );
+        assert_eq!(find_file("missing.sh", Some(&path)), None);
+        assert_eq!(find_file("both.sh", None), None);
+        // a name with `/` is not looked up, even if it does not exist.
+        assert_eq!(
+            find_file("./missing.sh", Some(&path)),
+            Some("./missing.sh".into())
+        );
+        // then the current directory is tried.
+        assert_eq!(
+            find_file("Cargo.toml", Some(&path)),
+            Some("Cargo.toml".into())
+        );
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,52 @@
+use crate::{
+    app::{Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct UnaliasCommand;
+
+impl Command for UnaliasCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        if args.first() == Some(&"-a") {
+            let names: Vec<String> = shell.cmd_registry.aliases().keys().cloned().collect();
+            for name in names {
+                shell.cmd_registry.remove_alias(&name);
+            }
+            return Ok(0);
+        }
+
+        if args.is_empty() {
+            return Err(ShellError::CommandExecutionFail(format!(
+                "{}: alias name required",
+                self.get_name()
+            )));
+        }
+
+        let mut status = 0;
+        for name in args {
+            if !shell.cmd_registry.remove_alias(name) {
+                status = shell.report_error(ShellError::CommandExecutionFail(format!(
+                    "{}: {}: not found",
+                    self.get_name(),
+                    name
+                )));
+            }
+        }
+
+        Ok(status)
+    }
+
+    fn get_name(&self) -> String {
+        "unalias".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [-a] <name> ...\n", self.get_name()).as_str());
+        help_message.push_str("removes the specified aliases, or all of them with -a.");
+
+        Ok(help_message)
+    }
+}
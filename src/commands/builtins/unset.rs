@@ -0,0 +1,38 @@
+use crate::{
+    app::{Shell, ShellError, Variables},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct UnsetCommand;
+
+impl Command for UnsetCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        for name in args {
+            if !Variables::is_valid_name(name) {
+                return Err(ShellError::CommandExecutionFail(format!(
+                    "{}: `{}`: not a valid identifier",
+                    self.get_name(),
+                    name
+                )));
+            }
+
+            shell.variables.unset(name);
+        }
+
+        Ok(0)
+    }
+
+    fn get_name(&self) -> String {
+        "unset".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} <name> ...\n", self.get_name()).as_str());
+        help_message.push_str("removes the specified shell and environment variables.");
+
+        Ok(help_message)
+    }
+}
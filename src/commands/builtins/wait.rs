@@ -0,0 +1,67 @@
+use crate::{
+    app::{Shell, ShellError},
+    commands::Command,
+};
+
+#[derive(Debug)]
+pub struct WaitCommand;
+
+impl Command for WaitCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        if args.is_empty() {
+            let ids: Vec<usize> = shell.jobs.jobs().iter().map(|job| job.id).collect();
+            for id in ids {
+                shell.jobs.wait_for(id);
+            }
+            return Ok(0);
+        }
+
+        let mut status = 0;
+        for arg in args {
+            let id = if arg.starts_with('%') {
+                shell.jobs.find(Some(arg)).map_err(|err| {
+                    ShellError::CommandExecutionFail(format!("{}: {}", self.get_name(), err))
+                })?
+            } else {
+                let pid = arg.parse().map_err(|_| {
+                    ShellError::CommandExecutionFail(format!(
+                        "{}: `{}`: not a pid or valid job spec",
+                        self.get_name(),
+                        arg
+                    ))
+                })?;
+
+                match shell.jobs.find_by_pid(pid) {
+                    Some(id) => id,
+                    None => {
+                        let _ = shell.stderr.writeln(&format!(
+                            "{}: pid {} is not a child of this shell",
+                            self.get_name(),
+                            pid
+                        ));
+                        status = 127;
+                        continue;
+                    }
+                }
+            };
+
+            status = shell.jobs.wait_for(id);
+        }
+
+        Ok(status)
+    }
+
+    fn get_name(&self) -> String {
+        "wait".to_string()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        let mut help_message = String::new();
+
+        help_message.push_str(format!("usage: {} [job|pid ...]\n", self.get_name()).as_str());
+        help_message.push_str("waits until the given jobs finish or stop, by default all jobs.\n");
+        help_message.push_str("the exit status is the one of the last given job.");
+
+        Ok(help_message)
+    }
+}
@@ -1,5 +1,7 @@
+use std::os::{fd::AsRawFd, unix::process::CommandExt};
+
 use crate::{
-    app::{Shell, ShellError},
+    app::{process, Job, JobState, Shell, ShellError},
     dprintln,
 };
 
@@ -20,9 +22,14 @@ impl ExternalCommand {
 }
 
 impl Command for ExternalCommand {
-    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<(), ShellError> {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
         dprintln!("spawning external command: {}", self.debug_print_message());
 
+        let stdin_stdio = shell
+            .stdin
+            .as_stdio()
+            .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
+
         let stdout_stdio = shell
             .stdout
             .as_stdio()
@@ -33,18 +40,70 @@ impl Command for ExternalCommand {
             .as_stdio()
             .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
 
-        let mut child = std::process::Command::new(&self.path)
+        let child_fds = shell
+            .child_fds()
+            .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
+
+        let args_text: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+        let mut command = std::process::Command::new(&self.path);
+        command
             .args(args)
+            .env_clear()
+            .envs(shell.variables.exported())
+            .stdin(stdin_stdio)
             .stdout(stdout_stdio)
-            .stderr(stderr_stdio)
+            .stderr(stderr_stdio);
+
+        // with job control the command runs in its own process group, which gets the terminal.
+        let job_control = shell.jobs.job_control();
+        let terminal = shell.jobs.terminal_fd();
+        unsafe {
+            command.pre_exec(move || {
+                if job_control {
+                    process::set_process_group(0, 0)?;
+                    if let Some(terminal) = terminal {
+                        let _ = process::set_foreground_group(terminal, libc::getpid());
+                    }
+                }
+                for (fd, file) in &child_fds {
+                    process::set_fd(*fd, file.as_ref().map(AsRawFd::as_raw_fd))?;
+                }
+                process::restore_terminal_signals();
+                Ok(())
+            });
+        }
+
+        if shell.exec_external {
+            // the forked stage has nothing left to do, so it is replaced by the program.
+            let err = command.exec();
+            return Err(ShellError::CommandExecutionFail(err.to_string()));
+        }
+
+        let child = command
             .spawn()
             .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
 
-        child
-            .wait()
+        let pid = child.id() as libc::pid_t;
+
+        if job_control {
+            // set in the parent as well, so the group exists before waiting on it.
+            let _ = process::set_process_group(pid, pid);
+
+            let command_line = std::iter::once(self.name.as_str())
+                .chain(args_text.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let state = shell
+                .jobs
+                .run_in_foreground(Job::new(pid, vec![pid], command_line));
+            return Ok(shell.foreground_status(state));
+        }
+
+        let raw_status = process::wait_pid(pid)
             .map_err(|err| ShellError::CommandExecutionFail(err.to_string()))?;
 
-        Ok(())
+        Ok(shell.foreground_status(JobState::Done(raw_status)))
     }
 
     fn get_name(&self) -> String {
@@ -57,14 +116,16 @@ impl Command for ExternalCommand {
             self.get_name()
         );
 
-        let man_cmd = shell.cmd_registry.get_command("man");
+        let man_cmd = shell
+            .cmd_registry
+            .get_command("man", shell.variables.get("PATH"));
         if let Some(man_cmd) = man_cmd {
-            man_cmd.clone().run(vec![&self.get_name()], shell)?;
-            return Ok("".to_string());
+            man_cmd.run(vec![&self.get_name()], shell)?;
+            Ok("".to_string())
         } else {
-            return Err(ShellError::CommandExecutionFail(
+            Err(ShellError::CommandExecutionFail(
                 "no man command found. can't display help message for external command".to_string(),
-            ));
+            ))
         }
     }
 }
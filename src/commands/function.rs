@@ -0,0 +1,39 @@
+use crate::{
+    app::{Shell, ShellError},
+    parser,
+};
+
+use super::Command;
+
+/// A function defined with `name() { ...; }` or `function name { ...; }`.
+#[derive(Debug)]
+pub struct FunctionCommand {
+    /// The name of the function.
+    name: String,
+    /// The compound command that runs when the function is called.
+    body: parser::Command,
+}
+
+impl FunctionCommand {
+    pub fn new(name: String, body: parser::Command) -> Self {
+        Self { name, body }
+    }
+}
+
+impl Command for FunctionCommand {
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError> {
+        let args = args.into_iter().map(str::to_string).collect();
+        shell.run_function(&self.name, &self.body, args)
+    }
+
+    fn get_name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn get_help_message(&self, _: &mut Shell) -> Result<String, ShellError> {
+        Ok(format!(
+            "{} is a shell function:\n{}() {}",
+            self.name, self.name, self.body
+        ))
+    }
+}
@@ -7,14 +7,18 @@ pub use builtins::*;
 mod external;
 pub use external::ExternalCommand;
 
-use crate::app::{Shell, ShellError};
+mod function;
+pub use function::FunctionCommand;
+
+use crate::app::{ArgCompletion, Shell, ShellError};
 
 pub trait Command
 where
     Self: std::fmt::Debug,
 {
     /// Executes the command with the given arguments.
-    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<(), ShellError>;
+    /// Returns the exit status of the command, zero means success.
+    fn run(&self, args: Vec<&str>, shell: &mut Shell) -> Result<i32, ShellError>;
 
     /// Returns the name of the command.
     fn get_name(&self) -> String;
@@ -22,6 +26,12 @@ where
     /// Returns a message describing the help of the command.
     fn get_help_message(&self, shell: &mut Shell) -> Result<String, ShellError>;
 
+    /// Returns what the arguments of the command are completed with.
+    /// Defaults to file and directory paths.
+    fn arg_completion(&self) -> ArgCompletion {
+        ArgCompletion::Paths
+    }
+
     #[cfg(debug_assertions)]
     fn debug_print_message(&self) -> String {
         format!("{:?}", self)
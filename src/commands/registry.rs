@@ -1,20 +1,33 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::Path,
+    sync::Arc,
+};
 
 use is_executable::IsExecutable;
 
 use crate::dprintln;
 
 use super::{
-    CdCommand, Command, EchoCommand, ExitCommand, ExternalCommand, HelpCommand, PwdCommand,
+    AliasCommand, BgCommand, BreakCommand, CdCommand, Command, ContinueCommand, DisownCommand,
+    EchoCommand, EnvCommand, ExitCommand, ExportCommand, ExternalCommand, FgCommand, HelpCommand,
+    JobsCommand, LocalCommand, PwdCommand, ReturnCommand, SetCommand, SourceCommand,
+    UnaliasCommand, UnsetCommand, WaitCommand,
 };
 
 pub struct CommandsRegistry {
+    /// Aliases defined with `alias`, the first word of a command is replaced by their value.
+    /// The key is the alias name and the value is the replacement text.
+    aliases: BTreeMap<String, String>,
+    /// Registry of shell functions, they take precedence over builtin and external commands.
+    /// The key is the function name and the value is the function itself.
+    functions: HashMap<String, Arc<dyn Command>>,
     /// Registry of builtin commands.
     /// The key is the command name and the value is the command itself.
     builtin: HashMap<String, Arc<dyn Command>>,
-    /// Registry of external commands.
-    /// The key is the command name and the value is the command itself.
-    external: HashMap<String, Arc<dyn Command>>,
+    /// Names of the external commands found in `PATH` at startup, they are completed and suggested.
+    /// Commands are looked up in `PATH` when they run, see `get_command`.
+    external: HashSet<String>,
 
     /// List of registered command names.
     pub registered_names: Vec<String>,
@@ -22,20 +35,88 @@ pub struct CommandsRegistry {
 
 impl CommandsRegistry {
     /// Creates a new instance of the `CommandsRegistry` struct.
-    /// Initializes the registry hash maps for builtin and external commands.
+    /// Initializes the registry hash maps for functions, builtin and external commands.
     pub fn new() -> Self {
         Self {
+            aliases: BTreeMap::new(),
+            functions: HashMap::new(),
             builtin: HashMap::new(),
-            external: HashMap::new(),
+            external: HashSet::new(),
 
             registered_names: Vec::new(),
         }
     }
 
-    /// Returns a reference to the `Command` with the given name if it exists.
-    /// First checks builtin commands, then external commands.
-    pub fn get_command(&self, name: &str) -> Option<&Arc<dyn Command>> {
-        self.builtin.get(name).or_else(|| self.external.get(name))
+    /// Returns the `Command` with the given name if it exists.
+    /// A name containing `/` is the path of an external command.
+    /// Otherwise checks functions, then builtin commands, then the directories of `path_var`.
+    pub fn get_command(&self, name: &str, path_var: Option<&str>) -> Option<Arc<dyn Command>> {
+        if name.contains('/') {
+            return Path::new(name)
+                .is_file()
+                .then(|| external_command(name, name.to_string()));
+        }
+
+        self.functions
+            .get(name)
+            .or_else(|| self.builtin.get(name))
+            .cloned()
+            .or_else(|| find_in_path(name, path_var?))
+    }
+
+    /// Registers a function, replacing a previous definition with the same name.
+    /// The name is added to `registered_names`, so it is completed and suggested.
+    pub fn register_function(&mut self, function: Arc<dyn Command>) {
+        let name = function.get_name();
+        dprintln!("defining function: {}", name);
+
+        self.add_registered_name(&name);
+        self.functions.insert(name, function);
+    }
+
+    /// Returns the value of the alias with the given name if it exists.
+    pub fn get_alias(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    /// Returns all aliases, sorted by name.
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// Defines an alias, replacing a previous one with the same name.
+    /// The name is added to `registered_names`, so it is completed and suggested.
+    pub fn set_alias(&mut self, name: String, value: String) {
+        dprintln!("defining alias: {}={:?}", name, value);
+
+        self.add_registered_name(&name);
+        self.aliases.insert(name, value);
+    }
+
+    /// Removes an alias, returns `false` if it does not exist.
+    pub fn remove_alias(&mut self, name: &str) -> bool {
+        if self.aliases.remove(name).is_none() {
+            return false;
+        }
+
+        let is_command = self.functions.contains_key(name)
+            || self.builtin.contains_key(name)
+            || self.external.contains(name);
+        if !is_command {
+            self.registered_names
+                .retain(|registered| registered != name);
+        }
+        true
+    }
+
+    /// Inserts a name into the sorted `registered_names` unless it is already there.
+    fn add_registered_name(&mut self, name: &str) {
+        if let Err(index) = self
+            .registered_names
+            .binary_search_by(|registered| registered.as_str().cmp(name))
+        {
+            self.registered_names.insert(index, name.to_string());
+        }
     }
 
     /// Registers a new `Command`.
@@ -53,7 +134,7 @@ impl CommandsRegistry {
         self.builtin.insert(name, command);
     }
 
-    /// Registers all external commands in the `PATH` environment variable.
+    /// Registers the names of all external commands in the `PATH` environment variable.
     pub fn register_external(&mut self) {
         if let Ok(paths) = std::env::var("PATH") {
             for dir in paths.split(':') {
@@ -74,16 +155,10 @@ impl CommandsRegistry {
                             Err(_) => continue,
                         };
 
-                        if self.external.contains_key(&name) {
+                        if !self.external.insert(name) {
                             continue;
                         }
 
-                        let executable_path = path.to_string_lossy().to_string();
-                        let external_command = ExternalCommand::new(name.clone(), executable_path);
-
-                        self.external
-                            .insert(external_command.get_name(), Arc::new(external_command));
-
                         #[cfg(debug_assertions)]
                         {
                             count += 1;
@@ -101,18 +176,35 @@ impl CommandsRegistry {
     }
 
     /// Populates the `registered_names` field of the `CommandsRegistry` struct.
-    /// Sorts the list of registered command names alphabetically.
+    /// Sorts the list of registered command names alphabetically, a name shared by several kinds appears once.
     pub fn populate_registered_names(&mut self) {
         let mut names = Vec::new();
 
+        names.extend(self.aliases.keys().map(|k| k.to_string()));
+        names.extend(self.functions.keys().map(|k| k.to_string()));
         names.extend(self.builtin.keys().map(|k| k.to_string()));
-        names.extend(self.external.keys().map(|k| k.to_string()));
+        names.extend(self.external.iter().cloned());
 
         names.sort();
+        names.dedup();
         self.registered_names = names;
     }
 }
 
+/// Searches the directories of `path_var` for an executable file named `name`.
+/// An empty directory is the current directory.
+fn find_in_path(name: &str, path_var: &str) -> Option<Arc<dyn Command>> {
+    path_var
+        .split(':')
+        .map(|dir| Path::new(if dir.is_empty() { "." } else { dir }).join(name))
+        .find(|path| path.is_file() && path.is_executable())
+        .map(|path| external_command(name, path.to_string_lossy().to_string()))
+}
+
+fn external_command(name: &str, path: String) -> Arc<dyn Command> {
+    Arc::new(ExternalCommand::new(name.to_string(), path))
+}
+
 macro_rules! register_builtins {
     ($registry:expr, $( $cmd:expr ),* ) => {
         $( $registry.register_builtin(Arc::new($cmd)); )*
@@ -144,7 +236,24 @@ impl Default for CommandsRegistry {
             EchoCommand,
             PwdCommand,
             CdCommand,
-            HelpCommand
+            HelpCommand,
+            ExportCommand,
+            UnsetCommand,
+            SetCommand,
+            EnvCommand,
+            JobsCommand,
+            FgCommand,
+            BgCommand,
+            WaitCommand,
+            DisownCommand,
+            BreakCommand,
+            ContinueCommand,
+            ReturnCommand,
+            LocalCommand,
+            SourceCommand::new("source"),
+            SourceCommand::new("."),
+            AliasCommand,
+            UnaliasCommand
         );
 
         registry.register_external();
@@ -154,3 +263,89 @@ impl Default for CommandsRegistry {
         registry
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, os::unix, sync::Arc};
+
+    use super::CommandsRegistry;
+    use crate::commands::EchoCommand;
+
+    fn alias_registry() -> CommandsRegistry {
+        let mut registry = CommandsRegistry::new();
+        registry.register_builtin(Arc::new(EchoCommand));
+        registry.populate_registered_names();
+        for name in ["ll", "echo", "a", "la", "ll"] {
+            registry.set_alias(name.to_string(), format!("{} -x", name));
+        }
+        registry
+    }
+
+    #[test]
+    fn test_set_alias_keeps_names_sorted() {
+        let registry = alias_registry();
+
+        assert_eq!(registry.registered_names, ["a", "echo", "la", "ll"]);
+        assert_eq!(registry.get_alias("ll"), Some("ll -x"));
+        assert_eq!(registry.get_alias("ls"), None);
+    }
+
+    #[test]
+    fn test_remove_alias_keeps_command_names() {
+        let mut registry = alias_registry();
+
+        assert!(registry.remove_alias("la"));
+        assert!(!registry.remove_alias("la"));
+        // `echo` is still a builtin.
+        assert!(registry.remove_alias("echo"));
+        assert_eq!(registry.registered_names, ["a", "echo", "ll"]);
+        assert_eq!(registry.get_alias("echo"), None);
+    }
+
+    #[test]
+    fn test_populate_registered_names_includes_aliases() {
+        let mut registry = alias_registry();
+        registry.populate_registered_names();
+
+        assert_eq!(registry.registered_names, ["a", "echo", "la", "ll"]);
+    }
+
+    #[test]
+    fn test_get_command_searches_path_when_called() {
+        let dir = env::temp_dir().join(format!("shellrs-path-{:x}", rand::random::<u64>()));
+        fs::create_dir_all(dir.join("bin")).unwrap();
+        unix::fs::symlink("/bin/sh", dir.join("bin/tool")).unwrap();
+        fs::write(dir.join("bin/data"), "").unwrap();
+        let path = format!("/nonexistent:{}", dir.join("bin").display());
+        let registry = alias_registry();
+        let command = |name: &str, path_var| {
+            registry
+                .get_command(name, path_var)
+                .map(|command| format!("{:?}", command))
+        };
+
+        let tool = dir.join("bin/tool");
+        let found = command("tool", Some(&path)).unwrap();
+        assert!(found.contains(&format!("{:?}", tool.display().to_string())));
+        assert_eq!(command("tool", None), None);
+        // files that are not executable are skipped.
+        assert_eq!(command("data", Some(&path)), None);
+        assert_eq!(
+            command("echo", Some(&path)),
+            Some("EchoCommand".to_string())
+        );
+
+        // a name with `/` is run directly, without searching `PATH`.
+        let tool = tool.display().to_string();
+        assert!(command(&tool, None)
+            .unwrap()
+            .contains(&format!("{:?}", tool)));
+        assert_eq!(
+            command(&format!("{}/bin/missing", dir.display()), None),
+            None
+        );
+        assert_eq!(command(&format!("{}/bin", dir.display()), None), None);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,8 +1,61 @@
+use std::io::{self, IsTerminal, Read};
+
 mod app;
 mod commands;
 mod debug_print;
+mod parser;
 
 fn main() {
+    let mut args = std::env::args().peekable();
+    // a login shell is started as `-shellrs` or with `-l`.
+    let mut login = args.next().is_some_and(|arg0| arg0.starts_with('-'));
+    if args
+        .next_if(|arg| arg == "-l" || arg == "--login")
+        .is_some()
+    {
+        login = true;
+    }
     let mut shell = app::Shell::new();
-    shell.run_repl();
+
+    // `shellrs -c command [name [args...]]`, `shellrs script [args...]`, commands piped to stdin
+    // or the interactive REPL.
+    let (source, name) = match args.next() {
+        Some(flag) if flag == "-c" => match args.next() {
+            Some(command) => {
+                let name = args
+                    .next()
+                    .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string());
+                (command, name)
+            }
+            None => fail(2, "-c: option requires an argument"),
+        },
+        Some(path) => match std::fs::read_to_string(&path) {
+            Ok(source) => (source, path),
+            Err(err) => fail(127, &format!("{}: {}", path, err)),
+        },
+        None if !io::stdin().is_terminal() => {
+            let mut source = String::new();
+            if let Err(err) = io::stdin().read_to_string(&mut source) {
+                fail(1, &format!("failed to read stdin: {}", err));
+            }
+            (source, env!("CARGO_PKG_NAME").to_string())
+        }
+        None => {
+            shell.run_repl(login);
+            return;
+        }
+    };
+
+    if login {
+        shell.source_profile();
+    }
+
+    let status = shell.run_script(source, name, args.collect());
+    std::process::exit(status);
+}
+
+/// Prints an error about the command line arguments and exits.
+fn fail(status: i32, message: &str) -> ! {
+    eprintln!("{}: {}", env!("CARGO_PKG_NAME"), message);
+    std::process::exit(status);
 }
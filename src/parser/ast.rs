@@ -0,0 +1,337 @@
+use std::fmt;
+
+use super::Redirection;
+use crate::app::Variables;
+
+/// A range of byte offsets in the parsed source.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+/// A word of the source.
+/// Its parts are expanded right before the command runs,
+/// the raw text keeps quotes and escapes for messages and alias values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    /// The source text, without line continuations.
+    pub raw: String,
+    pub parts: Vec<WordPart>,
+    pub span: Span,
+}
+
+/// A quote-aware part of a word.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    /// Unquoted text.
+    Literal(String),
+    /// A character escaped with a backslash.
+    Escaped(char),
+    /// The text between single quotes.
+    SingleQuoted(String),
+    /// The parts between double quotes.
+    DoubleQuoted(Vec<WordPart>),
+    /// `$name` or `${...}` as written.
+    Parameter(String),
+    /// `$(...)` or `` `...` `` as written.
+    CommandSubstitution(String),
+}
+
+impl Word {
+    /// Returns the text of a word that is nothing but unquoted literal text, like a reserved word.
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(text)] => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if any part of the word is quoted or escaped.
+    pub fn is_quoted(&self) -> bool {
+        self.parts.iter().any(|part| {
+            matches!(
+                part,
+                WordPart::Escaped(_) | WordPart::SingleQuoted(_) | WordPart::DoubleQuoted(_)
+            )
+        })
+    }
+
+    /// Returns the text of the word with quotes and escapes removed, without expanding anything.
+    pub fn unquoted(&self) -> String {
+        fn push_parts(text: &mut String, parts: &[WordPart]) {
+            for part in parts {
+                match part {
+                    WordPart::Escaped(c) => text.push(*c),
+                    WordPart::DoubleQuoted(parts) => push_parts(text, parts),
+                    WordPart::Literal(part)
+                    | WordPart::SingleQuoted(part)
+                    | WordPart::Parameter(part)
+                    | WordPart::CommandSubstitution(part) => text.push_str(part),
+                }
+            }
+        }
+
+        let mut text = String::new();
+        push_parts(&mut text, &self.parts);
+        text
+    }
+
+    /// Returns `true` if the word is a `NAME=value` assignment, the name has to be unquoted.
+    pub fn is_assignment(&self) -> bool {
+        self.assignment().is_some()
+    }
+
+    /// Splits a `NAME=value` assignment into the name and the parts of the value.
+    pub fn assignment(&self) -> Option<(&str, Vec<WordPart>)> {
+        let Some(WordPart::Literal(text)) = self.parts.first() else {
+            return None;
+        };
+        let (name, value) = text.split_once('=')?;
+        if !Variables::is_valid_name(name) {
+            return None;
+        }
+
+        let mut parts = Vec::new();
+        if !value.is_empty() {
+            parts.push(WordPart::Literal(value.to_string()));
+        }
+        parts.extend_from_slice(&self.parts[1..]);
+        Some((name, parts))
+    }
+}
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+/// A list of and-or lists separated by `;`, `&` or newlines.
+#[derive(Debug, Clone, Default)]
+pub struct CommandList {
+    pub items: Vec<ListItem>,
+}
+
+/// An and-or list in a command list.
+#[derive(Debug, Clone)]
+pub struct ListItem {
+    pub and_or: AndOr,
+    /// Whether the and-or list runs in the background (ends with `&`).
+    pub background: bool,
+}
+
+/// Pipelines joined by `&&` and `||`.
+#[derive(Debug, Clone)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+}
+
+/// How a pipeline in an and-or list depends on the exit status of the previous one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connector {
+    /// Runs only if the previous exit status is zero (after `&&`).
+    And,
+    /// Runs only if the previous exit status is non-zero (after `||`).
+    Or,
+}
+
+/// Commands joined by `|`, optionally negated with `!`.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    pub negated: bool,
+    pub commands: Vec<Command>,
+}
+
+/// A single command of a pipeline.
+#[derive(Debug, Clone)]
+pub enum Command {
+    Simple(SimpleCommand),
+    /// A compound command with the redirections that follow it.
+    Compound(CompoundCommand, Vec<Redirection>),
+    /// `name() compound-command` or `function name compound-command`, defines a function.
+    Function {
+        name: String,
+        body: Box<Command>,
+    },
+}
+
+/// Assignments, words and redirections, e.g. `LANG=C ls -l >out`.
+#[derive(Debug, Clone, Default)]
+pub struct SimpleCommand {
+    /// The leading `NAME=value` words.
+    pub assignments: Vec<Word>,
+    pub words: Vec<Word>,
+    pub redirections: Vec<Redirection>,
+}
+
+#[derive(Debug, Clone)]
+pub enum CompoundCommand {
+    /// `{ list; }`, runs the list in the shell itself.
+    BraceGroup(CommandList),
+    /// `( list )`, runs the list in a forked child.
+    Subshell(CommandList),
+    /// `if list; then list; [elif list; then list;]... [else list;] fi`.
+    If {
+        /// The conditions with the lists they guard, in order.
+        branches: Vec<(CommandList, CommandList)>,
+        else_branch: Option<CommandList>,
+    },
+    /// `while list; do list; done` or `until list; do list; done`.
+    Loop {
+        condition: CommandList,
+        body: CommandList,
+        /// Whether the loop runs while the condition fails (`until`).
+        until: bool,
+    },
+    /// `for name [in words]; do list; done`.
+    For {
+        name: String,
+        /// The words after `in`, or `None` to loop over the positional parameters.
+        words: Option<Vec<Word>>,
+        body: CommandList,
+    },
+    /// `case word in [(]pattern[|pattern]...) list;; ... esac`.
+    Case { word: Word, items: Vec<CaseItem> },
+}
+
+/// A clause of a case command, e.g. `*.txt|*.md) echo text;;`.
+#[derive(Debug, Clone)]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body: CommandList,
+}
+
+impl CommandList {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl fmt::Display for CommandList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, item) in self.items.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", item.and_or)?;
+            write!(f, "{}", if item.background { " &" } else { ";" })?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for AndOr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first)?;
+        for (connector, pipeline) in &self.rest {
+            let operator = match connector {
+                Connector::And => "&&",
+                Connector::Or => "||",
+            };
+            write!(f, " {} {}", operator, pipeline)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "! ")?;
+        }
+        let commands: Vec<String> = self.commands.iter().map(ToString::to_string).collect();
+        write!(f, "{}", commands.join(" | "))
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Simple(simple) => write!(f, "{}", simple),
+            Command::Compound(compound, redirections) => {
+                write!(f, "{}", compound)?;
+                for redirection in redirections {
+                    write!(f, " {}", redirection)?;
+                }
+                Ok(())
+            }
+            Command::Function { name, body } => write!(f, "{}() {}", name, body),
+        }
+    }
+}
+
+impl fmt::Display for CompoundCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompoundCommand::BraceGroup(list) => write!(f, "{{ {} }}", list),
+            CompoundCommand::Subshell(list) => {
+                write!(f, "( {} )", list.to_string().trim_end_matches(';'))
+            }
+            CompoundCommand::If {
+                branches,
+                else_branch,
+            } => {
+                for (i, (condition, body)) in branches.iter().enumerate() {
+                    let keyword = if i == 0 { "if" } else { " elif" };
+                    write!(f, "{} {} then {}", keyword, condition, body)?;
+                }
+                if let Some(list) = else_branch {
+                    write!(f, " else {}", list)?;
+                }
+                write!(f, " fi")
+            }
+            CompoundCommand::Loop {
+                condition,
+                body,
+                until,
+            } => {
+                let keyword = if *until { "until" } else { "while" };
+                write!(f, "{} {} do {} done", keyword, condition, body)
+            }
+            CompoundCommand::For { name, words, body } => {
+                write!(f, "for {}", name)?;
+                if let Some(words) = words {
+                    write!(f, " in")?;
+                    for word in words {
+                        write!(f, " {}", word)?;
+                    }
+                }
+                write!(f, "; do {} done", body)
+            }
+            CompoundCommand::Case { word, items } => {
+                write!(f, "case {} in", word)?;
+                for item in items {
+                    let patterns: Vec<String> =
+                        item.patterns.iter().map(ToString::to_string).collect();
+                    let body = item.body.to_string();
+                    write!(
+                        f,
+                        " {}) {};;",
+                        patterns.join("|"),
+                        body.trim_end_matches(';')
+                    )?;
+                }
+                write!(f, " esac")
+            }
+        }
+    }
+}
+
+impl fmt::Display for SimpleCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let words = self.assignments.iter().chain(&self.words);
+        let parts: Vec<String> = words
+            .map(ToString::to_string)
+            .chain(self.redirections.iter().map(ToString::to_string))
+            .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
@@ -0,0 +1,39 @@
+use thiserror::Error;
+
+use super::Span;
+
+/// An error found while parsing the shell input, with the span of the offending source.
+#[derive(Debug, Error)]
+#[error("{kind}")]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Error)]
+pub enum ParseErrorKind {
+    /// A token that is not allowed where it appears.
+    #[error("syntax error near unexpected token `{0}`")]
+    UnexpectedToken(String),
+    /// The input ended in the middle of a command, e.g. after `&&` or inside `{ ...`.
+    #[error("syntax error: unexpected end of input")]
+    UnexpectedEof,
+    /// The input ended inside a quote or a substitution, with the character that would close it.
+    #[error("unexpected end of input while looking for matching `{0}`")]
+    Unterminated(char),
+    /// The input ended before the delimiter of a here-document.
+    #[error("unexpected end of input while looking for here-document delimiter `{0}`")]
+    UnterminatedHereDocument(String),
+}
+
+impl ParseError {
+    /// Returns `true` if the input ended too early, so more input could complete it.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self.kind,
+            ParseErrorKind::UnexpectedEof
+                | ParseErrorKind::Unterminated(_)
+                | ParseErrorKind::UnterminatedHereDocument(_)
+        )
+    }
+}
@@ -0,0 +1,715 @@
+use std::collections::VecDeque;
+
+use super::{
+    lexer::{Operator, Token},
+    AndOr, CaseItem, Command, CommandList, CompoundCommand, Connector, HereDocument, ListItem,
+    ParseError, ParseErrorKind, Pipeline, Redirection, RedirectionKind, SimpleCommand, Span, Word,
+};
+use crate::app::Variables;
+
+/// Reserved words that end the list of a compound command.
+const CLOSING_WORDS: [&str; 8] = ["}", "then", "elif", "else", "fi", "do", "done", "esac"];
+
+/// Reserved words that start a compound command, besides `(`.
+const COMPOUND_WORDS: [&str; 6] = ["{", "if", "while", "until", "for", "case"];
+
+/// A recursive-descent parser over the tokens of the whole input.
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The here-documents read by the lexer, in the order of their operators.
+    here_documents: VecDeque<HereDocument>,
+    /// The length of the source, where the unexpected end of input is reported.
+    source_len: usize,
+}
+
+impl Parser {
+    pub fn new(
+        tokens: Vec<Token>,
+        here_documents: VecDeque<HereDocument>,
+        source_len: usize,
+    ) -> Self {
+        Self {
+            tokens,
+            pos: 0,
+            here_documents,
+            source_len,
+        }
+    }
+
+    /// Parses the whole input into a command list.
+    pub fn parse_program(&mut self) -> Result<CommandList, ParseError> {
+        let list = self.parse_list()?;
+        match self.peek() {
+            Some(token) => Err(unexpected(token)),
+            None => Ok(list),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_operator(&self) -> Option<&Operator> {
+        match self.peek()? {
+            Token::Operator(operator, _) => Some(operator),
+            Token::Word(_) => None,
+        }
+    }
+
+    /// Returns `true` if the next token is the unquoted reserved word `word`.
+    fn peek_reserved(&self, word: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(next)) if next.as_literal() == Some(word))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_operator(&mut self, operator: Operator) -> bool {
+        if self.peek_operator() == Some(&operator) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn eat_reserved(&mut self, word: &str) -> bool {
+        if self.peek_reserved(word) {
+            self.pos += 1;
+            return true;
+        }
+        false
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.eat_operator(Operator::Newline) {}
+    }
+
+    /// Returns the error for the next token, or for the end of input.
+    fn error_at_next(&self) -> ParseError {
+        match self.peek() {
+            Some(token) => unexpected(token),
+            None => ParseError {
+                kind: ParseErrorKind::UnexpectedEof,
+                span: Span::new(self.source_len, self.source_len),
+            },
+        }
+    }
+
+    /// Returns `true` if the next token ends the current list: the end of input, `)`, `;;` or a closing reserved word.
+    fn at_list_end(&self) -> bool {
+        match self.peek() {
+            None => true,
+            Some(Token::Operator(operator, _)) => {
+                matches!(operator, Operator::RightParen | Operator::DoubleSemicolon)
+            }
+            Some(Token::Word(word)) => word
+                .as_literal()
+                .is_some_and(|word| CLOSING_WORDS.contains(&word)),
+        }
+    }
+
+    /// Parses and-or lists separated by `;`, `&` or newlines, up to the end of the list.
+    fn parse_list(&mut self) -> Result<CommandList, ParseError> {
+        let mut items = Vec::new();
+
+        loop {
+            self.skip_newlines();
+            if self.at_list_end() {
+                break;
+            }
+
+            let and_or = self.parse_and_or()?;
+            let background = match self.peek_operator() {
+                Some(Operator::Background) => true,
+                Some(Operator::Semicolon | Operator::Newline) => false,
+                _ => {
+                    items.push(ListItem {
+                        and_or,
+                        background: false,
+                    });
+                    break;
+                }
+            };
+            self.pos += 1;
+            items.push(ListItem { and_or, background });
+        }
+
+        Ok(CommandList { items })
+    }
+
+    /// Parses the list of a compound command, which must not be empty.
+    fn parse_compound_list(&mut self) -> Result<CommandList, ParseError> {
+        let list = self.parse_list()?;
+        if list.is_empty() {
+            return Err(self.error_at_next());
+        }
+        Ok(list)
+    }
+
+    fn parse_and_or(&mut self) -> Result<AndOr, ParseError> {
+        let first = self.parse_pipeline()?;
+        let mut rest = Vec::new();
+
+        loop {
+            let connector = match self.peek_operator() {
+                Some(Operator::And) => Connector::And,
+                Some(Operator::Or) => Connector::Or,
+                _ => break,
+            };
+            self.pos += 1;
+            self.skip_newlines();
+            rest.push((connector, self.parse_pipeline()?));
+        }
+
+        Ok(AndOr { first, rest })
+    }
+
+    fn parse_pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let negated = self.eat_reserved("!");
+        let mut commands = vec![self.parse_command()?];
+
+        while self.eat_operator(Operator::Pipe) {
+            self.skip_newlines();
+            commands.push(self.parse_command()?);
+        }
+
+        Ok(Pipeline { negated, commands })
+    }
+
+    fn parse_command(&mut self) -> Result<Command, ParseError> {
+        let compound = if self.eat_reserved("{") {
+            let list = self.parse_compound_list()?;
+            self.expect_reserved("}")?;
+            CompoundCommand::BraceGroup(list)
+        } else if self.eat_operator(Operator::LeftParen) {
+            let list = self.parse_compound_list()?;
+            if !self.eat_operator(Operator::RightParen) {
+                return Err(self.error_at_next());
+            }
+            CompoundCommand::Subshell(list)
+        } else if self.eat_reserved("if") {
+            self.parse_if()?
+        } else if self.eat_reserved("while") {
+            self.parse_loop(false)?
+        } else if self.eat_reserved("until") {
+            self.parse_loop(true)?
+        } else if self.eat_reserved("for") {
+            self.parse_for()?
+        } else if self.eat_reserved("case") {
+            self.parse_case()?
+        } else if self.eat_reserved("function") {
+            let word = self.expect_word()?;
+            let Some(name) = word.as_literal() else {
+                return Err(unexpected(&Token::Word(word)));
+            };
+            let name = name.to_string();
+            if self.eat_operator(Operator::LeftParen) && !self.eat_operator(Operator::RightParen) {
+                return Err(self.error_at_next());
+            }
+            return self.parse_function_body(name);
+        } else if let Some(name) = self.peek_function_definition() {
+            self.pos += 3;
+            return self.parse_function_body(name);
+        } else {
+            return self.parse_simple_command().map(Command::Simple);
+        };
+
+        let mut redirections = Vec::new();
+        while let Some(Operator::Redirect(_)) = self.peek_operator() {
+            redirections.push(self.parse_redirection()?);
+        }
+        Ok(Command::Compound(compound, redirections))
+    }
+
+    /// Returns the name of a `name()` function definition that starts at the next token.
+    fn peek_function_definition(&self) -> Option<String> {
+        let Some(Token::Word(word)) = self.peek() else {
+            return None;
+        };
+        let parens = (self.tokens.get(self.pos + 1), self.tokens.get(self.pos + 2));
+        match parens {
+            (
+                Some(Token::Operator(Operator::LeftParen, _)),
+                Some(Token::Operator(Operator::RightParen, _)),
+            ) => word.as_literal().map(str::to_string),
+            _ => None,
+        }
+    }
+
+    /// Parses the body of a function definition, which has to be a compound command.
+    fn parse_function_body(&mut self, name: String) -> Result<Command, ParseError> {
+        self.skip_newlines();
+        let at_compound_command = self.peek_operator() == Some(&Operator::LeftParen)
+            || COMPOUND_WORDS.iter().any(|word| self.peek_reserved(word));
+        if !at_compound_command {
+            return Err(self.error_at_next());
+        }
+
+        let body = self.parse_command()?;
+        Ok(Command::Function {
+            name,
+            body: Box::new(body),
+        })
+    }
+
+    /// Parses the rest of an if command, after `if`.
+    fn parse_if(&mut self) -> Result<CompoundCommand, ParseError> {
+        let mut branches = Vec::new();
+        let mut else_branch = None;
+
+        loop {
+            let condition = self.parse_compound_list()?;
+            self.expect_reserved("then")?;
+            branches.push((condition, self.parse_compound_list()?));
+
+            if self.eat_reserved("elif") {
+                continue;
+            }
+            if self.eat_reserved("else") {
+                else_branch = Some(self.parse_compound_list()?);
+            }
+            self.expect_reserved("fi")?;
+            break;
+        }
+
+        Ok(CompoundCommand::If {
+            branches,
+            else_branch,
+        })
+    }
+
+    /// Parses the rest of a while or until loop, after the reserved word.
+    fn parse_loop(&mut self, until: bool) -> Result<CompoundCommand, ParseError> {
+        let condition = self.parse_compound_list()?;
+        let body = self.parse_do_group()?;
+        Ok(CompoundCommand::Loop {
+            condition,
+            body,
+            until,
+        })
+    }
+
+    /// Parses the rest of a for loop, after `for`.
+    fn parse_for(&mut self) -> Result<CompoundCommand, ParseError> {
+        let word = self.expect_word()?;
+        let name = match word.as_literal() {
+            Some(name) if Variables::is_valid_name(name) => name.to_string(),
+            _ => return Err(unexpected(&Token::Word(word))),
+        };
+
+        self.skip_newlines();
+        let words = if self.eat_reserved("in") {
+            let mut words = Vec::new();
+            loop {
+                match self.peek() {
+                    Some(Token::Word(word)) => {
+                        words.push(word.clone());
+                        self.pos += 1;
+                    }
+                    Some(Token::Operator(Operator::Semicolon | Operator::Newline, _)) => {
+                        self.pos += 1;
+                        break;
+                    }
+                    _ => return Err(self.error_at_next()),
+                }
+            }
+            Some(words)
+        } else {
+            self.eat_operator(Operator::Semicolon);
+            None
+        };
+
+        self.skip_newlines();
+        let body = self.parse_do_group()?;
+        Ok(CompoundCommand::For { name, words, body })
+    }
+
+    /// Parses `do list done`.
+    fn parse_do_group(&mut self) -> Result<CommandList, ParseError> {
+        self.expect_reserved("do")?;
+        let body = self.parse_compound_list()?;
+        self.expect_reserved("done")?;
+        Ok(body)
+    }
+
+    /// Parses the rest of a case command, after `case`.
+    fn parse_case(&mut self) -> Result<CompoundCommand, ParseError> {
+        let word = self.expect_word()?;
+        self.skip_newlines();
+        self.expect_reserved("in")?;
+
+        let mut items = Vec::new();
+        loop {
+            self.skip_newlines();
+            if self.eat_reserved("esac") {
+                break;
+            }
+
+            self.eat_operator(Operator::LeftParen);
+            let mut patterns = Vec::new();
+            loop {
+                patterns.push(self.expect_word()?);
+                if !self.eat_operator(Operator::Pipe) {
+                    break;
+                }
+            }
+            if !self.eat_operator(Operator::RightParen) {
+                return Err(self.error_at_next());
+            }
+
+            let body = self.parse_list()?;
+            items.push(CaseItem { patterns, body });
+
+            if !self.eat_operator(Operator::DoubleSemicolon) {
+                self.skip_newlines();
+                self.expect_reserved("esac")?;
+                break;
+            }
+        }
+
+        Ok(CompoundCommand::Case { word, items })
+    }
+
+    fn expect_word(&mut self) -> Result<Word, ParseError> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let word = word.clone();
+                self.pos += 1;
+                Ok(word)
+            }
+            _ => Err(self.error_at_next()),
+        }
+    }
+
+    fn expect_reserved(&mut self, word: &str) -> Result<(), ParseError> {
+        if !self.eat_reserved(word) {
+            return Err(self.error_at_next());
+        }
+        Ok(())
+    }
+
+    fn parse_simple_command(&mut self) -> Result<SimpleCommand, ParseError> {
+        let mut command = SimpleCommand::default();
+
+        loop {
+            match self.peek() {
+                Some(Token::Word(word)) => {
+                    // leading `NAME=value` words are variable assignments.
+                    if command.words.is_empty() && word.is_assignment() {
+                        command.assignments.push(word.clone());
+                    } else {
+                        command.words.push(word.clone());
+                    }
+                    self.pos += 1;
+                }
+                Some(Token::Operator(Operator::Redirect(_), _)) => {
+                    command.redirections.push(self.parse_redirection()?);
+                }
+                _ => break,
+            }
+        }
+
+        if command.assignments.is_empty()
+            && command.words.is_empty()
+            && command.redirections.is_empty()
+        {
+            return Err(self.error_at_next());
+        }
+        Ok(command)
+    }
+
+    /// Parses a redirection operator and the word after it.
+    fn parse_redirection(&mut self) -> Result<Redirection, ParseError> {
+        let Some(Token::Operator(Operator::Redirect(operator), span)) = self.next() else {
+            unreachable!("parse_redirection is only called at a redirection operator");
+        };
+
+        let word = match self.peek() {
+            Some(Token::Word(word)) => word.clone(),
+            Some(token) => return Err(unexpected(token)),
+            None => {
+                return Err(ParseError {
+                    kind: ParseErrorKind::UnexpectedToken("newline".to_string()),
+                    span: Span::new(self.source_len, self.source_len),
+                })
+            }
+        };
+        self.pos += 1;
+
+        let mut redirection = Redirection::parse(&operator, word).ok_or_else(|| ParseError {
+            kind: ParseErrorKind::UnexpectedToken(operator.clone()),
+            span,
+        })?;
+        if let RedirectionKind::HereDocument(here_document) = &mut redirection.kind {
+            if let Some(read) = self.here_documents.pop_front() {
+                *here_document = read;
+            }
+        }
+        Ok(redirection)
+    }
+}
+
+fn unexpected(token: &Token) -> ParseError {
+    ParseError {
+        kind: ParseErrorKind::UnexpectedToken(token.as_str().to_string()),
+        span: token.span(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{
+        parse, Command, CompoundCommand, Connector, ParseErrorKind, RedirectionKind,
+    };
+
+    fn simple_words(command: &Command) -> Vec<&str> {
+        match command {
+            Command::Simple(simple) => simple.words.iter().map(|word| word.raw.as_str()).collect(),
+            command => panic!("not a simple command: {:?}", command),
+        }
+    }
+
+    fn syntax_error(source: &str) -> ParseErrorKind {
+        parse(source).unwrap_err().kind
+    }
+
+    #[test]
+    fn test_list() {
+        let list = parse("a 1; b & c && d || e\n\nf").unwrap();
+        assert_eq!(list.items.len(), 4);
+        assert_eq!(
+            simple_words(&list.items[0].and_or.first.commands[0]),
+            ["a", "1"]
+        );
+        assert!(!list.items[0].background);
+        assert!(list.items[1].background);
+
+        let and_or = &list.items[2].and_or;
+        let connectors: Vec<Connector> = and_or.rest.iter().map(|(c, _)| *c).collect();
+        assert_eq!(connectors, [Connector::And, Connector::Or]);
+        assert_eq!(simple_words(&and_or.rest[1].1.commands[0]), ["e"]);
+
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse(" \n # comment\n").unwrap().is_empty());
+        assert_eq!(parse("a;").unwrap().items.len(), 1);
+        assert_eq!(parse("a &&\n b |\n c").unwrap().items.len(), 1);
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let list = parse("! a | b x | c").unwrap();
+        let pipeline = &list.items[0].and_or.first;
+        assert!(pipeline.negated);
+        assert_eq!(pipeline.commands.len(), 3);
+        assert_eq!(simple_words(&pipeline.commands[1]), ["b", "x"]);
+
+        // only an unquoted `!` negates.
+        assert!(!parse("'!' a").unwrap().items[0].and_or.first.negated);
+    }
+
+    #[test]
+    fn test_simple_command() {
+        let list = parse("A=1 B='x y' cmd C=2 >out 2>&1").unwrap();
+        let Command::Simple(command) = &list.items[0].and_or.first.commands[0] else {
+            panic!("not a simple command");
+        };
+        let assignments: Vec<&str> = command.assignments.iter().map(|w| w.raw.as_str()).collect();
+        assert_eq!(assignments, ["A=1", "B='x y'"]);
+        assert_eq!(
+            simple_words(&list.items[0].and_or.first.commands[0]),
+            ["cmd", "C=2"]
+        );
+        assert_eq!(command.redirections.len(), 2);
+
+        let list = parse("'A'=1 >log").unwrap();
+        let Command::Simple(command) = &list.items[0].and_or.first.commands[0] else {
+            panic!("not a simple command");
+        };
+        assert!(command.assignments.is_empty());
+        assert_eq!(command.words.len(), 1);
+    }
+
+    #[test]
+    fn test_compound_commands() {
+        let list = parse("{ a; b\n} >out | (c && d)").unwrap();
+        let commands = &list.items[0].and_or.first.commands;
+
+        let Command::Compound(CompoundCommand::BraceGroup(group), redirections) = &commands[0]
+        else {
+            panic!("not a brace group: {:?}", commands[0]);
+        };
+        assert_eq!(group.items.len(), 2);
+        assert_eq!(redirections.len(), 1);
+
+        let Command::Compound(CompoundCommand::Subshell(subshell), _) = &commands[1] else {
+            panic!("not a subshell: {:?}", commands[1]);
+        };
+        assert_eq!(subshell.items[0].and_or.rest.len(), 1);
+
+        // `}` is only reserved at the start of a command.
+        assert_eq!(
+            simple_words(&parse("echo }").unwrap().items[0].and_or.first.commands[0]),
+            ["echo", "}"]
+        );
+        assert_eq!(parse("{ echo a; } &").unwrap().to_string(), "{ echo a; } &");
+    }
+
+    #[test]
+    fn test_control_flow() {
+        for (source, display) in [
+            (
+                "if a; then b; elif c\nthen d; else e; fi",
+                "if a; then b; elif c; then d; else e; fi;",
+            ),
+            ("while a; do b; c; done", "while a; do b; c; done;"),
+            ("until a\ndo\nb\ndone", "until a; do b; done;"),
+            (
+                "for x in 1 \"2 3\"; do echo $x; done",
+                "for x in 1 \"2 3\"; do echo $x; done;",
+            ),
+            ("for x\ndo b; done", "for x; do b; done;"),
+            (
+                "case $x in\n(a|b) c;;\n*) ;;\nesac",
+                "case $x in a|b) c;; *) ;; esac;",
+            ),
+            ("case x in esac", "case x in esac;"),
+            ("echo if then fi", "echo if then fi;"),
+        ] {
+            assert_eq!(parse(source).unwrap().to_string(), display, "{:?}", source);
+        }
+
+        let list = parse("for i in a b; do :; done > out").unwrap();
+        let Command::Compound(CompoundCommand::For { name, words, .. }, redirections) =
+            &list.items[0].and_or.first.commands[0]
+        else {
+            panic!("not a for loop: {:?}", list);
+        };
+        assert_eq!(name, "i");
+        assert_eq!(words.as_ref().unwrap().len(), 2);
+        assert_eq!(redirections.len(), 1);
+
+        let list = parse("case a in x) b;; y | z) c; d\nesac").unwrap();
+        let Command::Compound(CompoundCommand::Case { items, .. }, _) =
+            &list.items[0].and_or.first.commands[0]
+        else {
+            panic!("not a case command: {:?}", list);
+        };
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1].patterns.len(), 2);
+        assert_eq!(items[1].body.items.len(), 2);
+    }
+
+    #[test]
+    fn test_function_definitions() {
+        for (source, display) in [
+            ("f() { echo a; }", "f() { echo a; };"),
+            ("f ( ) {\necho a\n}", "f() { echo a; };"),
+            ("function f { echo a; }", "f() { echo a; };"),
+            ("function f() ( cd /; ) >out", "f() ( cd / ) >out;"),
+            ("f()\nif a; then b; fi", "f() if a; then b; fi;"),
+        ] {
+            assert_eq!(parse(source).unwrap().to_string(), display, "{:?}", source);
+        }
+
+        let list = parse("f() { a; } 2>err").unwrap();
+        let Command::Function { name, body } = &list.items[0].and_or.first.commands[0] else {
+            panic!("not a function definition: {:?}", list);
+        };
+        assert_eq!(name, "f");
+        assert!(
+            matches!(**body, Command::Compound(CompoundCommand::BraceGroup(_), ref r) if r.len() == 1)
+        );
+    }
+
+    #[test]
+    fn test_syntax_errors() {
+        for (source, token) in [
+            ("| a", "|"),
+            ("a ;; b", ";;"),
+            ("a && || b", "||"),
+            ("echo >", "newline"),
+            ("echo > | x", "|"),
+            ("a )", ")"),
+            ("}", "}"),
+            ("{ }", "}"),
+            ("( )", ")"),
+            ("{ a; } b", "b"),
+            ("if a; fi", "fi"),
+            ("if then b; fi", "then"),
+            ("while a; done", "done"),
+            ("for 1x in a; do b; done", "1x"),
+            ("for x in a b do; done", "done"),
+            ("case a in b c) ;; esac", "c"),
+            ("case a in b) c;; d; esac", ";"),
+            ("done", "done"),
+            ("f() echo a", "echo"),
+            ("f() }", "}"),
+            ("function 'f' { a; }", "'f'"),
+            ("function f ( { a; }", "{"),
+        ] {
+            assert!(
+                matches!(syntax_error(source), ParseErrorKind::UnexpectedToken(ref t) if t == token),
+                "{}: {:?}",
+                source,
+                syntax_error(source)
+            );
+        }
+
+        for source in ["a |", "a &&", "{ a", "{ a }", "( a", "!"] {
+            assert!(
+                matches!(syntax_error(source), ParseErrorKind::UnexpectedEof),
+                "{}: {:?}",
+                source,
+                syntax_error(source)
+            );
+        }
+    }
+
+    #[test]
+    fn test_incomplete() {
+        for source in [
+            "a |",
+            "a ||\n",
+            "{ a;",
+            "echo 'a",
+            "echo \"a\nb",
+            "echo a\\",
+            "cat <<EOF\nx",
+            "if a; then",
+            "if a; then b; else",
+            "while a\ndo b",
+            "for x in a b",
+            "case a in\nb) c;;",
+            "f() {",
+            "function f",
+        ] {
+            assert!(parse(source).unwrap_err().is_incomplete(), "{:?}", source);
+        }
+        for source in ["echo >", "a )", "| a"] {
+            assert!(!parse(source).unwrap_err().is_incomplete(), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_here_document_bodies() {
+        let list = parse("cat <<A | cat <<B\na\nA\nb\nB").unwrap();
+        let bodies: Vec<&str> = list.items[0]
+            .and_or
+            .first
+            .commands
+            .iter()
+            .map(|command| match command {
+                Command::Simple(simple) => match &simple.redirections[0].kind {
+                    RedirectionKind::HereDocument(here_document) => here_document.body.as_str(),
+                    kind => panic!("not a here-document: {:?}", kind),
+                },
+                command => panic!("not a simple command: {:?}", command),
+            })
+            .collect();
+        assert_eq!(bodies, ["a\n", "b\n"]);
+    }
+}
@@ -0,0 +1,776 @@
+use std::collections::VecDeque;
+
+use super::{HereDocument, ParseError, ParseErrorKind, Span, Word, WordPart};
+
+/// A single token of the shell input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(Word),
+    Operator(Operator, Span),
+}
+
+/// The operators that separate words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operator {
+    /// The `|` pipeline operator.
+    Pipe,
+    /// The `||` operator, runs the next pipeline only if the previous one failed.
+    Or,
+    /// The `&&` operator, runs the next pipeline only if the previous one succeeded.
+    And,
+    /// The `;` command separator.
+    Semicolon,
+    /// The `;;` terminator of a case item.
+    DoubleSemicolon,
+    /// The `&` operator, runs the previous and-or list in the background.
+    Background,
+    LeftParen,
+    RightParen,
+    Newline,
+    /// A redirection operator with an optional file descriptor (e.g. `<`, `2>` or `<<-`).
+    Redirect(String),
+}
+
+impl Token {
+    pub fn span(&self) -> Span {
+        match self {
+            Token::Word(word) => word.span,
+            Token::Operator(_, span) => *span,
+        }
+    }
+
+    /// Returns the source text of the token, as shown in syntax errors.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Token::Word(word) => &word.raw,
+            Token::Operator(operator, _) => match operator {
+                Operator::Pipe => "|",
+                Operator::Or => "||",
+                Operator::And => "&&",
+                Operator::Semicolon => ";",
+                Operator::DoubleSemicolon => ";;",
+                Operator::Background => "&",
+                Operator::LeftParen => "(",
+                Operator::RightParen => ")",
+                Operator::Newline => "newline",
+                Operator::Redirect(operator) => operator,
+            },
+        }
+    }
+}
+
+/// Splits the source into tokens.
+/// Blanks, comments and line continuations (`\` followed by a newline) are skipped.
+/// Also returns the here-documents in source order, with the bodies read from the lines
+/// that follow their command.
+pub fn tokenize(source: &str) -> Result<(Vec<Token>, VecDeque<HereDocument>), ParseError> {
+    let mut lexer = Lexer::new(source);
+    let mut tokens = Vec::new();
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+    }
+    Ok((tokens, lexer.here_documents))
+}
+
+/// Returns the byte offset of the `)` that closes a command substitution,
+/// where `source` starts with the command after `$(`.
+/// Returns `None` if the substitution is not closed or its command can't be tokenized.
+pub fn command_substitution_end(source: &str) -> Option<usize> {
+    let mut lexer = Lexer::new(source);
+    lexer.skip_to_closing_paren().ok()?.then(|| lexer.pos - 1)
+}
+
+/// Reads the whole source as a single word, blanks and operators are part of it.
+pub fn whole_word(source: &str) -> Result<Word, ParseError> {
+    let mut lexer = Lexer::new(source);
+    lexer.whole_word = true;
+    lexer.read_word()
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    /// The byte offset of the next character.
+    pos: usize,
+    /// Whether blanks and operators are part of a word instead of ending it.
+    whole_word: bool,
+    /// Set right after `<<` or `<<-` (with whether tabs are stripped),
+    /// the next word is the delimiter of a here-document.
+    here_document_operator: Option<bool>,
+    /// Here-documents whose bodies start after the next newline.
+    pending_here_documents: Vec<HereDocument>,
+    here_documents: VecDeque<HereDocument>,
+}
+
+/// Where a `case` command skipped inside a command substitution is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaseState {
+    /// Before the word that is matched.
+    Word,
+    /// Before `in`.
+    In,
+    /// In the patterns of an item, a `)` ends them.
+    Patterns,
+    /// In the commands of an item, `;;` starts the patterns of the next one.
+    Body,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            pos: 0,
+            whole_word: false,
+            here_document_operator: None,
+            pending_here_documents: Vec::new(),
+            here_documents: VecDeque::new(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.source[self.pos..].chars().nth(1)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            return true;
+        }
+        false
+    }
+
+    /// Returns an error for a quote or substitution opened at `start` that is never closed.
+    fn unterminated(&self, start: usize, closing: char) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::Unterminated(closing),
+            span: Span::new(start, self.source.len()),
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        self.skip_blanks();
+
+        let start = self.pos;
+        let here_document_operator = self.here_document_operator.take();
+        let Some(c) = self.peek() else {
+            if let Some(here_document) = self.pending_here_documents.first() {
+                return Err(self.unterminated_here_document(here_document));
+            }
+            return Ok(None);
+        };
+
+        let operator = match c {
+            '\n' => {
+                self.bump();
+                self.read_here_document_bodies()?;
+                Operator::Newline
+            }
+            '|' | ';' | '(' | ')' => {
+                self.bump();
+                match c {
+                    '|' if self.eat('|') => Operator::Or,
+                    '|' => Operator::Pipe,
+                    ';' if self.eat(';') => Operator::DoubleSemicolon,
+                    ';' => Operator::Semicolon,
+                    '(' => Operator::LeftParen,
+                    _ => Operator::RightParen,
+                }
+            }
+            '&' => {
+                self.bump();
+                if self.eat('&') {
+                    Operator::And
+                } else if self.peek() == Some('>') {
+                    self.redirect_operator("&".to_string())
+                } else {
+                    Operator::Background
+                }
+            }
+            '<' | '>' => self.redirect_operator(String::new()),
+            _ => {
+                let word = self.read_word()?;
+                // a number right before a redirection operator is the redirected file descriptor.
+                let is_fd = word
+                    .as_literal()
+                    .is_some_and(|text| text.chars().all(|c| c.is_ascii_digit()));
+                if !(is_fd && matches!(self.peek(), Some('<' | '>'))) {
+                    if let Some(strip_tabs) = here_document_operator {
+                        self.pending_here_documents
+                            .push(HereDocument::new(word.clone(), strip_tabs));
+                    }
+                    return Ok(Some(Token::Word(word)));
+                }
+                self.redirect_operator(word.raw)
+            }
+        };
+
+        if let Operator::Redirect(operator) = &operator {
+            let without_fd = operator.trim_start_matches(|c: char| c.is_ascii_digit());
+            if without_fd == "<<" || without_fd == "<<-" {
+                self.here_document_operator = Some(without_fd == "<<-");
+            }
+        }
+
+        Ok(Some(Token::Operator(operator, Span::new(start, self.pos))))
+    }
+
+    /// Reads the bodies of the pending here-documents, one after another, from the following lines.
+    fn read_here_document_bodies(&mut self) -> Result<(), ParseError> {
+        for mut here_document in std::mem::take(&mut self.pending_here_documents) {
+            loop {
+                if self.pos == self.source.len() {
+                    return Err(self.unterminated_here_document(&here_document));
+                }
+
+                let end = self.source[self.pos..]
+                    .find('\n')
+                    .map_or(self.source.len(), |i| self.pos + i);
+                let line = &self.source[self.pos..end];
+                self.pos = (end + 1).min(self.source.len());
+
+                if here_document.push_line(line) {
+                    break;
+                }
+            }
+            self.here_documents.push_back(here_document);
+        }
+        Ok(())
+    }
+
+    fn unterminated_here_document(&self, here_document: &HereDocument) -> ParseError {
+        ParseError {
+            kind: ParseErrorKind::UnterminatedHereDocument(here_document.delimiter().to_string()),
+            span: Span::new(self.pos, self.source.len()),
+        }
+    }
+
+    /// Skips blanks, line continuations and a comment up to the end of the line.
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.peek() {
+                Some(' ' | '\t') => {
+                    self.bump();
+                }
+                Some('\\') if self.peek_second() == Some('\n') => {
+                    self.pos += 2;
+                }
+                Some('#') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Reads a redirection operator starting at `<` or `>`.
+    /// `prefix` is the file descriptor or `&` that came right before it.
+    fn redirect_operator(&mut self, prefix: String) -> Operator {
+        let mut operator = prefix;
+        let c = self.bump().unwrap();
+        operator.push(c);
+
+        if c == '<' && self.eat('<') {
+            operator.push('<');
+            if let Some(next @ ('<' | '-')) = self.peek() {
+                self.bump();
+                operator.push(next);
+            }
+        } else if c == '>' && self.eat('>') {
+            operator.push('>');
+        } else if self.eat('&') {
+            operator.push('&');
+        }
+        Operator::Redirect(operator)
+    }
+
+    /// Reads a word up to the next unquoted blank or operator.
+    fn read_word(&mut self) -> Result<Word, ParseError> {
+        let start = self.pos;
+        let mut raw = String::new();
+        let mut parts = Vec::new();
+
+        while let Some(c) = self.peek() {
+            match c {
+                ' ' | '\t' | '\n' | '|' | '&' | ';' | '<' | '>' | '(' | ')' if !self.whole_word => {
+                    break
+                }
+                '\\' => {
+                    self.bump();
+                    match self.bump() {
+                        Some('\n') => {}
+                        Some(escaped) => {
+                            raw.push('\\');
+                            raw.push(escaped);
+                            parts.push(WordPart::Escaped(escaped));
+                        }
+                        None => {
+                            return Err(ParseError {
+                                kind: ParseErrorKind::UnexpectedEof,
+                                span: Span::new(start, self.pos),
+                            })
+                        }
+                    }
+                }
+                '\'' => {
+                    let quote_start = self.pos;
+                    self.bump();
+                    let text_start = self.pos;
+                    while self.peek().is_some_and(|c| c != '\'') {
+                        self.bump();
+                    }
+                    let text = self.source[text_start..self.pos].to_string();
+                    if !self.eat('\'') {
+                        return Err(self.unterminated(quote_start, '\''));
+                    }
+                    raw.push_str(&self.source[quote_start..self.pos]);
+                    parts.push(WordPart::SingleQuoted(text));
+                }
+                '"' => parts.push(self.read_double_quoted(&mut raw)?),
+                '$' | '`' => match self.read_expansion(&mut raw)? {
+                    Some(part) => parts.push(part),
+                    None => push_literal(&mut parts, '$'),
+                },
+                _ => {
+                    self.bump();
+                    raw.push(c);
+                    push_literal(&mut parts, c);
+                }
+            }
+        }
+
+        Ok(Word {
+            raw,
+            parts,
+            span: Span::new(start, self.pos),
+        })
+    }
+
+    /// Reads a double-quoted string, inside it a backslash only escapes `$`, `` ` ``, `"` and `\`.
+    fn read_double_quoted(&mut self, raw: &mut String) -> Result<WordPart, ParseError> {
+        let start = self.pos;
+        self.bump();
+        raw.push('"');
+        let mut parts = Vec::new();
+
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.bump();
+                    raw.push('"');
+                    return Ok(WordPart::DoubleQuoted(parts));
+                }
+                Some('\\') => {
+                    self.bump();
+                    match self.peek() {
+                        Some('\n') => {
+                            self.bump();
+                        }
+                        Some(escaped @ ('$' | '`' | '"' | '\\')) => {
+                            self.bump();
+                            raw.push('\\');
+                            raw.push(escaped);
+                            parts.push(WordPart::Escaped(escaped));
+                        }
+                        _ => {
+                            raw.push('\\');
+                            push_literal(&mut parts, '\\');
+                        }
+                    }
+                }
+                Some('$' | '`') => match self.read_expansion(raw)? {
+                    Some(part) => parts.push(part),
+                    None => push_literal(&mut parts, '$'),
+                },
+                Some(c) => {
+                    self.bump();
+                    raw.push(c);
+                    push_literal(&mut parts, c);
+                }
+                None => return Err(self.unterminated(start, '"')),
+            }
+        }
+    }
+
+    /// Reads a parameter expansion or a command substitution starting at `$` or `` ` ``.
+    /// Returns `None` for a `$` that starts neither, it is literal text.
+    fn read_expansion(&mut self, raw: &mut String) -> Result<Option<WordPart>, ParseError> {
+        let start = self.pos;
+        let part = match self.bump() {
+            Some('`') => {
+                loop {
+                    match self.bump() {
+                        Some('`') => break,
+                        Some('\\') => {
+                            self.bump();
+                        }
+                        Some(_) => {}
+                        None => return Err(self.unterminated(start, '`')),
+                    }
+                }
+                WordPart::CommandSubstitution(self.source[start..self.pos].to_string())
+            }
+            _ => match self.peek() {
+                Some('{') => {
+                    self.bump();
+                    self.skip_braced_parameter(start)?;
+                    WordPart::Parameter(self.source[start..self.pos].to_string())
+                }
+                Some('(') => {
+                    self.bump();
+                    self.skip_command_substitution(start)?;
+                    WordPart::CommandSubstitution(self.source[start..self.pos].to_string())
+                }
+                Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+                    while self
+                        .peek()
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        self.bump();
+                    }
+                    WordPart::Parameter(self.source[start..self.pos].to_string())
+                }
+                Some(c) if c.is_ascii_digit() || "?$!#@*-".contains(c) => {
+                    self.bump();
+                    WordPart::Parameter(self.source[start..self.pos].to_string())
+                }
+                _ => {
+                    raw.push('$');
+                    return Ok(None);
+                }
+            },
+        };
+
+        raw.push_str(&self.source[start..self.pos]);
+        Ok(Some(part))
+    }
+
+    /// Skips the body of a `${` opened at `start` up to the matching `}`.
+    fn skip_braced_parameter(&mut self, start: usize) -> Result<(), ParseError> {
+        let mut depth = 1;
+        let mut quote = None;
+
+        while depth > 0 {
+            match self.bump() {
+                Some('\\') if quote != Some('\'') => {
+                    self.bump();
+                }
+                Some(c @ ('\'' | '"')) if quote.is_none() => quote = Some(c),
+                Some(c) if quote == Some(c) => quote = None,
+                Some('$') if quote.is_none() && self.peek() == Some('{') => {
+                    self.bump();
+                    depth += 1;
+                }
+                Some('}') if quote.is_none() => depth -= 1,
+                Some(_) => {}
+                None => return Err(self.unterminated(start, '}')),
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips the command of a `$(` opened at `start` up to the matching `)`.
+    /// The command is scanned by its own lexer, so the here-documents it starts
+    /// are not taken for the ones of the enclosing command.
+    fn skip_command_substitution(&mut self, start: usize) -> Result<(), ParseError> {
+        let offset = self.pos;
+        let mut lexer = Lexer::new(&self.source[offset..]);
+        let closed = lexer.skip_to_closing_paren().map_err(|err| ParseError {
+            span: Span::new(err.span.start + offset, err.span.end + offset),
+            ..err
+        })?;
+        self.pos += lexer.pos;
+
+        if !closed {
+            return Err(self.unterminated(start, ')'));
+        }
+        Ok(())
+    }
+
+    /// Skips tokens up to the `)` that closes the command, returns `false` if the source ends first.
+    /// The command is tokenized, so quoted and escaped parentheses are skipped.
+    /// `case` commands are followed, since the `)` after their patterns doesn't close anything.
+    fn skip_to_closing_paren(&mut self) -> Result<bool, ParseError> {
+        let mut depth = 0;
+        let mut cases: Vec<CaseState> = Vec::new();
+        // whether the next word is in command position, where `case` and `esac` are reserved words.
+        let mut command_start = true;
+
+        loop {
+            let Some(token) = self.next_token()? else {
+                return Ok(false);
+            };
+            let in_patterns = cases.last() == Some(&CaseState::Patterns);
+
+            match token {
+                Token::Word(word) => {
+                    let word = word.raw.as_str();
+                    match cases.last_mut() {
+                        Some(state @ CaseState::Word) => *state = CaseState::In,
+                        Some(state @ CaseState::In) if word == "in" => {
+                            *state = CaseState::Patterns;
+                        }
+                        Some(CaseState::Patterns) if word == "esac" => {
+                            cases.pop();
+                        }
+                        Some(CaseState::Body) if command_start && word == "esac" => {
+                            cases.pop();
+                        }
+                        Some(CaseState::Patterns) => {}
+                        _ if command_start && word == "case" => cases.push(CaseState::Word),
+                        _ => {}
+                    }
+                    command_start = matches!(
+                        word,
+                        "if" | "then" | "else" | "elif" | "while" | "until" | "do" | "{" | "!"
+                    );
+                }
+                // the patterns of an item may start with `(`.
+                Token::Operator(Operator::LeftParen, _) if in_patterns => {}
+                Token::Operator(Operator::RightParen, _) if in_patterns => {
+                    if let Some(state) = cases.last_mut() {
+                        *state = CaseState::Body;
+                    }
+                    command_start = true;
+                }
+                Token::Operator(Operator::DoubleSemicolon, _) => {
+                    if let Some(state @ CaseState::Body) = cases.last_mut() {
+                        *state = CaseState::Patterns;
+                    }
+                    command_start = true;
+                }
+                Token::Operator(Operator::LeftParen, _) => {
+                    depth += 1;
+                    command_start = true;
+                }
+                Token::Operator(Operator::RightParen, _) if depth == 0 => return Ok(true),
+                Token::Operator(Operator::RightParen, _) => {
+                    depth -= 1;
+                    command_start = false;
+                }
+                Token::Operator(Operator::Redirect(_), _) => {}
+                Token::Operator(..) => command_start = true,
+            }
+        }
+    }
+}
+
+/// Appends a character to the literal text at the end of `parts`.
+fn push_literal(parts: &mut Vec<WordPart>, c: char) {
+    match parts.last_mut() {
+        Some(WordPart::Literal(text)) => text.push(c),
+        _ => parts.push(WordPart::Literal(c.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_strings(source: &str) -> Vec<String> {
+        tokenize(source)
+            .unwrap()
+            .0
+            .iter()
+            .map(|token| token.as_str().to_string())
+            .collect()
+    }
+
+    fn only_word(source: &str) -> Word {
+        match tokenize(source).unwrap().0.as_slice() {
+            [Token::Word(word)] => word.clone(),
+            tokens => panic!("not a single word: {:?}", tokens),
+        }
+    }
+
+    #[test]
+    fn test_operators() {
+        assert_eq!(
+            token_strings("a|b||c&&d;e&f\n(g)"),
+            ["a", "|", "b", "||", "c", "&&", "d", ";", "e", "&", "f", "newline", "(", "g", ")"]
+        );
+        assert_eq!(token_strings("a;;b; ;c"), ["a", ";;", "b", ";", ";", "c"]);
+        assert_eq!(
+            token_strings("cmd 2>&1 <<-EOF &>>all 3<in x2>y\nEOF"),
+            [
+                "cmd", "2>&", "1", "<<-", "EOF", "&>>", "all", "3<", "in", "x2", ">", "y",
+                "newline"
+            ]
+        );
+        assert_eq!(
+            token_strings("cat <<<word '2'>x"),
+            ["cat", "<<<", "word", "'2'", ">", "x"]
+        );
+    }
+
+    #[test]
+    fn test_blanks_and_comments() {
+        assert_eq!(token_strings("  a\tb # c d\ne"), ["a", "b", "newline", "e"]);
+        assert_eq!(token_strings("a#b \\\n c"), ["a#b", "c"]);
+        assert_eq!(token_strings("ec\\\nho"), ["echo"]);
+        assert!(token_strings("# only a comment").is_empty());
+    }
+
+    #[test]
+    fn test_word_parts() {
+        let word = only_word("a'b c'\"x $y\\$\"\\d");
+        assert_eq!(word.raw, "a'b c'\"x $y\\$\"\\d");
+        assert_eq!(
+            word.parts,
+            [
+                WordPart::Literal("a".to_string()),
+                WordPart::SingleQuoted("b c".to_string()),
+                WordPart::DoubleQuoted(vec![
+                    WordPart::Literal("x ".to_string()),
+                    WordPart::Parameter("$y".to_string()),
+                    WordPart::Escaped('$'),
+                ]),
+                WordPart::Escaped('d'),
+            ]
+        );
+        assert_eq!(word.span, Span::new(0, word.raw.len()));
+
+        assert_eq!(
+            only_word("${x:-a b}$(echo \")\" (x))`echo`$").parts,
+            [
+                WordPart::Parameter("${x:-a b}".to_string()),
+                WordPart::CommandSubstitution("$(echo \")\" (x))".to_string()),
+                WordPart::CommandSubstitution("`echo`".to_string()),
+                WordPart::Literal("$".to_string()),
+            ]
+        );
+        assert_eq!(only_word("a$").as_literal(), Some("a$"));
+        assert_eq!(
+            only_word("$?x").parts[0],
+            WordPart::Parameter("$?".to_string())
+        );
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let word = whole_word("a b|'c d'>$(e f)").unwrap();
+        assert_eq!(
+            word.parts,
+            [
+                WordPart::Literal("a b|".to_string()),
+                WordPart::SingleQuoted("c d".to_string()),
+                WordPart::Literal(">".to_string()),
+                WordPart::CommandSubstitution("$(e f)".to_string()),
+            ]
+        );
+        assert!(whole_word("'a").is_err());
+    }
+
+    #[test]
+    fn test_assignment() {
+        let word = only_word("a=b'c'$d");
+        let (name, value) = word.assignment().unwrap();
+        assert_eq!(name, "a");
+        assert_eq!(
+            value,
+            [
+                WordPart::Literal("b".to_string()),
+                WordPart::SingleQuoted("c".to_string()),
+                WordPart::Parameter("$d".to_string()),
+            ]
+        );
+        assert_eq!(only_word("a=").assignment(), Some(("a", vec![])));
+        assert_eq!(only_word("'a'=b").assignment(), None);
+        assert_eq!(only_word("1=b").assignment(), None);
+    }
+
+    #[test]
+    fn test_case_in_command_substitution() {
+        for source in [
+            "$(case a in a) echo yes;; esac)",
+            "$(case a in (a|b) echo x;; *) (echo y);; esac)",
+            "$(case a in\n a)\n  case b in b) echo z;; esac\n;; esac)",
+            "$(if x; then case a in a) y;; esac; fi)",
+            "$(case a in a) esac)",
+        ] {
+            assert_eq!(only_word(source).raw, source);
+            assert_eq!(
+                command_substitution_end(&source[2..]),
+                Some(source.len() - 3)
+            );
+        }
+
+        // `case` as an argument is not a reserved word.
+        assert_eq!(
+            token_strings("$(echo case a in a) b"),
+            ["$(echo case a in a)", "b"]
+        );
+        assert_eq!(command_substitution_end("echo esac) x"), Some(9));
+        assert_eq!(command_substitution_end("case a in a) x"), None);
+    }
+
+    #[test]
+    fn test_here_documents() {
+        let source = "cat <<EOF; cat <<-'END'\nhello $x\nEOF\n\tbye\n\tEND\necho done";
+        let (tokens, here_documents) = tokenize(source).unwrap();
+        let tokens: Vec<&str> = tokens.iter().map(Token::as_str).collect();
+        assert_eq!(
+            tokens,
+            ["cat", "<<", "EOF", ";", "cat", "<<-", "'END'", "newline", "echo", "done"]
+        );
+
+        assert_eq!(here_documents.len(), 2);
+        assert_eq!(here_documents[0].body, "hello $x\n");
+        assert!(here_documents[0].expand);
+        assert_eq!(here_documents[1].body, "bye\n");
+        assert!(!here_documents[1].expand);
+
+        // a here-document in a command substitution has its body inside the substitution.
+        let source = "x=$(cat <<EOF\ninner\nEOF\n); cat <<X\nouter\nX\n";
+        let (tokens, here_documents) = tokenize(source).unwrap();
+        assert_eq!(tokens[0].as_str(), "x=$(cat <<EOF\ninner\nEOF\n)");
+        assert_eq!(here_documents.len(), 1);
+        assert_eq!(here_documents[0].body, "outer\n");
+
+        for source in ["cat <<EOF", "cat <<EOF\n", "cat <<EOF\nbody\nEOF2"] {
+            assert!(
+                matches!(
+                    tokenize(source).unwrap_err().kind,
+                    ParseErrorKind::UnterminatedHereDocument(ref delimiter) if delimiter == "EOF"
+                ),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_unterminated() {
+        for (source, closing) in [
+            ("echo 'abc", '\''),
+            ("echo \"abc", '"'),
+            ("echo \"$(x", ')'),
+            ("echo $(echo", ')'),
+            ("echo `echo", '`'),
+            ("echo ${x", '}'),
+        ] {
+            let err = tokenize(source).unwrap_err();
+            assert!(
+                matches!(err.kind, ParseErrorKind::Unterminated(c) if c == closing),
+                "{}: {:?}",
+                source,
+                err
+            );
+            assert_eq!(err.span.end, source.len());
+        }
+        assert!(matches!(
+            tokenize("echo a\\").unwrap_err().kind,
+            ParseErrorKind::UnexpectedEof
+        ));
+    }
+}
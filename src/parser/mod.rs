@@ -0,0 +1,25 @@
+mod ast;
+mod error;
+mod grammar;
+mod lexer;
+mod redirection;
+
+pub use ast::{
+    AndOr, CaseItem, Command, CommandList, CompoundCommand, Connector, ListItem, Pipeline,
+    SimpleCommand, Span, Word, WordPart,
+};
+pub use error::{ParseError, ParseErrorKind};
+pub use lexer::command_substitution_end;
+pub use redirection::{HereDocument, Redirection, RedirectionKind};
+
+/// Parses the shell input into a command list.
+pub fn parse(source: &str) -> Result<CommandList, ParseError> {
+    let (tokens, here_documents) = lexer::tokenize(source)?;
+    grammar::Parser::new(tokens, here_documents, source.len()).parse_program()
+}
+
+/// Parses the whole source as a single word, like the word of a `${name:-word}` expansion.
+/// Blanks and operators are part of the word.
+pub fn parse_word(source: &str) -> Result<Word, ParseError> {
+    lexer::whole_word(source)
+}
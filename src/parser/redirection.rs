@@ -0,0 +1,254 @@
+use std::{fmt, os::fd::RawFd};
+
+use super::Word;
+
+/// A redirection of one of the standard streams of a command.
+#[derive(Debug, Clone)]
+pub struct Redirection {
+    /// The redirected file descriptor, ignored for `&>` and `&>>`.
+    pub fd: RawFd,
+    pub kind: RedirectionKind,
+}
+
+/// What a stream is redirected to. Words are expanded when the command runs.
+#[derive(Debug, Clone)]
+pub enum RedirectionKind {
+    /// `< word`, reads from a file.
+    Input(Word),
+    /// `> word`, writes to a file.
+    Output(Word),
+    /// `>> word`, appends to a file.
+    Append(Word),
+    /// `&> word`, writes both stdout and stderr to a file.
+    OutputAll(Word),
+    /// `&>> word`, appends both stdout and stderr to a file.
+    AppendAll(Word),
+    /// `<& word`, duplicates the file descriptor `word`, or closes the stream if it is `-`.
+    DuplicateInput(Word),
+    /// `>& word`, duplicates the file descriptor `word`, or closes the stream if it is `-`.
+    DuplicateOutput(Word),
+    /// `<<delimiter`, reads the lines that follow the command.
+    HereDocument(HereDocument),
+    /// `<<< word`, reads the expanded word followed by a newline.
+    HereString(Word),
+}
+
+/// A here-document, its body is read after the whole command line is parsed.
+#[derive(Debug, Clone)]
+pub struct HereDocument {
+    /// The delimiter as written, with quotes.
+    word: Word,
+    /// The line that ends the body.
+    delimiter: String,
+    /// Whether parameters in the body are expanded, which is the case if the delimiter is unquoted.
+    pub expand: bool,
+    /// Whether leading tabs are removed from the lines (`<<-`).
+    strip_tabs: bool,
+    /// The lines of the body, each ending with a newline.
+    pub body: String,
+}
+
+impl HereDocument {
+    pub fn new(word: Word, strip_tabs: bool) -> Self {
+        Self {
+            delimiter: word.unquoted(),
+            expand: !word.is_quoted(),
+            word,
+            strip_tabs,
+            body: String::new(),
+        }
+    }
+
+    /// Returns the delimiter that ends the body.
+    pub fn delimiter(&self) -> &str {
+        &self.delimiter
+    }
+
+    /// Adds a line to the body. Returns `true` if the line is the delimiter, which ends the body.
+    pub fn push_line(&mut self, line: &str) -> bool {
+        let line = if self.strip_tabs {
+            line.trim_start_matches('\t')
+        } else {
+            line
+        };
+
+        if line == self.delimiter {
+            return true;
+        }
+        self.body.push_str(line);
+        self.body.push('\n');
+        false
+    }
+}
+
+impl Redirection {
+    /// Creates a redirection from an operator like `<`, `2>` or `<<-` and the word after it.
+    /// Returns `None` if the operator is not a valid redirection operator.
+    pub fn parse(operator: &str, word: Word) -> Option<Self> {
+        let digits = operator.len()
+            - operator
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .len();
+        let (fd, operator) = operator.split_at(digits);
+        let fd = if fd.is_empty() {
+            None
+        } else {
+            Some(fd.parse().ok()?)
+        };
+
+        let (default_fd, kind) = match operator {
+            "<" => (0, RedirectionKind::Input(word)),
+            ">" => (1, RedirectionKind::Output(word)),
+            ">>" => (1, RedirectionKind::Append(word)),
+            "&>" if fd.is_none() => (1, RedirectionKind::OutputAll(word)),
+            "&>>" if fd.is_none() => (1, RedirectionKind::AppendAll(word)),
+            "<&" => (0, RedirectionKind::DuplicateInput(word)),
+            ">&" => (1, RedirectionKind::DuplicateOutput(word)),
+            "<<" => (
+                0,
+                RedirectionKind::HereDocument(HereDocument::new(word, false)),
+            ),
+            "<<-" => (
+                0,
+                RedirectionKind::HereDocument(HereDocument::new(word, true)),
+            ),
+            "<<<" => (0, RedirectionKind::HereString(word)),
+            _ => return None,
+        };
+
+        Some(Self {
+            fd: fd.unwrap_or(default_fd),
+            kind,
+        })
+    }
+}
+
+impl fmt::Display for Redirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (default_fd, operator, word) = match &self.kind {
+            RedirectionKind::Input(word) => (0, "<", word),
+            RedirectionKind::Output(word) => (1, ">", word),
+            RedirectionKind::Append(word) => (1, ">>", word),
+            RedirectionKind::OutputAll(word) => (self.fd, "&>", word),
+            RedirectionKind::AppendAll(word) => (self.fd, "&>>", word),
+            RedirectionKind::DuplicateInput(word) => (0, "<&", word),
+            RedirectionKind::DuplicateOutput(word) => (1, ">&", word),
+            RedirectionKind::HereDocument(doc) if doc.strip_tabs => (0, "<<-", &doc.word),
+            RedirectionKind::HereDocument(doc) => (0, "<<", &doc.word),
+            RedirectionKind::HereString(word) => (0, "<<< ", word),
+        };
+
+        if self.fd != default_fd {
+            write!(f, "{}", self.fd)?;
+        }
+        write!(f, "{}{}", operator, word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::lexer::{tokenize, Token};
+
+    fn word(raw: &str) -> Word {
+        match tokenize(raw).unwrap().0.remove(0) {
+            Token::Word(word) => word,
+            token => panic!("not a word: {:?}", token),
+        }
+    }
+
+    fn here_document(redirection: Redirection) -> HereDocument {
+        match redirection.kind {
+            RedirectionKind::HereDocument(doc) => doc,
+            kind => panic!("not a here-document: {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let redirection = Redirection::parse("<", word("in")).unwrap();
+        assert_eq!(redirection.fd, 0);
+        assert!(matches!(redirection.kind, RedirectionKind::Input(word) if word.raw == "in"));
+
+        let redirection = Redirection::parse("2>", word("err")).unwrap();
+        assert_eq!(redirection.fd, 2);
+        assert!(matches!(redirection.kind, RedirectionKind::Output(_)));
+
+        let redirection = Redirection::parse("2>&", word("1")).unwrap();
+        assert_eq!(redirection.fd, 2);
+        assert!(
+            matches!(redirection.kind, RedirectionKind::DuplicateOutput(word) if word.raw == "1")
+        );
+
+        let redirection = Redirection::parse("3<&", word("0")).unwrap();
+        assert_eq!(redirection.fd, 3);
+        assert!(
+            matches!(redirection.kind, RedirectionKind::DuplicateInput(word) if word.raw == "0")
+        );
+
+        let redirection = Redirection::parse(">&", word("-")).unwrap();
+        assert_eq!(redirection.fd, 1);
+        assert!(matches!(
+            redirection.kind,
+            RedirectionKind::DuplicateOutput(_)
+        ));
+
+        let redirection = Redirection::parse("3>>", word("log")).unwrap();
+        assert_eq!(redirection.fd, 3);
+        assert!(matches!(redirection.kind, RedirectionKind::Append(_)));
+
+        assert!(Redirection::parse("2&>", word("out")).is_none());
+        assert!(Redirection::parse("2&>>", word("out")).is_none());
+        assert!(Redirection::parse("99999999999>", word("out")).is_none());
+    }
+
+    #[test]
+    fn test_display() {
+        let display = |operator: &str, target: &str| {
+            Redirection::parse(operator, word(target))
+                .unwrap()
+                .to_string()
+        };
+
+        assert_eq!(display("<", "in"), "<in");
+        assert_eq!(display("1>", "out"), ">out");
+        assert_eq!(display("2>", "err"), "2>err");
+        assert_eq!(display("&>>", "all"), "&>>all");
+        assert_eq!(display("2>&", "1"), "2>&1");
+        assert_eq!(display(">&", "-"), ">&-");
+        assert_eq!(display("<&", "3"), "<&3");
+        assert_eq!(display("3<&", "0"), "3<&0");
+        assert_eq!(display("0>&", "1"), "0>&1");
+        assert_eq!(display("3>", "file"), "3>file");
+        assert_eq!(display(">>", "log"), ">>log");
+        assert_eq!(display("<<-", "'EOF'"), "<<-'EOF'");
+        assert_eq!(display("<<<", "\"$x\""), "<<< \"$x\"");
+    }
+
+    #[test]
+    fn test_here_document_delimiter() {
+        let doc = here_document(Redirection::parse("<<", word("EOF")).unwrap());
+        assert_eq!(doc.delimiter(), "EOF");
+        assert!(doc.expand);
+
+        for raw in ["'EOF'", "\"EOF\"", "\\EOF", "E\"O\"F"] {
+            let doc = here_document(Redirection::parse("<<", word(raw)).unwrap());
+            assert_eq!(doc.delimiter(), "EOF");
+            assert!(!doc.expand);
+        }
+    }
+
+    #[test]
+    fn test_here_document_body() {
+        let mut doc = here_document(Redirection::parse("<<-", word("EOF")).unwrap());
+        assert!(!doc.push_line("\tindented"));
+        assert!(!doc.push_line("  spaces"));
+        assert!(doc.push_line("\t\tEOF"));
+        assert_eq!(doc.body, "indented\n  spaces\n");
+
+        let mut doc = here_document(Redirection::parse("<<", word("EOF")).unwrap());
+        assert!(!doc.push_line("\tEOF"));
+        assert!(doc.push_line("EOF"));
+        assert_eq!(doc.body, "\tEOF\n");
+    }
+}